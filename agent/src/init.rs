@@ -6,6 +6,7 @@ pub(super) fn init(ctx: &Context) {
     init_limit(&ctx);
     init_log(&ctx);
     init_local_ip(&ctx);
+    init_tls(ctx);
     start_metrics_register_task(ctx);
     #[cfg(feature = "console-api")]
     crate::console::start_console(ctx);
@@ -63,6 +64,12 @@ pub(crate) fn init_log(ctx: &Context) {
 pub(crate) fn init_local_ip(ctx: &Context) {
     metrics::init_local_ip(&ctx.metrics_probe);
 }
+// 证书配置错误时不影响其他端口，tls端口监听失败，后端tls使用系统默认的CA
+pub(crate) fn init_tls(ctx: &Context) {
+    if let Err(_e) = net::tls::init(&ctx.tls_cert, &ctx.tls_key, &ctx.tls_ca) {
+        log::error!("tls init failed: {:?}", _e);
+    }
+}
 
 pub(crate) fn start_metrics_register_task(_ctx: &Context) {
     rt::spawn(metrics::MetricRegister::default());
@@ -9,7 +9,7 @@ use ds::chan::Sender;
 use metrics::Path;
 use protocol::{Parser, Result};
 use stream::pipeline::copy_bidirectional;
-use stream::{Backend, CheckedTopology, Request, StreamMetrics};
+use stream::{Backend, CheckedTopology, Limiter, Request, StreamMetrics};
 
 type Endpoint = Backend<Request>;
 type Topology = endpoint::TopologyProtocol<Endpoint, Parser>;
@@ -51,9 +51,11 @@ pub(super) async fn process_one(
     let switcher = ds::Switcher::from(true);
 
     let metrics = Arc::new(metrics);
+    // 同一个service的所有连接共享连接数及qps的限制
+    let limiter = Arc::new(Limiter::default());
 
     // 服务注册完成，侦听端口直到成功。
-    while let Err(_e) = _process_one(quard, &p, &rx, metrics.clone()).await {
+    while let Err(_e) = _process_one(quard, &p, &rx, metrics.clone(), limiter.clone()).await {
         // 监听失败或accept连接失败，对监听失败数+1
         unsafe { *metrics.listen_failed.as_mut() += Status::ERROR };
         log::warn!("service process failed. {}, err:{:?}", quard, _e);
@@ -71,6 +73,7 @@ async fn _process_one(
     p: &Parser,
     top: &TopologyReadGuard<Topology>,
     metrics: Arc<StreamMetrics>,
+    limiter: Arc<Limiter>,
 ) -> Result<()> {
     let l = Listener::bind(&quard.family(), &quard.address()).await?;
     log::info!("started. {}", quard);
@@ -84,8 +87,9 @@ async fn _process_one(
         log::debug!("connection established:{:?}", metrics.biz());
         let ctop = CheckedTopology::from(top.clone());
         let metrics = metrics.clone();
+        let limiter = limiter.clone();
         spawn(async move {
-            if let Err(e) = copy_bidirectional(ctop, metrics.clone(), client, p).await {
+            if let Err(e) = copy_bidirectional(ctop, metrics.clone(), limiter, client, p).await {
                 use protocol::Error::*;
                 match e {
                     // TODO Eof、IO需要日志？
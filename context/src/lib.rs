@@ -107,6 +107,17 @@ pub struct ContextOption {
     #[clap(long, help("region"), default_value(""))]
     pub region: String,
 
+    // tls监听使用的证书链及私钥(pem)，未配置时不能监听tls端口
+    #[clap(long, help("tls certificate chain path (pem)"), default_value(""))]
+    pub tls_cert: String,
+
+    #[clap(long, help("tls private key path (pkcs8 pem)"), default_value(""))]
+    pub tls_key: String,
+
+    // 校验后端证书的CA，未配置时使用系统默认的CA
+    #[clap(long, help("tls ca path (pem) for backends"), default_value(""))]
+    pub tls_ca: String,
+
     // api参数，目前只有这一个差异参数，先放这里
     #[clap(long, help("api whitelist host"), default_value("localhost"))]
     pub whitelist_host: String,
@@ -357,7 +368,11 @@ impl From<ContextOption> for Context {
         if version.as_bytes().last() == Some(&b'_') {
             version.pop();
         }
-        Self { version, option, envs }
+        Self {
+            version,
+            option,
+            envs,
+        }
     }
 }
 
@@ -379,7 +394,6 @@ impl Context {
     }
 }
 
-
 #[inline(always)]
 pub fn get() -> &'static Context {
     &CONTEXT
@@ -406,4 +420,4 @@ impl EnvOptions {
             region: std::env::var("CURRENT_CLUSTER").unwrap_or("".to_string()),
         }
     }
-}
\ No newline at end of file
+}
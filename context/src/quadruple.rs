@@ -28,6 +28,7 @@ impl Quadruple {
         let protocol_item = fields[1];
         // 第一个field是应用协议名称(mc, redis)；
         // 第二个元素如果没有，则是unix协议，如果有则是tcp协议，该值必须是端口
+        // 第三个元素为tls时，tcp端口使用tls，如 redis:56379:tls
         let protocol_fields: Vec<&str> = protocol_item.split(':').collect();
         let is_tcp = protocol_fields
             .get(1)
@@ -39,7 +40,10 @@ impl Quadruple {
             #[cfg(not(feature = "listen-all"))]
             let local_ip = "127.0.0.1";
             let addr = local_ip.to_string() + ":" + protocol_fields[1];
-            ("tcp", addr)
+            match protocol_fields.get(2) {
+                Some(&"tls") => ("tls", addr),
+                _ => ("tcp", addr),
+            }
         } else {
             (
                 "unix",
@@ -119,7 +123,7 @@ impl PartialEq for Quadruple {
 }
 impl Ord for Quadruple {
     fn cmp(&self, other: &Self) -> Ordering {
-        // unix < tcp
+        // unix < tls < tcp
         other
             .family
             .cmp(&self.family)
@@ -279,6 +279,19 @@ impl RingSlice {
         }
     }
 
+    // 与s比较是否相同，耗时只与s及self的长度相关，用于密码等的比较，避免时序攻击
+    #[inline]
+    pub fn ct_eq(&self, s: &[u8]) -> bool {
+        let diff = s
+            .iter()
+            .enumerate()
+            .fold(self.len() ^ s.len(), |diff, (i, b)| {
+                let c = if i < self.len() { self[i] } else { 0 };
+                diff | (c ^ b) as usize
+            });
+        diff == 0
+    }
+
     // 读取一个u16的数字，大端
     #[inline(always)]
     pub fn u16_be(&self, oft: usize) -> u16 {
@@ -0,0 +1,38 @@
+use protocol::BreakerOption;
+use serde::{Deserialize, Serialize};
+
+// namespace级别的后端熔断配置，未配置或为0的项使用默认值：
+// 10秒窗口内至少32个请求、错误占比达到50%时摘除，5秒后半开探测，耗时超过超时时间一半的请求计为慢请求。
+// 目前支持cacheservice、redisservice，其他资源使用默认值
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Hash)]
+pub struct BreakerConfig {
+    #[serde(default)]
+    pub window_ms: u32,
+    #[serde(default)]
+    pub min_reqs: u32,
+    // 0~100
+    #[serde(default)]
+    pub err_percent: u8,
+    #[serde(default)]
+    pub cooldown_ms: u32,
+    // 0~100
+    #[serde(default)]
+    pub slow_percent: u8,
+}
+
+impl BreakerConfig {
+    pub fn option(&self) -> BreakerOption {
+        let or = |v: u32, default: u32| match v {
+            0 => default,
+            v => v,
+        };
+        let d = BreakerOption::default();
+        BreakerOption {
+            window_ms: or(self.window_ms, d.window_ms),
+            min_reqs: or(self.min_reqs, d.min_reqs),
+            err_percent: or(self.err_percent.min(100) as u32, d.err_percent),
+            cooldown_ms: or(self.cooldown_ms, d.cooldown_ms),
+            slow_percent: or(self.slow_percent.min(100) as u32, d.slow_percent),
+        }
+    }
+}
@@ -9,7 +9,10 @@ pub struct Namespace {
     #[serde(default)]
     pub distribution: String, //eg: ketama
     #[serde(default)]
-    pub hash_tag: String, //eg: user
+    pub hash_tag: String, //eg: {}，只对key中{}之间的部分做hash
+    // 线上已有配置了hash_tag但未生效的业务，为避免key迁移，需显式开启后hash_tag才生效
+    #[serde(default)]
+    pub enable_hash_tag: bool,
     //pub timeout: i32,         // unit: mills
     pub exptime: i64,
     #[serde(default)]
@@ -38,6 +41,35 @@ pub struct Namespace {
     pub local_affinity: bool,
     #[serde(default)]
     pub flag: u64, // 通过bit位，设置不同的策略/属性，详见下面Flag定义
+    // client的最大连接数、最大qps，超过后拒绝连接或请求，为0时不限制
+    #[serde(default)]
+    pub max_conns: usize,
+    #[serde(default)]
+    pub max_qps: u32,
+    // big key/value的阈值，为0时不检测；超过阈值时默认只记录日志及metrics，reject_big为true时拒绝请求
+    #[serde(default)]
+    pub max_key_len: usize,
+    #[serde(default)]
+    pub max_value_len: usize,
+    #[serde(default)]
+    pub reject_big: bool,
+    // hot key的near cache最多缓存的key数，为0时不开启；ttl很短，ttl内可能读到旧数据
+    #[serde(default)]
+    pub near_cache_size: usize,
+    #[serde(default = "Namespace::default_near_cache_ttl_ms")]
+    pub near_cache_ttl_ms: u32,
+    // 镜像集群，迁移时复制线上流量
+    #[serde(default)]
+    pub mirror: crate::MirrorConfig,
+    // 重试策略，读请求最多访问两层，max_attempts只能减少访问次数
+    #[serde(default)]
+    pub retry: crate::RetryConfig,
+    // 与后端（包括镜像集群）的连接使用tls
+    #[serde(default)]
+    pub tls: bool,
+    // 后端实例的熔断阈值
+    #[serde(default)]
+    pub breaker: crate::BreakerConfig,
 }
 
 // 通过bit位，设置不同的策略/属性；从低位开始依次排列
@@ -107,6 +139,9 @@ impl Namespace {
     fn default_update_slave_l1() -> bool {
         return true;
     }
+    fn default_near_cache_ttl_ms() -> u32 {
+        1000
+    }
     // 确保master在第0个位置
     // 返回master + master_l1的数量作为local
     pub(super) fn take_backends(self) -> (usize, Vec<Vec<String>>) {
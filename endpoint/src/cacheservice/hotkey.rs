@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::*};
+use std::sync::Mutex;
+
+use ds::time::{Duration, Instant};
+use ds::RingSlice;
+use metrics::Path;
+use protocol::Resource::Memcache;
+
+// 每SAMPLE_RATE个读请求采样一次
+const SAMPLE_RATE: u32 = 64;
+// 每个窗口统计一次top-N
+const WINDOW: Duration = Duration::from_secs(10);
+const TOP_N: usize = 10;
+// 一个窗口内最多统计的key数，超过后新的key不再统计
+const MAX_KEYS: usize = 4096;
+// 窗口内的采样次数超过该值才认为是hot key，即qps约为 16 * 64 / 10 ≈ 100
+const MIN_SAMPLES: u32 = 16;
+// 日志中key的最大长度
+const MAX_NAME_LEN: usize = 64;
+
+thread_local! {
+    static REQS: Cell<u32> = const { Cell::new(0) };
+}
+
+// 采样统计读请求的key，每个窗口结束时，把采样次数最多的top-N作为hot key，
+// 按排名上报metrics，key记录到日志中。
+pub struct HotKeys {
+    window: Mutex<Window>,
+    // 统计窗口的时长
+    period: Duration,
+    // 上一个窗口的hot key的hash，判断时不加锁、不拷贝key。hash冲突时误判为hot，不影响正确性
+    hot: [AtomicU64; TOP_N],
+    hot_num: AtomicUsize,
+}
+
+struct Window {
+    start: Instant,
+    samples: HashMap<Vec<u8>, u32>,
+}
+
+impl Default for HotKeys {
+    fn default() -> Self {
+        Self::new(WINDOW)
+    }
+}
+
+impl HotKeys {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            window: Mutex::new(Window {
+                start: Instant::now(),
+                samples: HashMap::new(),
+            }),
+            period,
+            hot: Default::default(),
+            hot_num: AtomicUsize::new(0),
+        }
+    }
+    #[inline]
+    pub fn sample(&self, service: &str, key: &RingSlice) {
+        let sampled = REQS.with(|reqs| {
+            let n = reqs.get().wrapping_add(1);
+            reqs.set(n);
+            n % SAMPLE_RATE == 0
+        });
+        if sampled {
+            self.sample_inner(service, key);
+        }
+    }
+    #[inline]
+    pub fn is_hot(&self, key: &RingSlice) -> bool {
+        let num = self.hot_num.load(Acquire);
+        if num == 0 {
+            return false;
+        }
+        let hash = hash(key);
+        self.hot[..num].iter().any(|h| h.load(Relaxed) == hash)
+    }
+
+    #[cold]
+    fn sample_inner(&self, service: &str, key: &RingSlice) {
+        // 其他线程正在统计时，放弃本次采样
+        let Ok(mut w) = self.window.try_lock() else {
+            return;
+        };
+        let mut k = Vec::with_capacity(key.len());
+        key.copy_to_vec(&mut k);
+        let full = w.samples.len() >= MAX_KEYS;
+        match w.samples.get_mut(&k) {
+            Some(n) => *n += 1,
+            None if !full => {
+                w.samples.insert(k, 1);
+            }
+            None => {}
+        }
+        if w.start.elapsed() < self.period {
+            return;
+        }
+        let mut top: Vec<(Vec<u8>, u32)> = w.samples.drain().collect();
+        w.start = Instant::now();
+        drop(w);
+
+        top.sort_unstable_by_key(|(_, n)| std::cmp::Reverse(*n));
+        top.truncate(TOP_N);
+        top.retain(|(_, n)| *n >= MIN_SAMPLES);
+        // metrics按排名上报，key的数量不固定，只记录在日志中
+        let path = Path::new(vec![Memcache.name(), service, "hotkey"]);
+        for (rank, (key, n)) in top.iter().enumerate() {
+            log::info!(
+                "{} hot key top{}:{} samples:{}",
+                service,
+                rank,
+                log_name(key),
+                n
+            );
+            let mut hits = path.clone().push(&rank.to_string()).qps("hits");
+            hits += (*n * SAMPLE_RATE) as i64;
+        }
+        // 先减少数量，避免读到未更新的hash
+        self.hot_num.store(0, Release);
+        for (h, (key, _)) in self.hot.iter().zip(top.iter()) {
+            h.store(hash(&RingSlice::from_slice(key)), Relaxed);
+        }
+        self.hot_num.store(top.len(), Release);
+    }
+}
+
+#[inline]
+fn hash(key: &RingSlice) -> u64 {
+    // fnv-1a
+    let mut h = 0xcbf29ce484222325u64;
+    key.visit(|b| h = (h ^ b as u64).wrapping_mul(0x100000001b3));
+    h
+}
+
+// 日志中的特殊字符替换为'_'，并截断过长的key
+fn log_name(key: &[u8]) -> String {
+    key.iter()
+        .take(MAX_NAME_LEN)
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' => *b as char,
+            _ => '_',
+        })
+        .collect()
+}
@@ -1,4 +1,6 @@
 mod config;
+pub mod hotkey;
+pub mod near;
 pub mod topo;
 
 // 63位用来标识是否初始化了。
@@ -0,0 +1,87 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::*};
+use std::sync::Mutex;
+
+use ds::time::{Duration, Instant};
+use lru::LruCache;
+
+// 进程内的near cache，只缓存hot key的读响应，减少热点key对后端的访问。
+//   1. 容量有限，满了之后淘汰最久未访问的key；
+//   2. ttl很短，ttl内的响应可能与后端不一致；
+//   3. 经过当前mesh的写请求会删除对应的key。
+pub struct NearCache {
+    // 最多缓存的key数，为0时不开启
+    cap: AtomicUsize,
+    ttl_ms: AtomicU64,
+    // 读写都会调整访问顺序，只有hot key会访问，竞争有限
+    entries: Mutex<LruCache<Vec<u8>, Entry>>,
+}
+
+struct Entry {
+    op_code: u16,
+    rsp: Vec<u8>,
+    created: Instant,
+}
+
+impl Default for NearCache {
+    fn default() -> Self {
+        Self {
+            cap: AtomicUsize::new(0),
+            ttl_ms: AtomicU64::new(0),
+            entries: Mutex::new(LruCache::new(NonZeroUsize::MIN)),
+        }
+    }
+}
+
+impl NearCache {
+    pub fn config(&self, cap: usize, ttl_ms: u64) {
+        self.cap.store(cap, Release);
+        self.ttl_ms.store(ttl_ms, Release);
+        let mut entries = self.entries.lock().expect("near");
+        match NonZeroUsize::new(cap) {
+            Some(cap) => entries.resize(cap),
+            None => entries.clear(),
+        }
+    }
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.cap.load(Acquire) > 0
+    }
+    #[inline]
+    fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms.load(Acquire))
+    }
+    // 相同指令的响应才能复用，如get与gets
+    #[inline]
+    pub fn get(&self, op_code: u16, key: &[u8]) -> Option<Vec<u8>> {
+        let ttl = self.ttl();
+        let mut entries = self.entries.lock().expect("near");
+        let e = entries.get(key)?;
+        if e.op_code != op_code || e.created.elapsed() >= ttl {
+            return None;
+        }
+        Some(e.rsp.clone())
+    }
+    // 已缓存且未过期的key不更新，避免near cache返回的响应刷新ttl
+    pub fn put(&self, op_code: u16, key: Vec<u8>, rsp: Vec<u8>) {
+        let ttl = self.ttl();
+        let mut entries = self.entries.lock().expect("near");
+        if let Some(e) = entries.peek(&key) {
+            if e.created.elapsed() < ttl {
+                return;
+            }
+        }
+        let created = Instant::now();
+        let e = Entry {
+            op_code,
+            rsp,
+            created,
+        };
+        entries.put(key, e);
+    }
+    #[inline]
+    pub fn remove(&self, key: &[u8]) {
+        let mut entries = self.entries.lock().expect("near");
+        entries.pop(key);
+    }
+}
@@ -1,11 +1,15 @@
 use crate::select::Distance;
 use crate::{Endpoint, Endpoints, Topology};
 use discovery::TopologyWrite;
-use protocol::memcache::Binary;
-use protocol::{Protocol, Request, Resource::Memcache};
+use ds::{MemGuard, RingSlice};
+use protocol::{Command, HashedCommand, Protocol, Request, ResOption, Resource::Memcache, Retry};
 use sharding::hash::{Hash, HashKey, Hasher};
+use std::sync::Arc;
 
 use super::config::Flag;
+use super::hotkey::HotKeys;
+use super::near::NearCache;
+use crate::mirror::Mirror;
 use crate::shards::Shards;
 use crate::PerformanceTuning;
 use protocol::Bit;
@@ -19,6 +23,21 @@ pub struct CacheService<E, P> {
     hasher: Hasher,
     parser: P,
     exp_sec: u32,
+    // client的最大连接数、最大qps
+    max_conns: usize,
+    max_qps: u32,
+    // big key/value的阈值，及超过阈值时是否拒绝
+    max_key_len: usize,
+    max_value_len: usize,
+    reject_big: bool,
+    // 读请求的hot key统计，及hot key的near cache。topo更新时保留
+    service: String,
+    hotkeys: Arc<HotKeys>,
+    near: Arc<NearCache>,
+    mirror: Mirror<E>,
+    retry: Retry,
+    // 与后端连接的tls及熔断配置，变更后老的endpoints不再复用
+    option: ResOption,
 
     // TODO 线上稳定后再清理，预计2024.2之后
     // 1. 去掉force_write_all，其设计的本意是set失败后，是否更新其他layer；
@@ -38,6 +57,17 @@ impl<E, P> From<P> for CacheService<E, P> {
             parser,
             streams: Distance::new(),
             exp_sec: 0,
+            max_conns: 0,
+            max_qps: 0,
+            max_key_len: 0,
+            max_value_len: 0,
+            reject_big: false,
+            service: String::new(),
+            hotkeys: Default::default(),
+            near: Default::default(),
+            mirror: Mirror::from(Memcache),
+            retry: Default::default(),
+            option: Default::default(),
             // force_write_all: false, // 兼容考虑默认为false，set master失败后，不更新其他layers，新业务推荐用true
             hasher: Default::default(),
             backend_no_storage: false,
@@ -80,6 +110,47 @@ where
     fn exp_sec(&self) -> u32 {
         self.exp_sec
     }
+    #[inline]
+    fn max_conns(&self) -> usize {
+        self.max_conns
+    }
+    #[inline]
+    fn max_qps(&self) -> u32 {
+        self.max_qps
+    }
+    #[inline]
+    fn max_key_len(&self) -> usize {
+        self.max_key_len
+    }
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        self.max_value_len
+    }
+    #[inline]
+    fn reject_big(&self) -> bool {
+        self.reject_big
+    }
+    #[inline]
+    fn mirror(&self, req: &HashedCommand) -> bool {
+        self.mirror.sample(req)
+    }
+    // hot key的读响应写入near cache
+    #[inline]
+    fn on_response(&self, req: &HashedCommand, rsp: &Command) {
+        let op = req.operation();
+        if !self.near.enabled() || !op.is_query() || op.master_first() || !rsp.ok() {
+            return;
+        }
+        if !self.parser.rsp_reusable(req) {
+            return;
+        }
+        // 非hot key不拷贝key
+        if let Some(key) = self.parser.sample_key(req) {
+            if self.hotkeys.is_hot(&key) {
+                self.near.put(req.op_code(), to_vec(&key), to_vec(rsp));
+            }
+        }
+    }
 }
 
 impl<E, Req, P> Endpoint for CacheService<E, P>
@@ -92,14 +163,29 @@ where
     #[inline]
     fn send(&self, mut req: Self::Item) {
         debug_assert!(self.streams.local_len() > 0);
+        if req.mirror() {
+            self.mirror.send(req);
+            return;
+        }
 
         // let mut idx: usize = 0; // master
         let mut ctx = super::Context::from(*req.mut_context());
         // gets及store类指令，都需要先请求master，然后再考虑masterL1
         let (idx, try_next, write_back) = if req.operation().is_store() {
+            // 经过当前mesh的写请求，清理near cache
+            if self.near.enabled() {
+                if let Some(key) = self.parser.sample_key(&req) {
+                    self.near.remove(&to_vec(&key));
+                }
+            }
             self.context_store(&mut ctx)
         } else {
             if !ctx.inited() {
+                // 第一次读请求，统计hot key，命中near cache时直接返回
+                if let Some(rsp) = self.sample_and_lookup(&req) {
+                    req.on_complete(Command::from_ok(MemGuard::from_vec(rsp)));
+                    return;
+                }
                 // ctx未初始化, 是第一次读请求；仅第一次请求记录时间，原因如下：
                 // 第一次读一般访问L1，miss之后再读master；
                 // 读quota的更新根据第一次的请求时间更合理
@@ -109,10 +195,15 @@ where
             }
             self.context_get(&mut ctx, &req)
         };
+        // 协议层面是否允许重试，由parser根据具体的请求决定
+        let retry_on_rsp_notok = self.parser.can_retry_on_rsp_notok(&req);
+        // cas/incr/append等写请求只在master上执行，master失败时不能try next到其他layers，
+        // 其他layers只由master成功后的回写来更新或清理
+        let try_next = try_next && (!req.operation().is_store() || retry_on_rsp_notok);
         req.try_next(try_next);
         req.write_back(write_back);
-        // TODO 有点怪异，先实现，晚点调整，这个属性直接从request获取更佳？ fishermen
-        req.retry_on_rsp_notok(req.can_retry_on_rsp_notok());
+        req.retry_on_rsp_notok(retry_on_rsp_notok);
+        req.retry(&self.retry);
         *req.mut_context() = ctx.ctx;
         if idx >= self.streams.len() {
             req.on_err(protocol::Error::TopChanged);
@@ -129,6 +220,29 @@ impl<E, Req: Request, P: Protocol> CacheService<E, P>
 where
     E: Endpoint<Item = Req>,
 {
+    // gets等master-first的请求需要读master，不走near cache
+    #[inline]
+    fn sample_and_lookup(&self, req: &Req) -> Option<Vec<u8>> {
+        let op = req.operation();
+        if !op.is_query() {
+            return None;
+        }
+        let key = self.parser.sample_key(req)?;
+        self.hotkeys.sample(&self.service, &key);
+        if !self.near.enabled() || op.master_first() || !self.parser.rsp_reusable(req) {
+            return None;
+        }
+        if !self.hotkeys.is_hot(&key) {
+            return None;
+        }
+        let mut rsp = self.near.get(req.op_code(), &to_vec(&key))?;
+        self.parser.reuse_rsp(req, &mut rsp);
+        Some(rsp)
+    }
+    // 所有写指令（含replace/cas/incr/decr/append/prepend/touch）都从master开始，master成功后回写其他layers：
+    //   set/add/replace/cas 以noreply的set/replace更新其他layers；
+    //   incr/decr/append/prepend/touch 结果依赖原值，无法重放，以deleteq清理其他layers。
+    // 具体的回写请求由parser的build_writeback_request构建。
     #[inline]
     fn context_store(&self, ctx: &mut super::Context) -> (usize, bool, bool) {
         let (idx, try_next, write_back);
@@ -200,23 +314,47 @@ where
     #[inline]
     fn update(&mut self, namespace: &str, cfg: &str) {
         if let Some(ns) = super::config::Namespace::try_from(cfg, namespace) {
-            self.hasher = Hasher::from(&ns.hash);
+            let hasher = Hasher::from(&ns.hash);
+            self.hasher = match ns.enable_hash_tag && !ns.hash_tag.is_empty() {
+                true => hasher.with_tag(&ns.hash_tag),
+                false => hasher,
+            };
 
             self.exp_sec = (ns.exptime / 1000) as u32; // 转换成秒
+            self.max_conns = ns.max_conns;
+            self.max_qps = ns.max_qps;
+            self.max_key_len = ns.max_key_len;
+            self.max_value_len = ns.max_value_len;
+            self.reject_big = ns.reject_big;
+            self.service = namespace.to_string();
+            self.retry = ns.retry.build(namespace, 2);
+            self.near
+                .config(ns.near_cache_size, ns.near_cache_ttl_ms as u64);
 
             // self.force_write_all = ns.flag.get(Flag::ForceWriteAll as u8);
             self.backend_no_storage = ns.flag.get(Flag::BackendNoStorage as u8);
             let dist = &ns.distribution.clone();
 
-            // 把所有的endpoints cache下来
+            // 把所有的endpoints cache下来，tls或熔断配置变更后，老的endpoints不再复用
+            let o = ResOption {
+                tls: ns.tls,
+                breaker: ns.breaker.option(),
+                ..Default::default()
+            };
             let mut endpoints: Endpoints<'_, P, E> =
-                Endpoints::new(namespace, &self.parser, Memcache);
-            self.streams.take().into_iter().for_each(|shard| {
-                endpoints.cache(shard.into());
-            });
+                Endpoints::new(namespace, &self.parser, Memcache).with_option(o.clone());
+            let old = self.streams.take();
+            if o == self.option {
+                old.into_iter()
+                    .for_each(|shard| endpoints.cache(shard.into()));
+            }
+            self.option = o.clone();
 
             let mto = crate::TO_MC_M.to(ns.timeout_ms_master);
             let rto = crate::TO_MC_S.to(ns.timeout_ms_slave);
+            let parser = &self.parser;
+            self.mirror
+                .update(namespace, parser, &ns.mirror, dist, mto, o);
 
             //use discovery::distance::{Balance, ByDistance};
             //let master = ns.master.clone();
@@ -227,8 +365,7 @@ where
             for (i, group) in backends.into_iter().enumerate() {
                 // 第一组是master
                 let to = if i == 0 { mto } else { rto };
-                let backends = endpoints.take_or_build(&group, to);
-                let shard = Shards::from_dist(dist, backends);
+                let shard = Shards::from_weighted(&mut endpoints, &group, dist, to);
 
                 new.push(shard);
             }
@@ -250,6 +387,13 @@ where
     }
 }
 
+#[inline]
+fn to_vec(data: &RingSlice) -> Vec<u8> {
+    let mut v = Vec::with_capacity(data.len());
+    data.copy_to_vec(&mut v);
+    v
+}
+
 use std::fmt::{self, Display, Formatter};
 impl<E, P> Display for CacheService<E, P> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
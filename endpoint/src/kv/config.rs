@@ -1,7 +1,5 @@
-use base64::{engine::general_purpose, Engine as _};
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
-use std::fs;
 
 use crate::{Timeout, TO_MYSQL_M, TO_MYSQL_S};
 
@@ -121,11 +119,7 @@ impl KvNamespace {
 
     #[inline]
     fn decrypt_password(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let key_pem = fs::read_to_string(&context::get().key_path)?;
-        let encrypted_data = general_purpose::STANDARD.decode(self.basic.password.as_bytes())?;
-        let decrypted_data = ds::decrypt::decrypt_password(&key_pem, &encrypted_data)?;
-        let decrypted_string = String::from_utf8(decrypted_data)?;
-        Ok(decrypted_string)
+        crate::decrypt_password(&self.basic.password)
     }
     pub(super) fn timeout_master(&self) -> Timeout {
         let mut to = TO_MYSQL_M;
@@ -262,6 +262,7 @@ where
                 let res_option = ResOption {
                     token: self.cfg.basic.password.clone(),
                     username: self.cfg.basic.user.clone(),
+                    ..Default::default()
                 };
                 let master = self.take_or_build(
                     &mut old,
@@ -301,9 +302,8 @@ where
                     replicas.push(slave);
                 }
 
-                use crate::PerformanceTuning;
                 let shard = Shard::selector(
-                    self.cfg.basic.selector.tuning_mode(),
+                    &self.cfg.basic.selector,
                     master,
                     replicas,
                     self.cfg.basic.region_enabled,
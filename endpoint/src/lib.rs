@@ -1,11 +1,18 @@
+mod breaker;
+mod retry;
 mod shards;
 mod topo;
+pub use breaker::BreakerConfig;
+pub use mirror::MirrorConfig;
+pub use retry::RetryConfig;
 pub use topo::*;
 
 pub mod cacheservice;
 pub mod kv;
+pub mod mirror;
 pub mod msgque;
 pub mod phantomservice;
+pub mod rediscluster;
 pub mod redisservice;
 pub mod select;
 pub mod uuid;
@@ -50,6 +57,16 @@ impl Timeout {
     }
 }
 
+// 解密经过RSA加密、base64编码的密码，私钥从key_path中读取
+pub(crate) fn decrypt_password(password: &str) -> Result<String, Box<dyn std::error::Error>> {
+    use base64::{engine::general_purpose, Engine as _};
+    let key_pem = std::fs::read_to_string(&context::get().key_path)?;
+    let encrypted_data = general_purpose::STANDARD.decode(password.as_bytes())?;
+    let decrypted_data = ds::decrypt::decrypt_password(&key_pem, &encrypted_data)?;
+    let decrypted_string = String::from_utf8(decrypted_data)?;
+    Ok(decrypted_string)
+}
+
 use std::time::Duration;
 impl Into<Duration> for Timeout {
     fn into(self) -> Duration {
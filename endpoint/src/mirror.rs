@@ -0,0 +1,121 @@
+use std::cell::Cell;
+
+use protocol::{Error, HashedCommand, Protocol, Request, ResOption, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::shards::Shards;
+use crate::{Endpoint, Endpoints, Timeout};
+
+thread_local! {
+    static SEQ: Cell<u32> = const { Cell::new(0) };
+}
+
+// 镜像集群的配置，用于迁移时把线上流量复制到新集群，镜像请求异步发送，不影响client
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Hash)]
+pub struct MirrorConfig {
+    // 镜像集群的后端，每个分片一个地址；redis的分片配置为"master,slave"时，只使用master
+    #[serde(default)]
+    pub backends: Vec<String>,
+    // 为空时与主集群相同
+    #[serde(default)]
+    pub distribution: String,
+    // 镜像的请求比例，0~100
+    #[serde(default = "MirrorConfig::default_percent")]
+    pub percent: u8,
+    // 只镜像写请求
+    #[serde(default)]
+    pub writes_only: bool,
+}
+
+impl MirrorConfig {
+    fn default_percent() -> u8 {
+        100
+    }
+}
+
+#[derive(Clone)]
+pub struct Mirror<E> {
+    shards: Option<Shards<E>>,
+    percent: u8,
+    writes_only: bool,
+    option: ResOption,
+    resource: Resource,
+}
+
+impl<E> From<Resource> for Mirror<E> {
+    fn from(resource: Resource) -> Self {
+        Self {
+            shards: None,
+            percent: 0,
+            writes_only: false,
+            option: Default::default(),
+            resource,
+        }
+    }
+}
+
+impl<E: Endpoint> Mirror<E> {
+    // 镜像集群的metrics使用单独的service名，与主集群的错误、耗时分开统计
+    pub fn update<P: Protocol>(
+        &mut self,
+        service: &str,
+        parser: &P,
+        cfg: &MirrorConfig,
+        dist: &str,
+        to: Timeout,
+        option: ResOption,
+    ) {
+        self.percent = cfg.percent.min(100);
+        self.writes_only = cfg.writes_only;
+
+        let service = format!("{}_mirror", service);
+        let mut endpoints: Endpoints<'_, P, E> =
+            Endpoints::new(&service, parser, self.resource).with_option(option.clone());
+        // 鉴权信息变更后，老的endpoints不再复用
+        if let Some(old) = self.shards.take() {
+            if option == self.option {
+                endpoints.cache::<E>(old.into());
+            }
+        }
+        self.option = option;
+        if cfg.backends.is_empty() {
+            return;
+        }
+        let dist = match cfg.distribution.as_str() {
+            "" => dist,
+            d => d,
+        };
+        let shards = Shards::from_masters(&mut endpoints, &cfg.backends, dist, to);
+        self.shards = Some(shards);
+    }
+    // 按比例采样需要镜像的请求
+    #[inline]
+    pub fn sample(&self, req: &HashedCommand) -> bool {
+        if self.shards.is_none() || self.percent == 0 {
+            return false;
+        }
+        if self.writes_only && !req.operation().is_store() {
+            return false;
+        }
+        self.percent >= 100
+            || SEQ.with(|seq| {
+                let n = seq.get().wrapping_add(1);
+                seq.set(n);
+                n % 100 < self.percent as u32
+            })
+    }
+}
+
+impl<E, Req> Mirror<E>
+where
+    E: Endpoint<Item = Req>,
+    Req: Request,
+{
+    #[inline]
+    pub fn send(&self, req: Req) {
+        match &self.shards {
+            Some(shards) => shards.send(req),
+            None => req.on_err(Error::TopChanged),
+        }
+    }
+}
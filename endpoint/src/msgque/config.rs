@@ -42,6 +42,10 @@ pub struct Namespace {
 
     #[serde(default)]
     pub offline_idle_time: Duration,
+
+    // 重试策略，max_attempts默认为2，即只重试一次；读请求的次数不少于3次时，最后一次概率访问offline队列
+    #[serde(default)]
+    pub(crate) retry: crate::RetryConfig,
 }
 
 impl Namespace {
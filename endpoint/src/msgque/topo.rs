@@ -1,5 +1,5 @@
 use discovery::TopologyWrite;
-use protocol::{Protocol, Request, Resource};
+use protocol::{Protocol, Request, Resource, Retry};
 use std::{
     collections::HashSet,
     sync::{
@@ -21,10 +21,10 @@ use super::{
     Context,
 };
 
-// 读miss后的重试次数
-const READ_RETRY_COUNT: usize = 3;
-// 写失败后的重试次数
-const WRITE_RETRY_COUNT: usize = 3;
+// 读miss、写失败后默认只重试一次，可通过retry.max_attempts配置
+const DEFAULT_ATTEMPTS: u8 = 2;
+// 连续空读该次数后，最后一次读请求才会概率访问offline队列
+const OFFLINE_READ_AFTER: usize = 2;
 // ip vintage下线后，N分钟后停止读
 const OFFLINE_STOP_READ_SECONDS: u64 = 60 * 20;
 // ip vintage下线后，N分钟从内存清理
@@ -51,6 +51,10 @@ pub struct MsgQue<E, P> {
 
     timeout_write: Timeout,
     timeout_read: Timeout,
+
+    // 读、写请求的重试策略
+    retry_read: Retry,
+    retry_write: Retry,
 }
 
 impl<E, P> From<P> for MsgQue<E, P> {
@@ -69,6 +73,8 @@ impl<E, P> From<P> for MsgQue<E, P> {
             max_size: super::BLOCK_SIZE,
             timeout_write: Timeout::from_millis(200),
             timeout_read: Timeout::from_millis(100),
+            retry_read: Default::default(),
+            retry_write: Default::default(),
         }
     }
 }
@@ -162,8 +168,9 @@ where
             if !get_offline {
                 ctx.update_qid(qid as u16);
             }
-            // 是否重试：之前重试次数小于阀值-1，且不是从offline streams获取(offline是最后一次获取)
-            req.try_next(rw_count < (READ_RETRY_COUNT - 1) && !get_offline);
+            // 是否重试：之前重试次数小于配置的重试次数，且不是从offline streams获取(offline是最后一次获取)
+            req.retry(&self.retry_read);
+            req.try_next(rw_count < self.retry_read.tries() as usize && !get_offline);
             *req.mut_context() = ctx.ctx;
             log::debug!(
                 "+++ mcq get {} from qid/{}, from_offline/{} req: {:?}",
@@ -188,7 +195,8 @@ where
         let (qid, wsize) = self.write_strategy.next_queue_write(wsize);
         ctx.update_write_size(wsize);
         ctx.update_qid(qid);
-        req.try_next(rw_count < WRITE_RETRY_COUNT);
+        req.retry(&self.retry_write);
+        req.try_next(rw_count < self.retry_write.tries() as usize);
         *req.mut_context() = ctx.ctx;
 
         log::debug!(
@@ -219,7 +227,7 @@ where
             Some(ctx.get_last_qid())
         };
 
-        if rw_count < (READ_RETRY_COUNT - 1) {
+        if rw_count < self.retry_read.tries() as usize || rw_count < OFFLINE_READ_AFTER {
             let qid = self.read_strategy.next_queue_read(last_qid);
             return (false, qid as usize);
         } else {
@@ -391,6 +399,8 @@ where
 
             self.timeout_read.adjust(ns.timeout_read);
             self.timeout_write.adjust(ns.timeout_write);
+            self.retry_read = ns.retry.build(name, DEFAULT_ATTEMPTS);
+            self.retry_write = ns.retry.build(name, DEFAULT_ATTEMPTS);
 
             let old_r = self.streams_read.split_off(0);
             let mut old_streams_read: HashMap<String, E> =
@@ -1,5 +1,6 @@
 //use ds::time::Duration;
 
+use protocol::ResOption;
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -20,12 +21,19 @@ pub struct Basic {
     //resource_type: String,
     #[serde(default)]
     pub(crate) timeout_ms: u32,
+    // 后端鉴权，同redis
+    #[serde(default)]
+    pub(crate) user: String,
+    #[serde(default)]
+    pub(crate) password: String,
+    #[serde(default)]
+    pub(crate) password_encrypted: bool,
 }
 
 impl PhantomNamespace {
     #[inline]
     pub fn try_from(cfg: &str) -> Option<PhantomNamespace> {
-        let ns = serde_yaml::from_str::<PhantomNamespace>(cfg)
+        let mut ns = serde_yaml::from_str::<PhantomNamespace>(cfg)
             .map_err(|_err| {
                 log::warn!("parse phantome cfg failed:{:?}, cfg:{}", _err, cfg);
             })
@@ -34,9 +42,24 @@ impl PhantomNamespace {
             log::warn!("found malfromed phantome cfg:{}", cfg);
             return None;
         }
+        if ns.basic.password_encrypted {
+            ns.basic.password = crate::decrypt_password(&ns.basic.password)
+                .map_err(|e| log::warn!("failed to decrypt phantom password, e:{}", e))
+                .ok()?;
+        }
         Some(ns)
     }
 
+    // 后端连接的鉴权信息
+    #[inline]
+    pub(super) fn res_option(&self) -> ResOption {
+        ResOption {
+            token: self.basic.password.clone(),
+            username: self.basic.user.clone(),
+            ..Default::default()
+        }
+    }
+
     pub(super) fn timeout(&self) -> crate::Timeout {
         let mut to = crate::TO_PHANTOM_M;
         if self.basic.timeout_ms > 0 {
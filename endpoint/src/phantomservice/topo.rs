@@ -4,7 +4,7 @@ use crate::{
     Endpoint, Endpoints, Topology,
 };
 use discovery::{Inited, TopologyWrite};
-use protocol::{Protocol, Request, Resource::Phantom};
+use protocol::{Protocol, Request, ResOption, Resource::Phantom};
 use sharding::{
     distribution::Range,
     hash::{Crc32, Hash, HashKey},
@@ -20,6 +20,8 @@ pub struct PhantomService<E, P> {
     distribution: Range,
     parser: P,
     cfg: Box<DnsConfig<PhantomNamespace>>,
+    // 当前endpoints使用的鉴权信息，变更后需要重建endpoints
+    auth: ResOption,
 }
 
 impl<E, P> From<P> for PhantomService<E, P> {
@@ -30,6 +32,7 @@ impl<E, P> From<P> for PhantomService<E, P> {
             hasher: Default::default(),
             distribution: Default::default(),
             cfg: Default::default(),
+            auth: Default::default(),
         }
     }
 }
@@ -82,6 +85,10 @@ where
         *req.context_mut() = ctx.ctx;
         e.send(req)
     }
+    #[inline]
+    fn shard_idx(&self, hash: i64) -> usize {
+        self.distribution.index(hash)
+    }
 }
 
 impl<E, P> TopologyWrite for PhantomService<E, P>
@@ -131,12 +138,17 @@ where
     fn load_inner(&mut self) -> Option<()> {
         let addrs = self.cfg.shards_url.lookup()?;
         assert_eq!(addrs.len(), self.cfg.shards_url.len());
+        let auth = self.cfg.res_option();
         let mut endpoints: Endpoints<'_, P, E> =
-            Endpoints::new(&self.cfg.service, &self.parser, Phantom);
-        // 把老的stream缓存起来
-        self.streams.split_off(0).into_iter().for_each(|shard| {
-            endpoints.cache(shard.into_inner());
-        });
+            Endpoints::new(&self.cfg.service, &self.parser, Phantom).with_option(auth.clone());
+        // 把老的stream缓存起来，鉴权信息变更后，老的stream不再复用
+        let old = self.streams.split_off(0);
+        if auth == self.auth {
+            old.into_iter().for_each(|shard| {
+                endpoints.cache(shard.into_inner());
+            });
+        }
+        self.auth = auth;
         addrs.iter().for_each(|shard| {
             assert!(!shard.is_empty());
             let backends = endpoints.take_or_build(&*shard, self.cfg.timeout());
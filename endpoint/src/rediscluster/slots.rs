@@ -0,0 +1,152 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering::*},
+    Arc, Mutex,
+};
+use std::time::Duration;
+
+use ds::time::Instant;
+use protocol::{
+    redis::cluster::{parse_slots, slots_request, SlotRange},
+    Error, Protocol, ResOption,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Notify;
+
+use crate::Endpoint;
+
+// 定期刷新slot map，避免master故障切换后，请求持续发往老的master
+const REFRESH_CYCLE: Duration = Duration::from_secs(30);
+// 两次获取之间的最小间隔，避免MOVED集中出现时频繁获取
+const FETCH_INTERVAL: Duration = Duration::from_secs(1);
+// slot map的响应上限，超过时认为异常
+const MAX_RSP_LEN: usize = 16 * 1024 * 1024;
+
+// 通过CLUSTER SLOTS异步获取slot map。依次尝试所有节点，直到有一个节点成功。
+// 获取成功后暂存，由topo在load时取走并更新。
+pub(super) struct Fetcher<E> {
+    fetching: AtomicBool,
+    ready: AtomicBool,
+    fetched: Mutex<Option<Vec<SlotRange>>>,
+    last: Mutex<Instant>,
+    // 最新slot map中的master，slot map更新后通知等待的请求，如MOVED到新节点的请求
+    masters: Mutex<Vec<E>>,
+    loaded: Notify,
+}
+
+impl<E> Default for Fetcher<E> {
+    fn default() -> Self {
+        Self {
+            fetching: false.into(),
+            ready: false.into(),
+            fetched: Mutex::new(None),
+            last: Mutex::new(Instant::now()),
+            masters: Mutex::new(Vec::new()),
+            loaded: Notify::new(),
+        }
+    }
+}
+
+impl<E: Endpoint + Clone + 'static> Fetcher<E> {
+    // 有新获取的slot map，或者长时间未刷新
+    #[inline]
+    pub(super) fn need_load(&self) -> bool {
+        self.ready.load(Acquire)
+            || (!self.fetching.load(Acquire)
+                && self.last.lock().expect("last").elapsed() >= REFRESH_CYCLE)
+    }
+    #[inline]
+    pub(super) fn take(&self) -> Option<Vec<SlotRange>> {
+        if !self.ready.load(Acquire) {
+            return None;
+        }
+        let slots = self.fetched.lock().expect("fetched").take();
+        self.ready.store(false, Release);
+        slots
+    }
+    // topo使用新的slot map后调用
+    #[inline]
+    pub(super) fn loaded(&self, masters: &[E]) {
+        *self.masters.lock().expect("masters") = masters.to_vec();
+        self.loaded.notify_waiters();
+    }
+    // 最新slot map中addr对应的master，不存在时等待slot map更新，超时后返回None
+    pub(super) async fn wait_master(&self, addr: &str, to: Duration) -> Option<E> {
+        let loaded = self.loaded.notified();
+        if let Some(master) = self.master(addr) {
+            return Some(master);
+        }
+        let _ = tokio::time::timeout(to, loaded).await;
+        self.master(addr)
+    }
+    #[inline]
+    fn master(&self, addr: &str) -> Option<E> {
+        let masters = self.masters.lock().expect("masters");
+        masters.iter().find(|e| e.addr() == addr).cloned()
+    }
+    // 已有获取任务时忽略
+    pub(super) fn fetch<P: Protocol>(
+        self: &Arc<Self>,
+        nodes: Vec<String>,
+        parser: P,
+        option: ResOption,
+        to: Duration,
+    ) {
+        if nodes.is_empty() || self.fetching.swap(true, AcqRel) {
+            return;
+        }
+        *self.last.lock().expect("last") = Instant::now();
+        let me = self.clone();
+        rt::spawn(async move {
+            for node in nodes.iter() {
+                match tokio::time::timeout(to, fetch_one(node, &parser, &option)).await {
+                    Ok(Ok(slots)) => {
+                        log::info!(
+                            "cluster slots fetched from {}: {} ranges",
+                            node,
+                            slots.len()
+                        );
+                        *me.fetched.lock().expect("fetched") = Some(slots);
+                        me.ready.store(true, Release);
+                        break;
+                    }
+                    Ok(Err(e)) => log::warn!("fetch cluster slots from {} failed:{:?}", node, e),
+                    Err(_) => log::warn!("fetch cluster slots from {} timeout", node),
+                }
+            }
+            tokio::time::sleep(FETCH_INTERVAL).await;
+            me.fetching.store(false, Release);
+        });
+    }
+}
+
+// 与后端连接相同：tls握手、鉴权完成后，再发送CLUSTER SLOTS
+async fn fetch_one<P: Protocol>(
+    node: &str,
+    parser: &P,
+    option: &ResOption,
+) -> protocol::Result<Vec<SlotRange>> {
+    let host = node.rsplit_once(':').map(|(h, _)| h).unwrap_or(node);
+    let mut stream = rt::Stream::from(net::connect(node, option.tls).await?);
+    if parser.config().need_auth {
+        let auth = rt::Auth {
+            option: &mut option.clone(),
+            s: &mut stream,
+            parser: parser.clone(),
+        };
+        auth.await?;
+    }
+    stream.write_all(slots_request()).await?;
+    stream.flush().await?;
+    let mut data = Vec::with_capacity(4096);
+    loop {
+        if stream.read_buf(&mut data).await? == 0 {
+            return Err(Error::Eof);
+        }
+        match parse_slots(&data, host) {
+            Err(Error::ProtocolIncomplete) if data.len() < MAX_RSP_LEN => {
+                data.reserve(data.len());
+            }
+            r => return r,
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{redisservice::config::RedisNamespace, Endpoint, Endpoints, Topology};
+use discovery::TopologyWrite;
+use protocol::{
+    redis::cluster::{self, Redirect, SlotRange},
+    Error, Protocol, RedisFlager, Request, ResOption,
+    Resource::Redis,
+};
+use sharding::hash::{crc16::CLUSTER_SLOTS, Crc16, Hash, HashKey};
+
+use super::slots::Fetcher;
+
+// slot未被任何分片覆盖
+const UNCOVERED: u16 = u16::MAX;
+
+// redis cluster：后端配置的是集群的种子节点，通过CLUSTER SLOTS获取slot map，按crc16(key) % 16384路由。
+//   1. 只访问master，replica需要READONLY才能读，暂不支持；
+//   2. 响应为MOVED时，按响应中的地址重试一次，并异步刷新slot map；
+//      地址不在当前slot map中时，等待slot map刷新后，按新的slot map重试一次；
+//   3. 响应为ASK时，先发送ASKING，再按响应中的地址重试一次，不刷新slot map；
+//   4. 无响应时，读请求重试一次，写请求不重试，避免重复写；
+//   5. 重试的响应不是重定向时（如ASKING失败），直接返回该响应。
+#[derive(Clone)]
+pub struct RedisCluster<E, P> {
+    // 所有的master，顺序即分片索引
+    shards: Vec<E>,
+    // slot => 分片索引
+    slots: Vec<u16>,
+    hasher: Crc16,
+    parser: P,
+    service: String,
+    cfg: Box<RedisNamespace>,
+    // 配置变更后，需要重新获取slot map并重建endpoints
+    updated: bool,
+    // 当前endpoints使用的鉴权信息，变更后需要重建endpoints
+    auth: ResOption,
+    fetcher: Arc<Fetcher<E>>,
+}
+impl<E, P> From<P> for RedisCluster<E, P> {
+    #[inline]
+    fn from(parser: P) -> Self {
+        Self {
+            parser,
+            shards: Default::default(),
+            slots: Default::default(),
+            hasher: Default::default(),
+            service: Default::default(),
+            cfg: Default::default(),
+            updated: false,
+            auth: Default::default(),
+            fetcher: Default::default(),
+        }
+    }
+}
+
+impl<E, P> Hash for RedisCluster<E, P>
+where
+    E: Endpoint,
+    P: Protocol,
+{
+    #[inline]
+    fn hash<K: HashKey>(&self, k: &K) -> i64 {
+        self.hasher.hash(k)
+    }
+}
+
+impl<E, Req, P> Topology for RedisCluster<E, P>
+where
+    E: Endpoint<Item = Req> + Clone + 'static,
+    Req: Request,
+    P: Protocol,
+{
+    #[inline]
+    fn client_password(&self) -> &str {
+        &self.cfg.basic.client_password
+    }
+    #[inline]
+    fn max_conns(&self) -> usize {
+        self.cfg.basic.max_conns
+    }
+    #[inline]
+    fn max_qps(&self) -> u32 {
+        self.cfg.basic.max_qps
+    }
+    #[inline]
+    fn max_key_len(&self) -> usize {
+        self.cfg.basic.max_key_len
+    }
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        self.cfg.basic.max_value_len
+    }
+    #[inline]
+    fn reject_big(&self) -> bool {
+        self.cfg.basic.reject_big
+    }
+}
+
+impl<E, Req, P> Endpoint for RedisCluster<E, P>
+where
+    E: Endpoint<Item = Req> + Clone + 'static,
+    Req: Request,
+    P: Protocol,
+{
+    type Item = Req;
+    #[inline]
+    fn send(&self, mut req: Self::Item) {
+        debug_assert_ne!(self.shards.len(), 0);
+
+        let shard_idx = if req.sendto_all() {
+            match req.cross_shards() {
+                // 跨分片请求由协议通过hash指定分片，解析后分片数发生变化时，分片可能已不存在
+                Some(_) if req.hash() as usize >= self.shards.len() => {
+                    req.on_err(Error::TopChanged);
+                    return;
+                }
+                Some(_) => req.hash() as usize,
+                None => {
+                    //全节点分发请求
+                    let ctx = super::transmute(req.context_mut());
+                    let idx = ctx.shard_idx as usize;
+                    ctx.shard_idx += 1;
+                    req.write_back(idx < self.shards.len() - 1);
+                    idx
+                }
+            }
+        } else {
+            let ctx = super::transmute(req.context_mut());
+            let runs = ctx.runs;
+            ctx.runs += 1;
+            let idx = match runs {
+                0 => {
+                    // 重定向时需要重试
+                    req.try_next(true);
+                    req.retry_on_rsp_notok(true);
+                    self.shard_of(req.hash())
+                }
+                _ => match self.redirect(req, runs) {
+                    Some((idx, redirected)) => {
+                        req = redirected;
+                        idx
+                    }
+                    None => return,
+                },
+            };
+            if idx == UNCOVERED as usize {
+                req.on_err(Error::TopInvalid);
+                return;
+            }
+            idx
+        };
+
+        assert!(shard_idx < self.shards.len(), "{} {:?}", shard_idx, req);
+        log::debug!("{} send {}=>{:?}", self, shard_idx, req);
+        unsafe { self.shards.get_unchecked(shard_idx) }.send(req)
+    }
+
+    #[inline]
+    fn shard_idx(&self, hash: i64) -> usize {
+        match self.shard_of(hash) {
+            idx if idx == UNCOVERED as usize => 0,
+            idx => idx,
+        }
+    }
+
+    // 阻塞、订阅类指令使用client独占的连接，与共享连接使用相同的鉴权
+    #[inline]
+    fn master_of(&self, shard: usize) -> Option<(String, ResOption)> {
+        let master = self.shards.get(shard)?;
+        Some((master.addr().to_string(), self.auth.clone()))
+    }
+    #[inline]
+    fn shards(&self) -> usize {
+        self.shards.len()
+    }
+}
+
+impl<E, P> TopologyWrite for RedisCluster<E, P>
+where
+    P: Protocol,
+    E: Endpoint + Clone + 'static,
+{
+    #[inline]
+    fn update(&mut self, namespace: &str, cfg: &str) {
+        if let Some(ns) = RedisNamespace::try_from(cfg) {
+            self.service = namespace.to_string();
+            *self.cfg = ns;
+            self.updated = true;
+        }
+    }
+    // 满足以下条件之一，则需要更新：
+    // 1. 配置变更，或者还未获取到slot map；
+    // 2. 获取到了新的slot map，或者需要定期刷新。
+    #[inline]
+    fn need_load(&self) -> bool {
+        self.updated || self.shards.is_empty() || self.fetcher.need_load()
+    }
+
+    #[inline]
+    fn load(&mut self) -> bool {
+        if let Some(slots) = self.fetcher.take() {
+            self.load_inner(slots);
+            self.updated = false;
+            self.fetcher.loaded(&self.shards);
+            return true;
+        }
+        // 配置变更后从种子节点获取，否则从当前的master获取
+        let nodes = match self.updated || self.shards.is_empty() {
+            true => self.seeds(),
+            false => self.shards.iter().map(|e| e.addr().to_string()).collect(),
+        };
+        let to = self.cfg.timeout_master().into();
+        self.fetcher
+            .fetch(nodes, self.parser.clone(), self.res_option(), to);
+        // slot map获取完成前，继续使用当前的分片
+        !self.updated && !self.shards.is_empty()
+    }
+}
+impl<E, P> discovery::Inited for RedisCluster<E, P>
+where
+    E: discovery::Inited,
+{
+    #[inline]
+    fn inited(&self) -> bool {
+        !self.shards.is_empty() && self.shards.iter().all(|e| e.inited())
+    }
+}
+
+impl<E, P> RedisCluster<E, P> {
+    #[inline]
+    fn shard_of(&self, hash: i64) -> usize {
+        let slot = hash as usize & (CLUSTER_SLOTS - 1);
+        self.slots.get(slot).map_or(UNCOVERED, |idx| *idx) as usize
+    }
+    // 配置的后端为种子节点，每一行可以配置多个，以','分隔
+    fn seeds(&self) -> Vec<String> {
+        let backends = self.cfg.backends.iter();
+        let nodes = backends.flat_map(|b| b.split(',')).map(|n| n.trim());
+        nodes
+            .filter(|n| !n.is_empty())
+            .map(|n| n.to_string())
+            .collect()
+    }
+    // cluster不支持select，忽略db
+    fn res_option(&self) -> ResOption {
+        let mut option = self.cfg.res_option();
+        option.db = 0;
+        option
+    }
+}
+
+impl<E, Req, P> RedisCluster<E, P>
+where
+    E: Endpoint<Item = Req> + Clone + 'static,
+    Req: Request,
+    P: Protocol,
+{
+    // 重试的请求，根据上一次的响应确定发送的分片。runs为已发送的次数。
+    // 返回None时，请求已结束，或者等待slot map刷新后重试
+    #[inline]
+    fn redirect(&self, mut req: Req, runs: u16) -> Option<(usize, Req)> {
+        let Some(redirect) = req.response().and_then(|rsp| Redirect::parse(rsp)) else {
+            // 无响应时只重试一次读请求；有响应但不是重定向，说明ASKING失败，直接返回该响应
+            if req.response().is_none() && runs == 1 && !req.operation().is_store() {
+                return Some((self.shard_of(req.hash()), req));
+            }
+            req.on_finish();
+            return None;
+        };
+        let idx = self.shards.iter().position(|e| e.addr() == redirect.addr());
+        match redirect {
+            Redirect::Moved(..) => self.refresh(),
+            Redirect::Ask(..) if idx.is_some() => cluster::asking(&mut req),
+            Redirect::Ask(..) => {}
+        }
+        match idx {
+            Some(idx) => Some((idx, req)),
+            // 新加入的节点，等待slot map刷新后，发送到新的slot map中的节点
+            None if matches!(redirect, Redirect::Moved(..)) => {
+                let fetcher = self.fetcher.clone();
+                let to = self.cfg.timeout_master().into();
+                rt::spawn(async move {
+                    match fetcher.wait_master(redirect.addr(), to).await {
+                        Some(master) => master.send(req),
+                        None => req.on_err(Error::TopChanged),
+                    }
+                });
+                None
+            }
+            None => {
+                req.on_err(Error::TopChanged);
+                None
+            }
+        }
+    }
+    // 从当前的master异步获取slot map
+    #[inline]
+    fn refresh(&self) {
+        let nodes = self.shards.iter().map(|e| e.addr().to_string()).collect();
+        let to = self.cfg.timeout_master().into();
+        self.fetcher
+            .fetch(nodes, self.parser.clone(), self.res_option(), to);
+    }
+}
+
+impl<E, P> RedisCluster<E, P>
+where
+    P: Protocol,
+    E: Endpoint,
+{
+    fn load_inner(&mut self, ranges: Vec<SlotRange>) {
+        // 把所有的endpoints cache下来，鉴权信息变更后，老的endpoints不再复用
+        let auth = self.res_option();
+        let mut endpoints: Endpoints<'_, P, E> =
+            Endpoints::new(&self.service, &self.parser, Redis).with_option(auth.clone());
+        let old = self.shards.split_off(0);
+        if auth == self.auth {
+            endpoints.cache(old);
+        }
+        self.auth = auth;
+
+        let mut slots = vec![UNCOVERED; CLUSTER_SLOTS];
+        let mut masters: HashMap<String, u16> = HashMap::new();
+        for range in ranges {
+            let Some(master) = range.nodes.first() else {
+                continue;
+            };
+            let idx = *masters.entry(master.clone()).or_insert_with(|| {
+                let to = self.cfg.timeout_master();
+                self.shards.push(endpoints.take_or_build_one(master, to));
+                (self.shards.len() - 1) as u16
+            });
+            let end = (range.end as usize).min(CLUSTER_SLOTS - 1);
+            if let Some(range) = slots.get_mut(range.start as usize..=end) {
+                range.fill(idx);
+            }
+        }
+        let uncovered = slots.iter().filter(|idx| **idx == UNCOVERED).count();
+        if uncovered > 0 {
+            log::warn!("{} slots uncovered:{}", self.service, uncovered);
+        }
+        self.slots = slots;
+        log::info!(
+            "{} slots loaded, masters:{}",
+            self.service,
+            self.shards.len()
+        );
+    }
+}
+
+impl<E, P> std::fmt::Display for RedisCluster<E, P>
+where
+    E: Endpoint,
+    P: Protocol,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisCluster")
+            .field("service", &self.service)
+            .field("shards", &self.shards.len())
+            .finish()
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::fmt::Debug;
 
+use protocol::ResOption;
 use serde::{Deserialize, Serialize};
 //use sharding::distribution::{DIST_ABS_MODULA, DIST_MODULA};
 
@@ -14,6 +15,39 @@ const NO_CHECK_SUFFIX: &str = "-nocheck";
 pub struct RedisNamespace {
     pub(crate) basic: Basic,
     pub(crate) backends: Vec<String>,
+    // 镜像集群，迁移时复制线上流量
+    #[serde(default)]
+    pub(crate) mirror: crate::MirrorConfig,
+    // 分片迁移：backends及distribution为迁移后的拓扑，migration为迁移前的拓扑
+    #[serde(default)]
+    pub(crate) migration: Migration,
+    // 重试策略，默认读请求失败后重试一次。只作用于从slave读的请求：
+    // 写请求及只访问master的请求，每个分片只有一个master，不重试；分片迁移期间按迁移的回退逻辑重试
+    #[serde(default)]
+    pub(crate) retry: crate::RetryConfig,
+    // 后端实例的熔断阈值
+    #[serde(default)]
+    pub(crate) breaker: crate::BreakerConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Migration {
+    // 迁移前的分片，分片配置为"master,slave"时只访问master
+    #[serde(default)]
+    pub(crate) backends: Vec<String>,
+    #[serde(default)]
+    pub(crate) distribution: String,
+    // 迁移完成，不再访问迁移前的分片
+    #[serde(default)]
+    pub(crate) completed: bool,
+}
+
+impl Migration {
+    // 迁移期间，写请求双写，读请求未命中时回退到迁移前的分片
+    #[inline]
+    pub(crate) fn migrating(&self) -> bool {
+        !self.completed && !self.backends.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -39,10 +73,51 @@ pub struct Basic {
     // master是否参与读
     #[serde(default)]
     pub(crate) master_read: bool,
+    // 后端鉴权：user为空时使用AUTH <password>，否则使用ACL方式AUTH <user> <password>
+    #[serde(default)]
+    pub(crate) user: String,
+    #[serde(default)]
+    pub(crate) password: String,
+    // password是否经过RSA加密，加密的password需要先解密
+    #[serde(default)]
+    pub(crate) password_encrypted: bool,
+    // 后端使用的db，多个namespace可以通过不同的db共享同一组redis
+    #[serde(default)]
+    pub(crate) db: u16,
+    // client连接mesh时的鉴权密码，为空时不需要鉴权
+    #[serde(default)]
+    pub(crate) client_password: String,
+    // client_password是否经过RSA加密，与password相同
+    #[serde(default)]
+    pub(crate) client_password_encrypted: bool,
+    // 与后端的连接使用tls，用于跨region访问等场景。
+    // 目前支持redis、memcache，其他资源的后端连接不使用tls
+    #[serde(default)]
+    pub(crate) tls: bool,
+    // client的最大连接数、最大qps，超过后拒绝连接或请求，为0时不限制
+    #[serde(default)]
+    pub(crate) max_conns: usize,
+    #[serde(default)]
+    pub(crate) max_qps: u32,
+    // big key/value的阈值，为0时不检测；超过阈值时默认只记录日志及metrics，reject_big为true时拒绝请求
+    #[serde(default)]
+    pub(crate) max_key_len: usize,
+    #[serde(default)]
+    pub(crate) max_value_len: usize,
+    #[serde(default)]
+    pub(crate) reject_big: bool,
+    // 读请求超过该耗时未返回时，向另一个从发送对冲请求，先返回ok响应的胜出，都失败时使用原请求的结果；
+    // 为0时不对冲
+    #[serde(default)]
+    pub(crate) hedge_delay_ms: u32,
+    // 按从响应耗时的分位数对冲，如95表示超过p95耗时未返回时对冲，hedge_delay_ms为其上限；
+    // 为0或样本数不足时，使用固定的hedge_delay_ms
+    #[serde(default)]
+    pub(crate) hedge_percentile: u8,
 }
 
 impl RedisNamespace {
-    pub(super) fn try_from(cfg: &str) -> Option<Self> {
+    pub(crate) fn try_from(cfg: &str) -> Option<Self> {
         let mut ns = serde_yaml::from_str::<RedisNamespace>(cfg)
             .map_err(|e| log::info!("failed to parse redis config:{} => {e:?}", cfg))
             .ok()?;
@@ -56,12 +131,35 @@ impl RedisNamespace {
             return None;
         }
 
+        if ns.basic.password_encrypted {
+            ns.basic.password = crate::decrypt_password(&ns.basic.password)
+                .map_err(|e| log::warn!("failed to decrypt redis password, e:{}", e))
+                .ok()?;
+        }
+        if ns.basic.client_password_encrypted {
+            ns.basic.client_password = crate::decrypt_password(&ns.basic.client_password)
+                .map_err(|e| log::warn!("failed to decrypt redis client password, e:{}", e))
+                .ok()?;
+        }
+
         log::debug!("parsed redis config:{}/{}", ns.basic.distribution, cfg);
         return Some(ns);
     }
 
+    // 后端连接的鉴权及db信息
+    #[inline]
+    pub(crate) fn res_option(&self) -> ResOption {
+        ResOption {
+            token: self.basic.password.clone(),
+            username: self.basic.user.clone(),
+            db: self.basic.db,
+            tls: self.basic.tls,
+            breaker: self.breaker.option(),
+        }
+    }
+
     #[inline]
-    pub(super) fn timeout_master(&self) -> Timeout {
+    pub(crate) fn timeout_master(&self) -> Timeout {
         let mut to = TO_REDIS_M;
         if self.basic.timeout_ms_master > 0 {
             to.adjust(self.basic.timeout_ms_master);
@@ -0,0 +1,74 @@
+use std::sync::{
+    atomic::{AtomicU32, AtomicU64, Ordering::*},
+    Arc,
+};
+
+use ds::time::Instant;
+
+use crate::{shards::Shard, Endpoint};
+
+// 分位数对冲延迟的刷新周期
+const REFRESH_MS: u64 = 1000;
+
+// 按从实例rtt分位数计算的对冲延迟。每次请求都会读取，因此缓存计算结果，每秒刷新一次；
+// 在topo的各个clone之间共享。
+#[derive(Clone)]
+pub(crate) struct HedgeDelay {
+    inner: Arc<Inner>,
+}
+struct Inner {
+    start: Instant,
+    // 对冲延迟，单位ms，0表示还未计算
+    ms: AtomicU32,
+    // 最后一次刷新的时间，距start的毫秒数
+    last: AtomicU64,
+}
+
+impl Default for HedgeDelay {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                start: Instant::now(),
+                ms: 0.into(),
+                last: 0.into(),
+            }),
+        }
+    }
+}
+
+impl HedgeDelay {
+    // percentile为0时使用固定的max_ms；否则取各从实例rtt该分位数的平均值，不超过max_ms
+    #[inline]
+    pub(crate) fn get<E: Endpoint>(&self, shards: &[Shard<E>], percentile: u8, max_ms: u32) -> u32 {
+        if percentile == 0 || max_ms == 0 {
+            return max_ms;
+        }
+        let now = self.inner.start.elapsed().as_millis() as u64;
+        let ms = self.inner.ms.load(Relaxed);
+        if ms > 0 && now.saturating_sub(self.inner.last.load(Relaxed)) < REFRESH_MS {
+            return ms;
+        }
+        let ms = Self::calc(shards, percentile, max_ms);
+        self.inner.ms.store(ms, Relaxed);
+        self.inner.last.store(now, Relaxed);
+        ms
+    }
+    #[inline]
+    fn calc<E: Endpoint>(shards: &[Shard<E>], percentile: u8, max_ms: u32) -> u32 {
+        let (mut sum, mut n) = (0u64, 0u64);
+        for us in shards
+            .iter()
+            .flat_map(|s| s.slaves.iter())
+            .map(|e| e.rtt_percentile(percentile))
+            .filter(|&us| us > 0)
+        {
+            sum += us;
+            n += 1;
+        }
+        // 样本数不足，使用固定的延迟
+        if n == 0 {
+            return max_ms;
+        }
+        ((sum / n).div_ceil(1000).max(1) as u32).min(max_ms)
+    }
+}
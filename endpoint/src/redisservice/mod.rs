@@ -1,11 +1,12 @@
-pub(super) mod config;
+pub(crate) mod config;
+mod hedge;
 pub mod topo;
 
 struct Context {
     runs: u16, // 运行的次数
     idx: u16,  //最多有65535个主从
     shard_idx: u16,
-    _ignore: u16,
+    migrated: u16, // 分片迁移期间，下一次发送到迁移前的分片
 }
 
 #[inline]
@@ -1,14 +1,17 @@
 use crate::{
     dns::{DnsConfig, DnsLookup},
-    shards::Shard,
-    Endpoint, Endpoints, PerformanceTuning, Topology,
+    mirror::Mirror,
+    shards::{Shard, Shards},
+    Endpoint, Endpoints, Topology,
 };
 use discovery::TopologyWrite;
-use protocol::{Protocol, RedisFlager, Request, Resource::Redis};
+use protocol::{
+    Error, HashedCommand, Protocol, RedisFlager, Request, ResOption, Resource::Redis, Retry,
+};
 use sharding::distribution::Distribute;
 use sharding::hash::{Hash, HashKey, Hasher};
 
-use super::config::RedisNamespace;
+use super::{config::RedisNamespace, hedge::HedgeDelay};
 
 #[derive(Clone)]
 pub struct RedisService<E, P> {
@@ -18,6 +21,14 @@ pub struct RedisService<E, P> {
     distribute: Distribute,
     parser: P,
     cfg: Box<DnsConfig<RedisNamespace>>,
+    // 当前endpoints使用的鉴权及db信息，变更后需要重建endpoints
+    auth: ResOption,
+    mirror: Mirror<E>,
+    // 分片迁移期间，迁移前的分片
+    old: Option<Shards<E>>,
+    // 读请求的重试策略
+    retry: Retry,
+    hedge: HedgeDelay,
 }
 impl<E, P> From<P> for RedisService<E, P> {
     #[inline]
@@ -28,6 +39,11 @@ impl<E, P> From<P> for RedisService<E, P> {
             hasher: Default::default(),
             distribute: Default::default(),
             cfg: Default::default(),
+            auth: Default::default(),
+            mirror: Mirror::from(Redis),
+            old: None,
+            retry: Default::default(),
+            hedge: Default::default(),
         }
     }
 }
@@ -49,6 +65,51 @@ where
     Req: Request,
     P: Protocol,
 {
+    #[inline]
+    fn client_password(&self) -> &str {
+        &self.cfg.basic.client_password
+    }
+    #[inline]
+    fn max_conns(&self) -> usize {
+        self.cfg.basic.max_conns
+    }
+    #[inline]
+    fn max_qps(&self) -> u32 {
+        self.cfg.basic.max_qps
+    }
+    #[inline]
+    fn max_key_len(&self) -> usize {
+        self.cfg.basic.max_key_len
+    }
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        self.cfg.basic.max_value_len
+    }
+    #[inline]
+    fn reject_big(&self) -> bool {
+        self.cfg.basic.reject_big
+    }
+    // 跨分片的请求不镜像
+    #[inline]
+    fn mirror(&self, req: &HashedCommand) -> bool {
+        !req.sendto_all() && self.mirror.sample(req)
+    }
+    #[inline]
+    fn hedge_ms(&self) -> u32 {
+        let basic = &self.cfg.basic;
+        self.hedge
+            .get(&self.shards, basic.hedge_percentile, basic.hedge_delay_ms)
+    }
+    // 只对冲有多个从的分片上的读请求；迁移期间读请求可能回退到迁移前的分片，不对冲
+    #[inline]
+    fn hedge(&self, req: &HashedCommand) -> bool {
+        if self.old.is_some() || req.operation().is_store() || req.master_only() || req.sendto_all()
+        {
+            return false;
+        }
+        let idx = self.distribute.index(req.hash());
+        self.shards.get(idx).is_some_and(|s| s.slaves.len() > 1)
+    }
 }
 
 impl<E, Req, P> Endpoint for RedisService<E, P>
@@ -61,14 +122,50 @@ where
     #[inline]
     fn send(&self, mut req: Self::Item) {
         debug_assert_ne!(self.shards.len(), 0);
+        if req.mirror() {
+            self.mirror.send(req);
+            return;
+        }
+        if let Some(old) = &self.old {
+            if !req.sendto_all() {
+                let ctx = super::transmute(req.context_mut());
+                // 写请求双写，读请求未命中时回退到迁移前的分片
+                if ctx.migrated > 0 {
+                    req.write_back(false);
+                    req.try_next(false);
+                    old.send(req);
+                    return;
+                }
+                ctx.migrated = 1;
+                if req.operation().is_store() {
+                    req.write_back(true);
+                } else {
+                    // 只有未命中时回退，请求异常（如超时）时不回退
+                    req.set_miss_notok();
+                    req.retry_on_rsp_notok(true);
+                    req.retry_on_err(false);
+                    req.try_next(true);
+                }
+            }
+        }
 
         let shard_idx = if req.sendto_all() {
-            //全节点分发请求
-            let ctx = super::transmute(req.context_mut());
-            let idx = ctx.shard_idx as usize;
-            ctx.shard_idx += 1;
-            req.write_back(idx < self.shards.len() - 1);
-            idx
+            match req.cross_shards() {
+                // 跨分片请求由协议通过hash指定分片，解析后分片数发生变化时，分片可能已不存在
+                Some(_) if req.hash() as usize >= self.shards.len() => {
+                    req.on_err(Error::TopChanged);
+                    return;
+                }
+                Some(_) => req.hash() as usize,
+                None => {
+                    //全节点分发请求
+                    let ctx = super::transmute(req.context_mut());
+                    let idx = ctx.shard_idx as usize;
+                    ctx.shard_idx += 1;
+                    req.write_back(idx < self.shards.len() - 1);
+                    idx
+                }
+            }
         } else {
             self.distribute.index(req.hash())
         };
@@ -80,11 +177,16 @@ where
 
         // 如果有从，并且是读请求，如果目标server异常，会重试其他slave节点
         if shard.has_slave() && !req.operation().is_store() && !req.master_only() {
-            if *req.context_mut() == 0 {
+            if super::transmute(req.context_mut()).runs == 0 {
                 if let Some(quota) = shard.slaves.quota() {
                     req.quota(quota);
                 }
             }
+            // 迁移期间读请求需要回退到迁移前的分片，使用默认的重试策略
+            let hedge = req.hedge();
+            if self.old.is_none() && !hedge {
+                req.retry(&self.retry);
+            }
             let ctx = super::transmute(req.context_mut());
             let (idx, endpoint) = if ctx.runs == 0 {
                 shard.select()
@@ -102,8 +204,9 @@ where
             // 1. 第一次访问. （无论如何都允许try_next，如果只有一个从，则下一次失败时访问主）
             // 2. 有多个从，访问的次数小于从的数量
             //let try_next = ctx.runs == 1 || (ctx.runs as usize) < shard.slaves.len();
-            // 只重试一次，重试次数过多，可能会导致雪崩。
-            let try_next = ctx.runs == 1;
+            // 默认只重试一次，重试次数过多，可能会导致雪崩。
+            // 对冲请求复制了原请求的ctx，会选择下一个从，且不再重试
+            let try_next = !hedge && ctx.runs as usize <= self.retry.tries() as usize;
             req.try_next(try_next);
 
             endpoint.send(req)
@@ -116,6 +219,17 @@ where
     fn shard_idx(&self, hash: i64) -> usize {
         self.distribute.index(hash)
     }
+
+    // 阻塞、订阅类指令使用client独占的连接，与共享连接使用相同的鉴权及db
+    #[inline]
+    fn master_of(&self, shard: usize) -> Option<(String, ResOption)> {
+        let shard = self.shards.get(shard)?;
+        Some((shard.master().addr().to_string(), self.auth.clone()))
+    }
+    #[inline]
+    fn shards(&self) -> usize {
+        self.shards.len()
+    }
 }
 impl<E, P> TopologyWrite for RedisService<E, P>
 where
@@ -127,6 +241,12 @@ where
         if let Some(ns) = RedisNamespace::try_from(cfg) {
             self.hasher = Hasher::from(&ns.basic.hash);
             self.distribute = Distribute::from(ns.basic.distribution.as_str(), &ns.backends);
+            let (dist, to, o) = (&ns.basic.distribution, ns.timeout_master(), ns.res_option());
+            let parser = &self.parser;
+            self.mirror
+                .update(namespace, parser, &ns.mirror, dist, to, o.clone());
+            self.update_migration(namespace, &ns, o);
+            self.retry = ns.retry.build(namespace, 2);
             self.cfg.update(namespace, ns);
         }
     }
@@ -174,6 +294,26 @@ where
     P: Protocol,
     E: Endpoint,
 {
+    // 迁移前的分片只访问master，迁移完成后释放
+    fn update_migration(&mut self, namespace: &str, ns: &RedisNamespace, o: ResOption) {
+        let mut endpoints: Endpoints<'_, P, E> =
+            Endpoints::new(namespace, &self.parser, Redis).with_option(o.clone());
+        if let Some(old) = self.old.take() {
+            if o == self.auth {
+                endpoints.cache::<E>(old.into());
+            }
+        }
+        if !ns.migration.migrating() {
+            return;
+        }
+        let dist = match ns.migration.distribution.as_str() {
+            "" => ns.basic.distribution.as_str(),
+            d => d,
+        };
+        let to = ns.timeout_master();
+        let old = Shards::from_masters(&mut endpoints, &ns.migration.backends, dist, to);
+        self.old = Some(old);
+    }
     // TODO 把load的日志级别提升，在罕见异常情况下（dns解析异常、配置异常）,持续load时可以通过日志来跟进具体状态；
     //      当然，也可以通过指标汇报的方式进行，但对这种罕见情况进行metrics消耗，需要考量；
     //      先对这种罕见情况用日志记录，确有需要，再考虑用指标汇报； 待讨论 fishermen
@@ -183,13 +323,18 @@ where
         assert_eq!(addrs.len(), self.cfg.shards_url.len());
         // 到这之后，所有的shard都能解析出ip
 
-        // 把所有的endpoints cache下来
+        // 把所有的endpoints cache下来，鉴权信息、db或熔断配置变更后，老的endpoints不再复用
+        let auth = self.cfg.res_option();
         let mut endpoints: Endpoints<'_, P, E> =
-            Endpoints::new(&self.cfg.service, &self.parser, Redis);
-        self.shards.split_off(0).into_iter().for_each(|shard| {
-            endpoints.cache_one(shard.master);
-            endpoints.cache(shard.slaves.into_inner());
-        });
+            Endpoints::new(&self.cfg.service, &self.parser, Redis).with_option(auth.clone());
+        let old = self.shards.split_off(0);
+        if auth == self.auth {
+            old.into_iter().for_each(|shard| {
+                endpoints.cache_one(shard.master);
+                endpoints.cache(shard.slaves.into_inner());
+            });
+        }
+        self.auth = auth;
 
         // 遍历所有的shards_url
         addrs.iter().for_each(|ips| {
@@ -199,7 +344,7 @@ where
             let oft = if self.cfg.basic.master_read { 0 } else { 1 };
             let slaves = endpoints.take_or_build(&ips[oft..], self.cfg.timeout_slave());
             let shard = Shard::selector(
-                self.cfg.basic.selector.tuning_mode(),
+                &self.cfg.basic.selector,
                 master,
                 slaves,
                 self.cfg.basic.region_enabled,
@@ -0,0 +1,41 @@
+use protocol::{Retry, RetryBudget, RETRY_ALL, RETRY_CHAN_FULL, RETRY_RSP_NOTOK, RETRY_TIMEOUT};
+use serde::{Deserialize, Serialize};
+
+// namespace级别的重试策略，未配置时保持各资源原有的重试行为
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Hash)]
+pub struct RetryConfig {
+    // 包含首次请求在内的最大请求次数，0表示使用资源的默认值，1表示不重试
+    #[serde(default)]
+    pub max_attempts: u8,
+    // 可重试的错误：timeout、chan_full、rsp_notok，为空时均可重试。
+    // 未发送成功的错误（如连接断开）始终可以重试
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+    // 每秒重试次数占请求数的最大百分比，0表示不限制
+    #[serde(default)]
+    pub budget_percent: u8,
+}
+
+impl RetryConfig {
+    // attempts：资源默认的最大请求次数
+    pub fn build(&self, service: &str, attempts: u8) -> Retry {
+        let attempts = match self.max_attempts {
+            0 => attempts,
+            n => n,
+        };
+        let mut on = 0;
+        for kind in self.retry_on.iter() {
+            match kind.as_str() {
+                "timeout" => on |= RETRY_TIMEOUT,
+                "chan_full" => on |= RETRY_CHAN_FULL,
+                "rsp_notok" => on |= RETRY_RSP_NOTOK,
+                _ => log::warn!("{} unknown retry_on:{}", service, kind),
+            }
+        }
+        if self.retry_on.is_empty() {
+            on = RETRY_ALL;
+        }
+        let budget = (self.budget_percent > 0).then(|| RetryBudget::new(self.budget_percent));
+        Retry::new(attempts.max(1) - 1, on, budget)
+    }
+}
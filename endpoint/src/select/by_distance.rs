@@ -3,6 +3,8 @@ use protocol::BackendQuota;
 use rand::Rng;
 use std::sync::atomic::{AtomicUsize, Ordering::*};
 
+use super::Selector;
+
 // 选择replica策略，len_local指示的是优先访问的replicas。
 // 1. cacheservice因存在跨机房同步、优先访问本地机房，当前机房的replicas为local
 // 2. 其他资源，replicas长度与len_local相同
@@ -11,6 +13,8 @@ pub struct Distance<T> {
     len_region: u16, // 通过排序计算出的可用区内的实例数量，len_region <= len_local
     backend_quota: bool,
     region_enabled: bool,
+    // ewma、p2c按负载选择local实例，其他按轮询或时间配额选择
+    selector: Selector,
     idx: AtomicUsize,
     replicas: Vec<(T, BackendQuota)>,
 }
@@ -22,6 +26,7 @@ impl<T: Clone> Clone for Distance<T> {
             len_region: self.len_region.clone(),
             backend_quota: self.backend_quota.clone(),
             region_enabled: self.region_enabled.clone(),
+            selector: self.selector,
             //不同Distance之间没必要共享idx，也许应该设置为0，但当前对外暴露的更新接口更新replicas时都会更新idx，没有问题，否则可能产生越界
             //警告：更新replicas需要同时更新idx
             idx: self.idx.load(Relaxed).into(),
@@ -36,6 +41,7 @@ impl<T> Distance<T> {
             len_region: 0,
             backend_quota: false,
             region_enabled: false,
+            selector: Selector::ByDistance,
             idx: Default::default(),
             replicas: Vec::new(),
         }
@@ -93,6 +99,12 @@ impl<T> Distance<T> {
 
         me
     }
+    // 按负载选择时，不再使用时间配额
+    pub fn with_selector(mut self, selector: Selector) -> Self {
+        self.backend_quota &= !selector.by_load();
+        self.selector = selector;
+        self
+    }
     // None说明没有启动
     pub fn len_region(&self) -> Option<u16> {
         self.region_enabled.then(|| self.len_region)
@@ -113,7 +125,8 @@ impl<T> Distance<T> {
     }
     //和新建不等价，谨慎使用
     pub fn update(&mut self, replicas: Vec<T>, topn: usize, is_performance: bool) {
-        self.backend_quota = is_performance; // 性能模式当前实现为按时间quota访问后端资源
+        // 性能模式当前实现为按时间quota访问后端资源，按负载选择时不使用
+        self.backend_quota = is_performance && !self.selector.by_load();
         self.refresh(replicas);
         self.topn(topn);
     }
@@ -176,19 +189,80 @@ impl<T> Distance<T> {
     }
     // 从local选择一个实例
     #[inline]
-    pub fn select_idx(&self) -> usize {
+    pub fn select_idx(&self) -> usize
+    where
+        T: Endpoint,
+    {
         assert_ne!(self.len(), 0);
         let idx = if self.len() == 1 {
             0
         } else {
-            self.check_quota_get_idx()
+            match self.selector {
+                Selector::Ewma => self.least_load_idx(),
+                Selector::P2c => self.p2c_idx(),
+                _ => self.available_idx(self.check_quota_get_idx()),
+            }
         };
         debug_assert!(idx < self.local_len(), "idx:{} < {}", idx, self.local_len());
         idx
     }
+    // 跳过被熔断摘除的实例，local都不可用时仍使用原实例。
+    // available只读，摘除到期的实例在被选中并发送请求时才开始探测
+    #[inline]
+    fn available_idx(&self, idx: usize) -> usize
+    where
+        T: Endpoint,
+    {
+        if self.replicas[idx].0.available() {
+            return idx;
+        }
+        (1..self.local_len())
+            .map(|i| (idx + i) % self.local_len())
+            .find(|&i| self.replicas[i].0.available())
+            .unwrap_or(idx)
+    }
+    // 选择local中负载最小的可用实例，负载相同时轮询
+    #[inline]
+    fn least_load_idx(&self) -> usize
+    where
+        T: Endpoint,
+    {
+        let start = self.idx.fetch_add(1, Relaxed) % self.local_len();
+        let mut min = (u64::MAX, start);
+        for i in (0..self.local_len()).map(|i| (start + i) % self.local_len()) {
+            let r = &self.replicas[i].0;
+            if r.available() && r.load() < min.0 {
+                min = (r.load(), i);
+            }
+        }
+        min.1
+    }
+    // power of two choices：随机选择两个local实例，取负载较小的可用实例
+    #[inline]
+    fn p2c_idx(&self) -> usize
+    where
+        T: Endpoint,
+    {
+        if self.local_len() == 1 {
+            return self.available_idx(0);
+        }
+        let mut rng = rand::thread_rng();
+        let a = rng.gen_range(0..self.local_len());
+        let b = (a + rng.gen_range(1..self.local_len())) % self.local_len();
+        let (ra, rb) = (&self.replicas[a].0, &self.replicas[b].0);
+        match (ra.available(), rb.available()) {
+            (true, true) if rb.load() < ra.load() => b,
+            (true, _) => a,
+            (false, true) => b,
+            (false, false) => self.available_idx(a),
+        }
+    }
     // 只从local获取
     #[inline]
-    pub fn unsafe_select(&self) -> (usize, &T) {
+    pub fn unsafe_select(&self) -> (usize, &T)
+    where
+        T: Endpoint,
+    {
         let idx = self.select_idx();
         (idx, unsafe { &self.replicas.get_unchecked(idx).0 })
     }
@@ -8,12 +8,21 @@ pub use by_distance::*;
 
 //use discovery::distance::*;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Selector {
     Random,
     ByDistance,
+    // 选择ewma rtt与在途请求数综合负载最小的实例
+    Ewma,
+    // 随机选择两个实例，取负载较小的一个
+    P2c,
 }
 impl Selector {
+    // 按负载选择，不使用时间配额
+    #[inline]
+    pub fn by_load(&self) -> bool {
+        matches!(self, Self::Ewma | Self::P2c)
+    }
     // pub fn is_local(&self) -> bool {
     //     match self {
     //         Self::Random => false,
@@ -26,6 +35,8 @@ impl From<&str> for Selector {
     fn from(selector: &str) -> Self {
         match selector {
             "random" => Self::Random,
+            "ewma" => Self::Ewma,
+            "p2c" => Self::P2c,
             _ => Self::ByDistance,
         }
     }
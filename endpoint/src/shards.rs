@@ -53,14 +53,37 @@ impl<E: Endpoint> Into<Vec<E>> for Shards<E> {
 }
 
 impl<E: Endpoint> Shards<E> {
-    pub fn from_dist(dist: &str, group: Vec<E>) -> Self {
-        let addrs = group.iter().map(|e| e.addr()).collect::<Vec<_>>();
-        let router = Distribute::from(dist, &addrs);
+    // 节点可配置为host:port:weight，权重只用于ketama分布，连接时使用host:port
+    pub(crate) fn from_weighted<P: protocol::Protocol>(
+        endpoints: &mut crate::Endpoints<'_, P, E>,
+        names: &[String],
+        dist: &str,
+        to: crate::Timeout,
+    ) -> Self {
+        let addrs: Vec<String> = (names.iter())
+            .map(|n| sharding::distribution::split_weight(n).0.to_string())
+            .collect();
+        let router = match Distribute::weighted(dist) {
+            true => Distribute::from(dist, names),
+            false => Distribute::from(dist, &addrs),
+        };
         Self {
             router,
-            backends: group,
+            backends: endpoints.take_or_build(&addrs, to),
         }
     }
+    // 每个分片只使用一个地址，分片配置为"master,slave"时只使用master
+    pub(crate) fn from_masters<P: protocol::Protocol>(
+        endpoints: &mut crate::Endpoints<'_, P, E>,
+        backends: &[String],
+        dist: &str,
+        to: crate::Timeout,
+    ) -> Self {
+        let names: Vec<String> = (backends.iter())
+            .map(|b| b.split(',').next().unwrap_or_default().to_string())
+            .collect();
+        Self::from_weighted(endpoints, &names, dist, to)
+    }
 }
 
 use discovery::distance::Addr;
@@ -74,7 +97,7 @@ impl<E: Endpoint> Addr for Shards<E> {
     }
 }
 
-use crate::select::Distance;
+use crate::select::{Distance, Selector};
 #[derive(Clone)]
 pub struct Shard<E> {
     pub(crate) master: E,
@@ -82,10 +105,12 @@ pub struct Shard<E> {
 }
 impl<E: Endpoint> Shard<E> {
     #[inline]
-    pub fn selector(performance: bool, master: E, replicas: Vec<E>, region_enabled: bool) -> Self {
+    pub fn selector(selector: &str, master: E, replicas: Vec<E>, region_enabled: bool) -> Self {
+        use crate::PerformanceTuning;
+        let slaves = Distance::with_mode(replicas, selector.tuning_mode(), region_enabled);
         Self {
             master,
-            slaves: Distance::with_mode(replicas, performance, region_enabled),
+            slaves: slaves.with_selector(Selector::from(selector)),
         }
     }
 }
@@ -99,7 +124,10 @@ impl<E> Shard<E> {
         &self.master
     }
     #[inline]
-    pub(crate) fn select(&self) -> (usize, &E) {
+    pub(crate) fn select(&self) -> (usize, &E)
+    where
+        E: Endpoint,
+    {
         self.slaves.unsafe_select()
     }
     #[inline]
@@ -1,5 +1,5 @@
 use discovery::{Inited, TopologyWrite};
-use protocol::{Protocol, Request, ResOption, Resource};
+use protocol::{Command, HashedCommand, Protocol, Request, ResOption, Resource};
 use sharding::hash::{Hash, HashKey};
 
 use crate::Timeout;
@@ -15,6 +15,7 @@ procs::topology_dispatcher! {
     pub enum Topologies<E, P> {
         MsgQue(crate::msgque::topo::MsgQue<E, P>),
         RedisService(crate::redisservice::topo::RedisService<E, P>),
+        RedisCluster(crate::rediscluster::topo::RedisCluster<E, P>),
         CacheService(crate::cacheservice::topo::CacheService<E, P>),
         PhantomService(crate::phantomservice::topo::PhantomService<E, P>),
         KvService(crate::kv::topo::KvService<E, P>),
@@ -25,15 +26,41 @@ procs::topology_dispatcher! {
         type Item;
         fn send(&self, req: Self::Item);
         fn shard_idx(&self, _hash: i64) -> usize {todo!("shard_idx not implemented");}
+        // 实例是否可用于选择，只读，不改变实例状态
         fn available(&self) -> bool {todo!("available not implemented");}
         fn addr(&self) -> &str {"addr not implemented"}
+        // 实例的负载，ewma rtt(us)乘以(在途请求数+1)，用于按负载选择副本
+        fn load(&self) -> u64 {0}
+        // 实例响应耗时(us)的分位数，p取值1~100，样本数不足时为0，用于按分位数对冲
+        fn rtt_percentile(&self, _p: u8) -> u64 {0}
+        // 分片master的地址及鉴权信息，用于建立client独占的后端连接
+        fn master_of(&self, _shard: usize) -> Option<(String, ResOption)> {None}
+        // 分片数，用于跨分片的请求，如redis的scan
+        fn shards(&self) -> usize {1}
         fn build_o<P:Protocol>(_addr: &str, _p: P, _r: Resource, _service: &str, _to: Timeout, _o: ResOption) -> Self {todo!("build not implemented")}
         fn build<P:Protocol>(addr: &str, p: P, r: Resource, service: &str, to: Timeout) -> Self {Self::build_o(addr, p, r, service, to, Default::default())}
-    } => where P:Protocol, E:Endpoint<Item = R> + Inited, R: Request
+    } => where P:Protocol, E:Endpoint<Item = R> + Inited + Clone + 'static, R: Request
 
     pub trait Topology : Endpoint + Hash{
         fn exp_sec(&self) -> u32 {86400}
-    } => where P:Protocol, E:Endpoint<Item = R>, R:Request, Topologies<E, P>: Endpoint
+        // client连接的鉴权密码，为空时不需要鉴权
+        fn client_password(&self) -> &str {""}
+        // client的最大连接数、最大qps，为0时不限制
+        fn max_conns(&self) -> usize {0}
+        fn max_qps(&self) -> u32 {0}
+        // big key/value的阈值，为0时不检测；超过阈值时是否拒绝请求
+        fn max_key_len(&self) -> usize {0}
+        fn max_value_len(&self) -> usize {0}
+        fn reject_big(&self) -> bool {false}
+        // 请求是否需要复制一份发送到镜像集群，镜像请求通过Request::mirror区分
+        fn mirror(&self, _req: &HashedCommand) -> bool {false}
+        // 读请求超过该耗时未返回时，向另一个副本发送对冲请求，先返回的响应胜出；为0时不对冲
+        fn hedge_ms(&self) -> u32 {0}
+        // 请求是否可以对冲，对冲请求通过Request::hedge区分
+        fn hedge(&self, _req: &HashedCommand) -> bool {false}
+        // 收到后端响应，在写给client之前回调，如cacheservice的near cache
+        fn on_response(&self, _req: &HashedCommand, _rsp: &Command) {}
+    } => where P:Protocol, E:Endpoint<Item = R> + Clone + 'static, R:Request, Topologies<E, P>: Endpoint
 
     trait Inited {
         fn inited(&self) -> bool;
@@ -44,7 +71,7 @@ procs::topology_dispatcher! {
         fn disgroup<'a>(&self, _path: &'a str, cfg: &'a str) -> Vec<(&'a str, &'a str)>;
         fn need_load(&self) -> bool;
         fn load(&mut self) -> bool;
-    } => where P:Protocol, E:Endpoint
+    } => where P:Protocol, E:Endpoint + Clone + 'static
 
     trait Hash {
         fn hash<S: HashKey>(&self, key: &S) -> i64;
@@ -62,10 +89,10 @@ pub(crate) trait PerformanceTuning {
     fn tuning_mode(&self) -> bool;
 }
 
-impl PerformanceTuning for String {
+impl PerformanceTuning for str {
     fn tuning_mode(&self) -> bool {
         is_performance_tuning_from_env()
-            || match self.as_str() {
+            || match self {
                 "distance" | "timeslice" => true,
                 _ => false,
             }
@@ -105,6 +132,8 @@ pub struct Endpoints<'a, P, E: Endpoint> {
     parser: &'a P,
     resource: Resource,
     cache: HashMap<String, Vec<E>>,
+    // 新建endpoint时使用的鉴权信息
+    option: ResOption,
 }
 impl<'a, P, E: Endpoint> Endpoints<'a, P, E> {
     pub fn new(service: &'a str, parser: &'a P, resource: Resource) -> Self {
@@ -113,8 +142,13 @@ impl<'a, P, E: Endpoint> Endpoints<'a, P, E> {
             parser,
             resource,
             cache: HashMap::new(),
+            option: Default::default(),
         }
     }
+    pub fn with_option(mut self, option: ResOption) -> Self {
+        self.option = option;
+        self
+    }
     pub fn cache_one<T: Into<Pair<E>>>(&mut self, endpoint: T) {
         self.cache(vec![endpoint]);
     }
@@ -149,7 +183,8 @@ impl<'a, P: Protocol, E: Endpoint> Endpoints<'a, P, E> {
                     .flatten()
                     .unwrap_or_else(|| {
                         let p = self.parser.clone();
-                        E::build(&addr, p, self.resource, self.service, to)
+                        let o = self.option.clone();
+                        E::build_o(&addr, p, self.resource, self.service, to, o)
                     })
             })
             .collect()
@@ -1,6 +1,6 @@
 use crate::{
     dns::{DnsConfig, DnsLookup},
-    select::Distance,
+    select::{Distance, Selector},
     Endpoint, Endpoints, PerformanceTuning, Topology,
 };
 use discovery::TopologyWrite;
@@ -132,11 +132,13 @@ where
         let mut endpoints: Endpoints<'_, P, E> =
             Endpoints::new(&self.cfg.service, &self.parser, Uuid).with_cache(self.shard.take());
         let backends = endpoints.take_or_build(&addrs, self.cfg.timeout());
+        let selector = &self.cfg.basic.selector;
         self.shard = Distance::with_mode(
             backends,
-            self.cfg.basic.selector.tuning_mode(),
+            selector.tuning_mode(),
             self.cfg.basic.region_enabled,
-        );
+        )
+        .with_selector(Selector::from(selector.as_str()));
 
         log::info!("{} load backends. dropping:{}", self, endpoints);
         Some(())
@@ -152,12 +152,16 @@ fn get_register() -> &'static Sender<Op> {
 pub mod tests {
     use super::*;
     static mut TEST_RECEIVER: Option<Receiver<Op>> = None;
+    static INIT: std::sync::Once = std::sync::Once::new();
+    // 多个测试用例会并发调用，只初始化一次
     pub fn init_metrics_onlyfor_test() {
-        let (register_tx, chan_rx) = unbounded_channel();
-        let (_tx, rx) = ds::cow(Metrics::new());
-        unsafe { SENDER = Some(register_tx) };
-        let _ = METRICS.set(rx).map_err(|_e| panic!("init"));
-        unsafe { TEST_RECEIVER = Some(chan_rx) };
+        INIT.call_once(|| {
+            let (register_tx, chan_rx) = unbounded_channel();
+            let (_tx, rx) = ds::cow(Metrics::new());
+            unsafe { SENDER = Some(register_tx) };
+            let _ = METRICS.set(rx).map_err(|_e| panic!("init"));
+            unsafe { TEST_RECEIVER = Some(chan_rx) };
+        });
     }
 }
 
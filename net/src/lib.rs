@@ -1,5 +1,6 @@
 mod stream;
 pub use stream::*;
+pub mod tls;
 
 pub trait StreamInit {
     #[inline]
@@ -14,3 +15,6 @@ impl StreamInit for tokio::net::TcpStream {
         let _ = self.set_nodelay(true);
     }
 }
+
+// 握手前已设置nodelay
+impl StreamInit for tls::TlsStream {}
@@ -104,8 +104,24 @@ impl Bind for tokio::net::UnixListener {
         Self::bind(addr)
     }
 }
+impl Bind for crate::tls::TlsListener {
+    async fn binding(addr: &str) -> Result<Self> {
+        Self::bind(addr).await
+    }
+}
 
 define_stream!(
     "unix", Unix,    tokio::net::UnixStream,     tokio::net::UnixListener,    tokio::net::unix::SocketAddr;
-    "tcp",  Tcp,     tokio::net::TcpStream,      tokio::net::TcpListener,     std::net::SocketAddr
+    "tcp",  Tcp,     tokio::net::TcpStream,      tokio::net::TcpListener,     std::net::SocketAddr;
+    "tls",  Tls,     crate::tls::TlsStream,      crate::tls::TlsListener,     std::net::SocketAddr
 );
+
+// 连接后端，tls为true时，连接建立后完成tls握手
+pub async fn connect(addr: &str, tls: bool) -> Result<Stream> {
+    let tcp = tokio::net::TcpStream::connect(addr).await?;
+    let _ = tcp.set_nodelay(true);
+    match tls {
+        true => Ok(Stream::Tls(crate::tls::connect(addr, tcp).await?)),
+        false => Ok(Stream::Tcp(tcp)),
+    }
+}
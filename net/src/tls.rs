@@ -0,0 +1,141 @@
+use std::io::{Error, ErrorKind, Result};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_native_tls::{native_tls, TlsAcceptor, TlsConnector};
+
+pub type TlsStream = tokio_native_tls::TlsStream<TcpStream>;
+
+// client侧：监听tls端口时使用的证书，未配置时不能监听tls
+static ACCEPTOR: OnceLock<TlsAcceptor> = OnceLock::new();
+// 后端侧：校验后端证书，未配置CA时使用系统默认的CA
+static CONNECTOR: OnceLock<TlsConnector> = OnceLock::new();
+
+// 握手超时，避免慢连接一直占用资源
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// 已完成握手，等待accept的连接数
+const BACKLOG: usize = 1024;
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+// 加载证书，进程启动时调用一次。
+// cert、key: pem格式的证书链及pkcs8私钥，需要同时配置；
+// ca: pem格式的CA证书，可以包含多个，用于校验后端证书。
+pub fn init(cert: &str, key: &str, ca: &str) -> Result<()> {
+    if cert.is_empty() != key.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "tls cert and key must be configured together",
+        ));
+    }
+    if !cert.is_empty() {
+        let (cert, key) = (std::fs::read(cert)?, std::fs::read(key)?);
+        let identity = native_tls::Identity::from_pkcs8(&cert, &key).map_err(invalid)?;
+        let acceptor = native_tls::TlsAcceptor::new(identity).map_err(invalid)?;
+        let _ = ACCEPTOR.set(acceptor.into());
+    }
+    let mut builder = native_tls::TlsConnector::builder();
+    if !ca.is_empty() {
+        let pem = std::fs::read_to_string(ca)?;
+        for one in pem.split_inclusive(PEM_END).filter(|c| c.contains(PEM_END)) {
+            let cert = native_tls::Certificate::from_pem(one.as_bytes()).map_err(invalid)?;
+            builder.add_root_certificate(cert);
+        }
+    }
+    let _ = CONNECTOR.set(builder.build().map_err(invalid)?.into());
+    Ok(())
+}
+
+// 在已建立的tcp连接上与后端握手，使用addr中的host校验证书
+pub async fn connect(addr: &str, tcp: TcpStream) -> Result<TlsStream> {
+    let connector = match CONNECTOR.get() {
+        Some(connector) => connector,
+        None => {
+            let connector = native_tls::TlsConnector::new().map_err(invalid)?;
+            CONNECTOR.get_or_init(|| connector.into())
+        }
+    };
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    connector.connect(host, tcp).await.map_err(invalid)
+}
+
+// tls监听：accept tcp连接后，在独立的task中握手，握手完成后才返回给调用方，
+// 避免单个慢连接的握手阻塞其他连接的accept。
+pub struct TlsListener {
+    rx: Mutex<mpsc::Receiver<Result<(TlsStream, SocketAddr)>>>,
+    task: JoinHandle<()>,
+    local: SocketAddr,
+}
+
+impl TlsListener {
+    pub async fn bind(addr: &str) -> Result<Self> {
+        let acceptor = ACCEPTOR
+            .get()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "tls cert not configured"))?;
+        let l = TcpListener::bind(addr).await?;
+        let local = l.local_addr()?;
+        let (tx, rx) = mpsc::channel(BACKLOG);
+        let task = tokio::spawn(accepting(l, acceptor.clone(), tx));
+        Ok(Self {
+            rx: Mutex::new(rx),
+            task,
+            local,
+        })
+    }
+    // 实际监听的地址，如绑定0端口时由系统分配的端口
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.local)
+    }
+    pub async fn accept(&self) -> Result<(TlsStream, SocketAddr)> {
+        let mut rx = self.rx.lock().await;
+        match rx.recv().await {
+            Some(accepted) => accepted,
+            None => Err(Error::new(ErrorKind::BrokenPipe, "tls listener closed")),
+        }
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn accepting(
+    l: TcpListener,
+    acceptor: TlsAcceptor,
+    tx: mpsc::Sender<Result<(TlsStream, SocketAddr)>>,
+) {
+    loop {
+        match l.accept().await {
+            Ok((tcp, addr)) => {
+                let _ = tcp.set_nodelay(true);
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send(Ok((stream, addr))).await;
+                        }
+                        Ok(Err(_e)) => log::info!("tls handshake with {} failed:{:?}", addr, _e),
+                        Err(_) => log::info!("tls handshake with {} timeout", addr),
+                    }
+                });
+            }
+            // accept失败与tcp一致，返回给调用方处理
+            Err(e) => {
+                if tx.send(Err(e)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn invalid<E: std::fmt::Display>(e: E) -> Error {
+    Error::new(ErrorKind::InvalidData, e.to_string())
+}
@@ -92,6 +92,10 @@ pub fn topology_dispatcher(input: TokenStream) -> TokenStream {
         if s.eq("PhantomService") {
             endpoints.push("pt".to_string());
         }
+        // RedisCluster的第一个单词与RedisService相同，使用全名
+        if s.eq("RedisCluster") {
+            endpoints[0] = s.to_lowercase();
+        }
 
         quote! {
             #(#endpoints) | * => Ok(Self::#variant_ident(p.into())),
@@ -10,7 +10,7 @@ use std::{
 use crate::BackendQuota;
 use ds::{time::Instant, AtomicWaker};
 
-use crate::{request::Request, Command, Error, HashedCommand};
+use crate::{request::Request, Command, Error, HashedCommand, Retry, RETRY_RSP_NOTOK};
 
 //const REQ_TRY_MAX_COUNT: u8 = 3;
 
@@ -32,14 +32,21 @@ impl Callback {
 pub struct CallbackContext {
     pub(crate) flag: crate::Context,
     async_mode: bool,                    // 是否是异步请求
+    mirror: bool,                        // 是否是发送到镜像集群的请求
+    hedge: bool,                         // 是否是对冲请求
+    lost: AtomicBool,                    // 对冲请求中未胜出的一方，响应直接丢弃
     done: AtomicBool,                    // 当前模式请求是否完成
     inited: AtomicBool,                  // response是否已经初始化
     pub(crate) try_next: bool,           // 请求失败后，topo层面是否允许重试
     pub(crate) retry_on_rsp_notok: bool, // 有响应且响应不ok时，协议层面是否允许重试
+    pub(crate) retry_on_err: bool,       // 请求异常（如超时、连接断开）时，topo层面是否允许重试
     pub(crate) write_back: bool,         // 请求结束后，是否需要回写。
     first: bool,                         // 当前请求是否是所有子请求的第一个
     last: bool,                          // 当前请求是否是所有子请求的最后一个
     tries: AtomicU8,
+    retry_limited: bool, // 是否因重试预算不足而放弃重试
+    failed: bool,        // 请求是否以错误结束，镜像请求据此统计错误
+    retry: Retry,        // 重试次数、可重试的错误及重试预算
     request: HashedCommand,
     response: MaybeUninit<Command>,
     start: Instant, // 请求的开始时间
@@ -67,14 +74,21 @@ impl CallbackContext {
             done: AtomicBool::new(false),
             inited: AtomicBool::new(false),
             async_mode: false,
+            mirror: false,
+            hedge: false,
+            lost: AtomicBool::new(false),
             try_next: false,
             retry_on_rsp_notok,
+            retry_on_err: true,
             write_back: false,
             request: req,
             response: MaybeUninit::uninit(),
             callback: cb,
             start: now,
             tries: 0.into(),
+            retry_limited: false,
+            failed: false,
+            retry: Retry::default(),
             waker,
             quota: None,
         }
@@ -112,8 +126,9 @@ impl CallbackContext {
     #[inline]
     pub fn on_complete(&mut self, resp: Command) {
         log::debug!("on-complete:{} resp:{}", self, resp);
+        self.failed = false;
         // 异步请求不关注response。
-        if !self.async_mode {
+        if !self.async_mode && !self.lost() {
             debug_assert!(!self.complete(), "{:?}", self);
             self.swap_response(resp);
         }
@@ -133,8 +148,12 @@ impl CallbackContext {
     }
 
     #[inline]
-    fn need_gone(&self) -> bool {
+    fn need_gone(&mut self) -> bool {
         if !self.async_mode {
+            // 对冲请求中未胜出的一方不再重试
+            if self.lost() {
+                return false;
+            }
             // 当前重试条件为 rsp == None || ("mc" && !rsp.ok())
             if self.inited() {
                 // 优先筛出正常的请求，便于理解
@@ -147,7 +166,18 @@ impl CallbackContext {
                     return false;
                 }
             }
-            self.try_next && self.tries.fetch_add(1, Release) < 1
+            if !self.try_next || self.tries.load(Acquire) >= self.retry.tries() {
+                return false;
+            }
+            // 重试预算不足时放弃重试，避免后端抖动时重试放大流量
+            if let Some(budget) = self.retry.budget() {
+                if !budget.acquire() {
+                    self.retry_limited = true;
+                    return false;
+                }
+            }
+            self.tries.fetch_add(1, Release);
+            true
         } else {
             // write back请求
             self.write_back
@@ -177,7 +207,7 @@ impl CallbackContext {
 
     #[inline]
     pub fn async_done(&self) -> bool {
-        debug_assert!(self.async_mode, "{:?}", self);
+        debug_assert!(self.async_mode || self.lost(), "{:?}", self);
         self.done.load(Acquire)
     }
 
@@ -185,9 +215,16 @@ impl CallbackContext {
     pub fn on_err(&mut self, err: Error) {
         // 正常err场景，仅仅在debug时check
         log::debug!("+++ on_err: {:?} => {:?}", err, self);
+        // 不可重试的错误，如配置了超时不重试
+        if !self.retry_on_err || !self.retry.on_err(&err) {
+            self.try_next = false;
+        }
+        self.failed = true;
         use Error::*;
         match err {
-            Closed | ChanDisabled | Waiting | Pending => {}
+            Closed | ChanDisabled | Waiting | Pending | RateLimited | BigRequest => {}
+            // 镜像请求的错误由pipeline单独统计，不逐条记录日志
+            _ if self.mirror => {}
             _err => log::warn!("on-err:{} {:?}", self, _err),
         }
         // 一次错误至少消耗500ms的配额
@@ -196,6 +233,13 @@ impl CallbackContext {
             .map(|q| q.err_incr(self.start_at().elapsed()));
         self.on_done();
     }
+    // 重试前发现无需重试，以已有的响应（或无响应）结束请求
+    #[inline]
+    pub fn on_finish(&mut self) {
+        log::debug!("on-finish:{}", self);
+        self.try_next = false;
+        self.on_done();
+    }
     #[inline]
     pub fn request(&self) -> &HashedCommand {
         &self.request
@@ -210,10 +254,19 @@ impl CallbackContext {
         self.response.assume_init_ref()
     }
     #[inline]
+    pub fn response(&self) -> Option<&Command> {
+        self.inited().then(|| unsafe { self.unchecked_response() })
+    }
+    #[inline]
     pub fn complete(&self) -> bool {
         debug_assert!(!self.async_mode, "{:?}", self);
         self.done.load(Acquire)
     }
+    // 请求已结束，且有ok的响应
+    #[inline]
+    pub fn succeeded(&self) -> bool {
+        self.complete() && self.response().is_some_and(|r| r.ok())
+    }
     #[inline]
     pub fn inited(&self) -> bool {
         self.inited.load(Acquire)
@@ -253,6 +306,37 @@ impl CallbackContext {
             .compare_exchange(true, false, AcqRel, Relaxed)
             .expect("sync mode not done");
     }
+    // 镜像请求：未发送前即为异步请求，不关注响应
+    #[inline]
+    pub fn mirror_mode(&mut self) {
+        debug_assert!(!self.complete() && !self.async_mode, "{:?}", self);
+        self.async_mode = true;
+        self.mirror = true;
+    }
+    #[inline]
+    pub fn is_mirror(&self) -> bool {
+        self.mirror
+    }
+    // 对冲请求：复制原请求的topo上下文，由topo据此选择另一个副本
+    #[inline]
+    pub fn hedge_mode(&mut self, flag: crate::Context) {
+        debug_assert!(!self.complete() && !self.async_mode, "{:?}", self);
+        self.hedge = true;
+        self.flag = flag;
+    }
+    #[inline]
+    pub fn is_hedge(&self) -> bool {
+        self.hedge
+    }
+    // 对冲请求中另一方已胜出，当前请求的响应不再需要，结束后由pipeline回收
+    #[inline]
+    pub fn lose(&self) {
+        self.lost.store(true, Release);
+    }
+    #[inline]
+    pub fn lost(&self) -> bool {
+        self.lost.load(Acquire)
+    }
     #[inline]
     pub fn with_request(&mut self, req: HashedCommand) {
         debug_assert!(self.async_mode, "{:?}", self);
@@ -280,6 +364,28 @@ impl CallbackContext {
     pub fn quota(&mut self, quota: BackendQuota) {
         self.quota = Some(quota);
     }
+    // 首次发送时计入重试预算的请求数；重试时再次设置不重复计数
+    #[inline]
+    pub fn retry(&mut self, retry: &Retry) {
+        if let (0, Some(budget)) = (self.tries.load(Acquire), retry.budget()) {
+            budget.on_request();
+        }
+        self.retry_on_rsp_notok &= retry.on(RETRY_RSP_NOTOK);
+        self.retry = retry.clone();
+    }
+    // 已重试的次数
+    #[inline]
+    pub fn tries(&self) -> u8 {
+        self.tries.load(Acquire)
+    }
+    #[inline]
+    pub fn retry_limited(&self) -> bool {
+        self.retry_limited
+    }
+    #[inline]
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
 }
 
 impl Drop for CallbackContext {
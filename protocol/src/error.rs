@@ -34,6 +34,8 @@ pub enum Error {
     Waiting, // 连接退出时，有请求已发送，但未接收到response
     IO(std::io::ErrorKind),
     AuthFailed,
+    RateLimited, // client请求超过了qps限制
+    BigRequest,  // 请求的key或value超过了阈值
 }
 
 impl From<std::io::Error> for Error {
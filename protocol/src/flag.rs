@@ -1,14 +1,47 @@
 use crate::{HashedCommand, OpCode, Operation};
 pub type FlagExt = u64;
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Flag {
     op_code: OpCode,
     op: Operation,
     sentonly: bool,
     noforward: bool,
+    dedicated: Dedicated,
     v: FlagExt,
 }
 
+// 需要client独占后端连接的请求，如redis的阻塞指令、订阅类指令
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Dedicated {
+    #[default]
+    None = 0,
+    // 阻塞指令：一个请求对应一个响应，但响应时间不确定，发送到hash对应分片的独占连接
+    Block,
+    // 订阅：发送到hash对应分片的订阅连接，响应均由后端推送
+    Subscribe,
+    // 取消订阅：发送到hash对应分片的订阅连接
+    Unsubscribe,
+    // 取消所有订阅：发送到所有已建立的订阅连接
+    UnsubscribeAll,
+    // 模式订阅及取消：发送到所有分片的模式订阅连接
+    Pattern,
+    // watch：发送到hash对应分片的独占连接，并记录watch所在的分片
+    Watch,
+    // 会清理watch的请求，如unwatch、事务中的discard、事务的exec：
+    // 有watch时发送到watch所在的独占连接，否则按普通请求处理
+    Unwatch,
+}
+
+impl Dedicated {
+    // 订阅类请求，响应由后端推送，不需要按请求返回
+    #[inline]
+    pub fn push(&self) -> bool {
+        use Dedicated::*;
+        !matches!(self, None | Block | Watch | Unwatch)
+    }
+}
+
 use ds::Ext;
 impl Ext for Flag {
     #[inline]
@@ -73,6 +106,15 @@ impl Flag {
         self.noforward
     }
     #[inline]
+    pub fn set_dedicated(&mut self, dedicated: Dedicated) -> &mut Self {
+        self.dedicated = dedicated;
+        self
+    }
+    #[inline]
+    pub fn dedicated(&self) -> Dedicated {
+        self.dedicated
+    }
+    #[inline]
     pub fn reset_flag(&mut self, op_code: OpCode, op: Operation) {
         self.op_code = op_code;
         self.op = op;
@@ -32,6 +32,8 @@ pub use operation::*;
 
 pub mod callback;
 pub mod request;
+mod retry;
+pub use retry::*;
 
 #[derive(Copy, Clone)]
 pub enum Resource {
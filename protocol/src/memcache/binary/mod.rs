@@ -9,11 +9,21 @@ pub use packet::Binary;
 #[derive(Clone, Default)]
 pub struct MemcacheBinary;
 
+// 连接数超过限制时的响应：noop，status为busy
+const CONN_LIMITED: [u8; HEADER_LEN] = {
+    let mut rsp = [0; HEADER_LEN];
+    rsp[PacketPos::Magic as usize] = RESPONSE_MAGIC;
+    rsp[PacketPos::Opcode as usize] = OP_NOOP;
+    rsp[PacketPos::Status as usize + 1] = Busy as u8;
+    rsp
+};
+
 use crate::{
     Command, Commander, Error, Flag, HashedCommand, Metric, MetricItem, Protocol, RequestProcessor,
     Result, Stream, Writer,
 };
 
+use ds::RingSlice;
 use sharding::hash::Hash;
 
 impl Protocol for MemcacheBinary {
@@ -24,6 +34,45 @@ impl Protocol for MemcacheBinary {
             ..Default::default()
         }
     }
+    #[inline]
+    fn can_retry_on_rsp_notok(&self, req: &HashedCommand) -> bool {
+        req.can_retry_on_rsp_notok()
+    }
+    #[inline]
+    fn key_len(&self, req: &HashedCommand) -> usize {
+        req.key_len() as usize
+    }
+    // 二进制协议没有独立的错误响应，连接数超限时返回opaque为0、status为busy的noop响应后关闭
+    #[inline]
+    fn conn_limited(&self) -> &'static [u8] {
+        &CONN_LIMITED
+    }
+    #[inline]
+    fn rate_limited(&self, req: &HashedCommand) -> Vec<u8> {
+        self.build_empty_response(Busy, req.op_code() as u8, req)
+            .to_vec()
+    }
+    #[inline]
+    fn sample_key(&self, req: &HashedCommand) -> Option<RingSlice> {
+        (req.key_len() > 0).then(|| req.key())
+    }
+    // 读响应中与请求相关的只有opaque，复用时替换为当前请求的opaque
+    #[inline]
+    fn rsp_reusable(&self, req: &HashedCommand) -> bool {
+        req.operation().is_query()
+    }
+    #[inline]
+    fn reuse_rsp(&self, req: &HashedCommand, rsp: &mut [u8]) {
+        let opaque = PacketPos::Opaque as usize;
+        if rsp.len() >= HEADER_LEN {
+            (opaque..opaque + 4).for_each(|i| rsp[i] = req.at(i));
+        }
+    }
+    #[inline]
+    fn big_rejected(&self, req: &HashedCommand) -> Vec<u8> {
+        self.build_empty_response(ValueTooLarge, req.op_code() as u8, req)
+            .to_vec()
+    }
     // 解析请求。把所有的multi-get请求转换成单一的n个get请求。
     #[inline]
     fn parse_request<S: Stream, H: Hash, P: RequestProcessor>(
@@ -38,6 +87,18 @@ impl Protocol for MemcacheBinary {
             let mut req = data.slice();
             req.check_request()?;
             let packet_len = req.packet_len();
+            // 声明的value长度超过上限时，不等待value的数据，只保留header用于构建响应，
+            // body由pipeline读到后直接丢弃
+            let max = process.max_value_len();
+            let value_len = (req.total_body_len() as usize)
+                .saturating_sub(req.extra_len() as usize + req.key_len() as usize);
+            if max > 0 && value_len > max {
+                let flag = Flag::from_op(req.op() as u16, req.operation());
+                let cmd = HashedCommand::new(data.take(HEADER_LEN), 0, flag);
+                process.reject_big(cmd, value_len);
+                process.skip(packet_len - HEADER_LEN);
+                break;
+            }
             if req.len() < packet_len {
                 data.reserve(packet_len - req.len());
                 break;
@@ -164,11 +225,12 @@ impl Protocol for MemcacheBinary {
             OP_VERSION => w.write(&VERSION_RESPONSE),
             OP_STAT => w.write(&STAT_RESPONSE),
             // TODO 参考packet::is_quiet_get，需要同步变，性能考虑继续放这里 fishermen
-            OP_GETQ | OP_GETKQ | OP_GETSQ => Ok(()),
-            OP_SET | OP_DEL | OP_ADD => {
+            OP_GETQ | OP_GETKQ | OP_GETSQ | OP_GATQ => Ok(()),
+            OP_SET | OP_DEL | OP_ADD | OP_REPLACE | OP_INCR | OP_DECR | OP_APPEND | OP_PREPEND
+            | OP_TOUCH => {
                 w.write(&self.build_empty_response(NotStored, old_op_code, ctx.request()))
             }
-            OP_GET | OP_GETS => {
+            OP_GET | OP_GETS | OP_GAT => {
                 w.write(&self.build_empty_response(NotFound, old_op_code, ctx.request()))
             }
             OP_QUIT | OP_QUITQ => Err(Error::Quit),
@@ -176,7 +238,7 @@ impl Protocol for MemcacheBinary {
         }
     }
 
-    // 如果是写请求，把cas请求转换为set请求。
+    // 如果是写请求，把cas请求转换为set请求；无法重放的写请求，转换为deleteq请求。
     // 如果是读请求，则通过response重新构建一个新的写请求。
     #[inline]
     fn build_writeback_request<C, M, I>(
@@ -193,6 +255,8 @@ impl Protocol for MemcacheBinary {
         if ctx.request_mut().operation().is_retrival() {
             let req = &*ctx.request();
             self.build_write_back_get(req, response, exp_sec)
+        } else if invalidate_on_write_back(ctx.request().op()) {
+            self.build_write_back_delete(ctx.request())
         } else {
             self.build_write_back_inplace(ctx.request_mut());
             None
@@ -206,7 +270,7 @@ impl MemcacheBinary {
         &self,
         status: RespStatus,
         real_op: u8,
-        req: &HashedCommand,
+        req: &RingSlice,
     ) -> [u8; HEADER_LEN] {
         //let req_slice = req.data();
         let mut response = [0; HEADER_LEN];
@@ -233,6 +297,29 @@ impl MemcacheBinary {
         assert!(req.operation().is_store(), "req: {:?}", req);
         assert!(req.sentonly(), "req: {:?}", req);
     }
+    // 构建deleteq请求，用于清理其他layers
+    #[inline]
+    fn build_write_back_delete(&self, req: &HashedCommand) -> Option<HashedCommand> {
+        let key_len = req.key_len();
+        let mut req_cmd: Vec<u8> = Vec::with_capacity(HEADER_LEN + key_len as usize);
+        use ds::Buffer;
+
+        req_cmd.push(Magic::Request as u8); // magic: [0]
+        req_cmd.push(OP_DELQ); // opcode: [1]
+        req_cmd.write_u16(key_len); // key len: [2,3]
+        req_cmd.push(0); // extra len: [4]
+        req_cmd.push(0); // data type: [5]
+        req_cmd.write_u16(0); // vbucket id: [6,7]
+        req_cmd.write_u32(key_len as u32); // total body len: [8-11]
+        req_cmd.write_u32(u32::MAX); // opaque: [12, 15]
+        req_cmd.write_u64(0); // cas: [16, 23]
+        req.key().copy_to_vec(&mut req_cmd);
+
+        let mut flag = Flag::from_op(OP_DELQ as u16, COMMAND_IDX[OP_DELQ as usize].into());
+        flag.set_sentonly(true);
+        let guard = ds::MemGuard::from_vec(req_cmd);
+        Some(HashedCommand::new(guard, req.hash(), flag))
+    }
     #[inline]
     fn build_write_back_get(
         &self,
@@ -18,6 +18,7 @@ pub enum RespStatus {
     NoError = 0x0000,
     NotFound = 0x0001,
     KeyExists = 0x0002,
+    ValueTooLarge = 0x0003,
     InvalidArg = 0x0004,
     NotStored = 0x0005,
     NonNumeric = 0x0006,
@@ -25,6 +26,8 @@ pub enum RespStatus {
     Quit = 0x0007,
     UnkownCmd = 0x0081,
     OutOfMemory = 0x0082,
+    // 连接数、qps超过限制
+    Busy = 0x0085,
 }
 
 use crate::Operation;
@@ -55,8 +58,9 @@ pub(crate) const STAT_RESPONSE: [u8; 24] = [
 // https://github.com/memcached/memcached/wiki/BinaryProtocolRevamped#command-opcodes
 // MC包含Get, MGet, Gets, Store, Meta四类命令，索引分别是0-4
 // 0x48 是Gets请求
+// append/prepend/touch 及其quiet请求为Store；gat/gatq需要touch master，按Gets处理
 pub(crate) const COMMAND_IDX: [u8; 128] = [
-    0, 3, 3, 3, 3, 3, 3, 4, 4, 1, 1, 4, 0, 1, 0, 0, 4, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 3, 3, 3, 3, 3, 3, 4, 4, 1, 1, 4, 0, 1, 3, 3, 4, 3, 3, 3, 3, 3, 3, 0, 0, 3, 3, 0, 3, 2, 2, 0,
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -64,9 +68,10 @@ pub(crate) const COMMAND_IDX: [u8; 128] = [
 // OP_CODE对应的noreply code。
 // 注意：根据业务逻辑，add会转换成setq
 // cas 变更为setq
+// touch没有对应的quiet请求，回写时以deleteq清理其他layers，故映射为deleteq；gat 对应 gatq
 pub(crate) const NOREPLY_MAPPING: [u8; 128] = [
     0x09, 0x11, 0x11, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x09, 0x00, 0x00, 0x0d, 0x0d, 0x19, 0x1a,
-    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x14, 0x1e, 0x1e, 0x1f,
     0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f,
     0x30, 0x32, 0x32, 0x34, 0x34, 0x36, 0x36, 0x38, 0x38, 0x3a, 0x3a, 0x3c, 0x3c, 0x3d, 0x3e, 0x3f,
     0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x49, 0x49, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -83,9 +88,10 @@ pub(crate) const NOREPLY_MAPPING: [u8; 128] = [
 
 // 请求完毕后，不考虑layer及其他配置，如果cmd失败,是否继续retry:
 // (1) 0: not retry (对cas/casq/add/replace生效);  (2) 1: retry ; (3) 2: unknown，需要进一步check.
+// incr/decr/append/prepend/touch 非幂等或者只对master有意义，失败后不再retry
 // TODO 本次修改影响：注意check set/cas、add/addq、setq/casq的影响
 const RETRY_TABLE: [u8; 128] = [
-    1, 2, 0, 0, 1, 1, 1, 1, 0, 1, 0, 1, 1, 1, 0, 0, 1, 2, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 0,
+    1, 2, 0, 0, 1, 0, 0, 1, 0, 1, 0, 1, 1, 1, 0, 0, 1, 2, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1, 0,
     1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1,
     0, 0, 1, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -114,6 +120,14 @@ pub enum Opcode {
     GETS = OP_GETS,
     GETSQ = OP_GETSQ,
     SETQ = OP_SETQ,
+    REPLACE = OP_REPLACE,
+    INCR = OP_INCR,
+    DECR = OP_DECR,
+    APPEND = OP_APPEND,
+    PREPEND = OP_PREPEND,
+    TOUCH = OP_TOUCH,
+    GAT = OP_GAT,
+    GATQ = OP_GATQ,
 }
 
 pub(crate) const REQUEST_MAGIC: u8 = 0x80;
@@ -138,6 +152,18 @@ pub(crate) const OP_SETQ: u8 = 0x11;
 pub const OP_GETS: u8 = 0x48;
 // 这个没有业务使用，先注销掉
 pub const OP_GETSQ: u8 = 0x49;
+pub const OP_REPLACE: u8 = 0x03;
+pub const OP_INCR: u8 = 0x05;
+pub(crate) const OP_INCRQ: u8 = 0x15;
+pub const OP_DECR: u8 = 0x06;
+pub(crate) const OP_DECRQ: u8 = 0x16;
+pub const OP_APPEND: u8 = 0x0e;
+pub(crate) const OP_APPENDQ: u8 = 0x19;
+pub const OP_PREPEND: u8 = 0x0f;
+pub(crate) const OP_PREPENDQ: u8 = 0x1a;
+pub const OP_TOUCH: u8 = 0x1c;
+pub const OP_GAT: u8 = 0x1d;
+pub(crate) const OP_GATQ: u8 = 0x1e;
 
 // 0x09: getq
 // 0x0d: getkq
@@ -151,12 +177,30 @@ pub const OP_GETSQ: u8 = 0x49;
 #[inline(always)]
 pub(crate) fn is_quiet_get(op_code: u8) -> bool {
     match op_code {
-        OP_GETQ | OP_GETKQ | OP_GETSQ => true,
+        OP_GETQ | OP_GETKQ | OP_GETSQ | OP_GATQ => true,
         _ => false,
     }
     //QUITE_GET_TABLE[op_code as usize] == 1
 }
 
+// 回写时无法在其他layers上重放的写请求：incr/decr/append/prepend的结果依赖原值，touch没有quiet请求；
+// 这些请求在master执行后，通过deleteq清理其他layers，后续读取时再从master回种。
+#[inline(always)]
+pub(crate) fn invalidate_on_write_back(op_code: u8) -> bool {
+    matches!(
+        op_code,
+        OP_INCR
+            | OP_INCRQ
+            | OP_DECR
+            | OP_DECRQ
+            | OP_APPEND
+            | OP_APPENDQ
+            | OP_PREPEND
+            | OP_PREPENDQ
+            | OP_TOUCH
+    )
+}
+
 // 在请求时，部分场景下把op_code进行一次映射。
 // 1. quite get请求映射成 non-quite get请求。
 //      getq(0x09) => get(0x00); getkq(0x0d) => getk(0x0c); 以实现multiget的pipeline
@@ -258,9 +302,11 @@ impl Binary for RingSlice {
     }
     /// 对于写指令，处理逻辑：
     ///   1 set/setq只要有响应则认为请求成功，然后set其他layers，但master结果会返回client，由client决定后续操作；
-    ///   2 cas/casq只要不是Key Exists异常，则认为是成功，然后set其他layers；
+    ///   2 cas/casq只要不是Key Exists或Not Found异常，则认为是成功，然后set其他layers；
     ///   3 add/addq只要不是Key Exists 则认为请求成功，然后set其他layers；
     ///   4 del/delq不管什么状态都认为成功，然后del其他layers；
+    ///   5 replace只有成功才set其他layers；
+    ///   6 incr/decr/append/prepend/touch 只要key不存在或未存储，也认为成功，然后del其他layers，避免其他layers残留旧值；
     /// 不管mesh认为是否成功，写指令的master响应都会原封不动的返回给client。
     #[inline(always)]
     fn status_ok(&self) -> bool {
@@ -274,11 +320,17 @@ impl Binary for RingSlice {
 
         // store cmd 请求失败了，某些描述的场景需要当作成功 fishermen
         match self.op() {
+            // cas请求的key不存在时，返回NotFound，此时不能set其他layers
             OP_SET | OP_SETQ | OP_ADD | OP_ADDQ => {
                 let status = self.u16_be(6);
-                status != RespStatus::KeyExists as u16
+                status != RespStatus::KeyExists as u16 && status != RespStatus::NotFound as u16
             }
             OP_DEL | OP_DELQ => true,
+            OP_INCR | OP_INCRQ | OP_DECR | OP_DECRQ | OP_APPEND | OP_APPENDQ | OP_PREPEND
+            | OP_PREPENDQ | OP_TOUCH => {
+                let status = self.u16_be(6);
+                status == RespStatus::NotFound as u16 || status == RespStatus::NotStored as u16
+            }
             _ => is_ok,
         }
     }
@@ -344,6 +396,7 @@ impl Binary for RingSlice {
         let new = match old {
             OP_GETQ | OP_GETS => OP_GET,
             OP_GETKQ | OP_GETSQ => OP_GETK,
+            OP_GATQ => OP_GAT,
             o => o,
         };
         if new != old {
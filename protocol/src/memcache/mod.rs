@@ -1,11 +1,11 @@
 mod binary;
 pub(crate) use binary::packet;
-//mod text;
+mod text;
 
 pub use binary::Binary;
 pub use binary::MemcacheBinary as MemcacheBin;
 pub use binary::MemcacheBinary;
-//pub use text::MemcacheText;
+pub use text::MemcacheText;
 
 // #[derive(Debug, PartialEq)]
 // pub enum Command {
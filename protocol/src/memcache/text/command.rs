@@ -0,0 +1,133 @@
+use ds::RingSlice;
+
+use crate::{OpCode, Operation};
+
+use super::error::McTextError;
+
+// mc 文本协议的指令类型，决定了请求行中参数的个数及是否携带value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RequestType {
+    // get/gets <key>*
+    Retrieval,
+    // set/add/replace/append/prepend <key> <flags> <exptime> <bytes> [noreply]
+    Storage,
+    // cas <key> <flags> <exptime> <bytes> <cas unique> [noreply]
+    Cas,
+    // delete <key> [noreply]
+    Delete,
+    // incr/decr <key> <value> [noreply]
+    Arith,
+    // touch <key> <exptime> [noreply]
+    Touch,
+    // version/stats/quit，不发往后端
+    Misc,
+}
+
+impl RequestType {
+    // 请求行中key之后的参数个数（不含noreply）
+    #[inline]
+    pub(super) fn args(&self) -> usize {
+        match self {
+            RequestType::Retrieval | RequestType::Misc => 0,
+            RequestType::Storage => 3,
+            RequestType::Cas => 4,
+            RequestType::Delete => 0,
+            RequestType::Arith | RequestType::Touch => 1,
+        }
+    }
+    // 请求行之后，是否还有<data block>\r\n
+    #[inline]
+    pub(super) fn has_val(&self) -> bool {
+        matches!(self, RequestType::Storage | RequestType::Cas)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CommandProperties {
+    pub(crate) name: &'static str,
+    pub(crate) req_type: RequestType,
+    pub(crate) op_code: OpCode,
+    pub(crate) op: Operation,
+    padding_rsp: usize,
+    pub(crate) noforward: bool,
+    // 有响应但响应不ok时，是否可以重试其他layer，与binary的RETRY_TABLE保持一致
+    pub(crate) retry: bool,
+    pub(crate) quit: bool,
+}
+
+impl CommandProperties {
+    // 后端不可用或noforward指令时，返回给client的默认响应
+    #[inline]
+    pub(super) fn padding_rsp(&self) -> &'static [u8] {
+        PADDING_RSP_TABLE[self.padding_rsp].as_bytes()
+    }
+}
+
+const PADDING_RSP_TABLE: [&str; 4] = [
+    "",
+    "END\r\n",
+    "SERVER_ERROR mc not available\r\n",
+    "VERSION 0.0.1\r\n",
+];
+
+const fn cmd(
+    name: &'static str,
+    req_type: RequestType,
+    op_code: OpCode,
+    op: Operation,
+    padding_rsp: usize,
+    noforward: bool,
+    retry: bool,
+) -> CommandProperties {
+    CommandProperties {
+        name,
+        req_type,
+        op_code,
+        op,
+        padding_rsp,
+        noforward,
+        retry,
+        quit: op_code == OP_QUIT,
+    }
+}
+
+// 回种读请求时使用set指令
+pub(super) const OP_SET: OpCode = 2;
+const OP_QUIT: OpCode = 14;
+
+// op_code即为指令在COMMANDS中的位置
+use Operation::*;
+use RequestType::*;
+static COMMANDS: [CommandProperties; 15] = [
+    cmd("get", Retrieval, 0, Get, 1, false, true),
+    cmd("gets", Retrieval, 1, Gets, 1, false, true),
+    cmd("set", Storage, OP_SET, Store, 2, false, true),
+    cmd("add", Storage, 3, Store, 2, false, false),
+    cmd("replace", Storage, 4, Store, 2, false, false),
+    cmd("append", Storage, 5, Store, 2, false, false),
+    cmd("prepend", Storage, 6, Store, 2, false, false),
+    cmd("cas", Cas, 7, Store, 2, false, false),
+    cmd("delete", Delete, 8, Store, 2, false, true),
+    cmd("incr", Arith, 9, Store, 2, false, true),
+    cmd("decr", Arith, 10, Store, 2, false, true),
+    cmd("touch", Touch, 11, Store, 2, false, true),
+    cmd("version", Misc, 12, Meta, 3, true, true),
+    cmd("stats", Misc, 13, Meta, 1, true, true),
+    cmd("quit", Misc, OP_QUIT, Meta, 0, true, true),
+];
+
+#[inline]
+pub(super) fn get_cfg<'a>(op_code: OpCode) -> crate::Result<&'a CommandProperties> {
+    COMMANDS
+        .get(op_code as usize)
+        .ok_or(crate::Error::OpCodeNotSupported(op_code))
+}
+
+// 指令数量很少，直接顺序匹配即可
+#[inline]
+pub(super) fn get_cfg_by_name<'a>(name: &RingSlice) -> crate::Result<&'a CommandProperties> {
+    COMMANDS
+        .iter()
+        .find(|c| c.name.len() == name.len() && name.start_with(0, c.name.as_bytes()))
+        .ok_or_else(|| McTextError::ReqNotSupported.into())
+}
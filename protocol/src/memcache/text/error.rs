@@ -0,0 +1,33 @@
+use crate::Error;
+
+/// 用于处理mc文本协议解析中的异常，请求异常在关闭client连接前，返回特定的异常响应
+#[derive(Debug)]
+pub enum McTextError {
+    ReqInvalid,
+    ReqInvalidKey,
+    ReqBadChunk,
+    ReqLineTooLong,
+    ReqNotSupported,
+    RspInvalid,
+}
+
+const REQ_INVALID: &[u8] = b"CLIENT_ERROR bad command line format\r\n";
+const REQ_INVALID_KEY: &[u8] = b"CLIENT_ERROR invalid key\r\n";
+const REQ_BAD_CHUNK: &[u8] = b"CLIENT_ERROR bad data chunk\r\n";
+pub(super) const REQ_TOO_LARGE: &[u8] = b"SERVER_ERROR object too large for cache\r\n";
+const REQ_LINE_TOO_LONG: &[u8] = b"CLIENT_ERROR line too long\r\n";
+const REQ_NOT_SUPPORTED: &[u8] = b"ERROR\r\n";
+
+impl From<McTextError> for Error {
+    #[inline]
+    fn from(e: McTextError) -> Self {
+        match e {
+            McTextError::ReqInvalid => Error::FlushOnClose(REQ_INVALID.into()),
+            McTextError::ReqInvalidKey => Error::FlushOnClose(REQ_INVALID_KEY.into()),
+            McTextError::ReqBadChunk => Error::FlushOnClose(REQ_BAD_CHUNK.into()),
+            McTextError::ReqLineTooLong => Error::FlushOnClose(REQ_LINE_TOO_LONG.into()),
+            McTextError::ReqNotSupported => Error::FlushOnClose(REQ_NOT_SUPPORTED.into()),
+            McTextError::RspInvalid => Error::ResponseProtocolInvalid,
+        }
+    }
+}
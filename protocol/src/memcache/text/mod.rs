@@ -0,0 +1,366 @@
+mod command;
+mod error;
+mod packet;
+
+use command::{CommandProperties, RequestType};
+use error::McTextError;
+use packet::*;
+
+#[derive(Clone, Default)]
+pub struct MemcacheText;
+
+use crate::{
+    Bit, Command, Commander, Error, Flag, HashedCommand, Metric, MetricItem, Operation, Protocol,
+    RequestProcessor, Result, Stream, Writer,
+};
+use ds::{Buffer, MemGuard};
+
+use sharding::hash::Hash;
+
+// multi-get拆分后的子请求，如果不是最后一个key，则设置该位。
+// 写响应时，非最后一个key的响应需要去掉END，由最后一个key统一返回END
+const MKEY_MORE_SHIFT: u8 = 0;
+
+impl Protocol for MemcacheText {
+    #[inline]
+    fn config(&self) -> crate::Config {
+        crate::Config {
+            retry_on_rsp_notok: true,
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn conn_limited(&self) -> &'static [u8] {
+        b"SERVER_ERROR too many connections\r\n"
+    }
+    #[inline]
+    fn rate_limited(&self, _req: &HashedCommand) -> Vec<u8> {
+        b"SERVER_ERROR rate limited\r\n".to_vec()
+    }
+    #[inline]
+    fn big_rejected(&self, _req: &HashedCommand) -> Vec<u8> {
+        error::REQ_TOO_LARGE.to_vec()
+    }
+    #[inline]
+    fn key_len(&self, req: &HashedCommand) -> usize {
+        self.sample_key(req).map(|k| k.len()).unwrap_or_default()
+    }
+    #[inline]
+    fn can_retry_on_rsp_notok(&self, req: &HashedCommand) -> bool {
+        command::get_cfg(req.op_code())
+            .map(|cfg| cfg.retry)
+            .unwrap_or_default()
+    }
+    #[inline]
+    fn sample_key(&self, req: &HashedCommand) -> Option<ds::RingSlice> {
+        let cfg = command::get_cfg(req.op_code()).ok()?;
+        if cfg.req_type == RequestType::Misc {
+            return None;
+        }
+        let line = req.line_end(0)?;
+        req.tokens(0, line).nth(1)
+    }
+    // 文本协议的响应中不包含请求相关的信息，相同指令、相同key的响应可以复用
+    #[inline]
+    fn rsp_reusable(&self, _req: &HashedCommand) -> bool {
+        true
+    }
+    // 解析请求。把所有的multi-get请求转换成单一的n个get请求。
+    #[inline]
+    fn parse_request<S: Stream, H: Hash, P: RequestProcessor>(
+        &self,
+        data: &mut S,
+        alg: &H,
+        process: &mut P,
+    ) -> Result<()> {
+        log::debug!("+++ recv mc text:{:?}", data.slice());
+        while data.len() > 0 {
+            let req = data.slice();
+            // 没有行结束符时，避免client通过超长的请求行持续占用内存
+            let line = match req.line_end(0) {
+                Some(line) if line <= MAX_LINE_LEN => line,
+                None if req.len() <= MAX_LINE_LEN => break,
+                _ => return Err(McTextError::ReqLineTooLong.into()),
+            };
+            let mut tokens = req.tokens(0, line);
+            let name = tokens.next().ok_or(McTextError::ReqInvalid)?;
+            let cfg = command::get_cfg_by_name(&name)?;
+            if cfg.req_type == RequestType::Retrieval {
+                self.parse_get(data, cfg, line, tokens, alg, process)?;
+                continue;
+            }
+
+            let mut packet_len = line + CRLF.len();
+            let mut hash = 0;
+            let mut noreply = false;
+            if cfg.req_type != RequestType::Misc {
+                let key = tokens.next().ok_or(McTextError::ReqInvalid)?;
+                if !key.valid_key() {
+                    return Err(McTextError::ReqInvalidKey.into());
+                }
+                let mut bytes = 0;
+                for i in 0..cfg.req_type.args() {
+                    let arg = tokens.next().ok_or(McTextError::ReqInvalid)?;
+                    // <key> <flags> <exptime> <bytes>，bytes是第三个参数
+                    if cfg.req_type.has_val() && i == 2 {
+                        bytes = arg.num().ok_or(McTextError::ReqInvalid)?;
+                    }
+                }
+                if let Some(t) = tokens.next() {
+                    if !t.equal(NOREPLY) || tokens.next().is_some() {
+                        return Err(McTextError::ReqInvalid.into());
+                    }
+                    noreply = true;
+                }
+                if cfg.req_type.has_val() {
+                    // 声明的value长度超过上限时，不等待value的数据，只保留请求行用于响应，
+                    // value由pipeline读到后直接丢弃。noreply的请求不响应
+                    let max = process.max_value_len();
+                    if max > 0 && bytes > max {
+                        let mut flag = Flag::from_op(cfg.op_code, cfg.op);
+                        flag.set_sentonly(noreply);
+                        flag.set_noforward(noreply);
+                        let cmd = HashedCommand::new(data.take(packet_len), 0, flag);
+                        process.reject_big(cmd, bytes);
+                        process.skip(bytes.saturating_add(CRLF.len()));
+                        break;
+                    }
+                    // 超过item大小的value不再缓存，避免client通过请求行申请大内存
+                    if bytes > MAX_ITEM_LEN {
+                        return Err(McTextError::ReqBadChunk.into());
+                    }
+                    packet_len = (packet_len.checked_add(bytes))
+                        .and_then(|l| l.checked_add(CRLF.len()))
+                        .ok_or(McTextError::ReqBadChunk)?;
+                    if req.len() < packet_len {
+                        data.reserve(packet_len - req.len());
+                        break;
+                    }
+                    if !req.start_with(packet_len - CRLF.len(), CRLF) {
+                        return Err(McTextError::ReqInvalid.into());
+                    }
+                }
+                hash = alg.hash(&key);
+            }
+
+            let mut flag = Flag::from_op(cfg.op_code, cfg.op);
+            flag.set_sentonly(noreply);
+            flag.set_noforward(cfg.noforward);
+            let cmd = HashedCommand::new(data.take(packet_len), hash, flag);
+            process.process(cmd, true);
+        }
+        Ok(())
+    }
+    #[inline]
+    fn parse_response<S: Stream>(&self, data: &mut S) -> Result<Option<Command>> {
+        log::debug!("+++ mc text will parse rsp: {:?}", data.slice());
+        let rsp = data.slice();
+        let mut oft = 0;
+        match rsp.parse_rsp(&mut oft) {
+            Ok(ok) => Ok(Some(Command::from(ok, data.take(oft)))),
+            Err(Error::ProtocolIncomplete) => {
+                if oft > rsp.len() {
+                    data.reserve(oft - rsp.len());
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    #[inline]
+    fn write_response<C, W, M, I>(
+        &self,
+        ctx: &mut C,
+        response: Option<&mut Command>,
+        w: &mut W,
+    ) -> Result<()>
+    where
+        W: Writer,
+        C: Commander<M, I>,
+        M: Metric<I>,
+        I: MetricItem,
+    {
+        let req = ctx.request();
+        let cfg = command::get_cfg(req.op_code())?;
+        if cfg.quit {
+            return Err(Error::Quit);
+        }
+        // noreply 直接返回
+        if req.sentonly() {
+            assert!(response.is_none(), "req:{:?}", req);
+            return Ok(());
+        }
+        // 查询请求统计缓存命中率
+        if req.operation().is_query() {
+            ctx.metric()
+                .cache(response.as_ref().map(|r| r.ok()).unwrap_or_default());
+        }
+
+        // multi-get的非最后一个key，只返回VALUE部分，没有命中则什么都不返回，由最后一个key统一返回END；
+        // 后端返回错误时，返回错误响应，避免client把错误当作未命中。
+        // 没有响应时与单key的get一致，按未命中处理
+        let more = req.get(MKEY_MORE_SHIFT);
+        match response {
+            Some(rsp) if !more => w.write_slice(rsp, 0),
+            Some(rsp) if rsp.ok() => {
+                debug_assert!(rsp.len() > END.len(), "rsp:{:?}", rsp);
+                w.write_ringslice(&rsp.sub_slice(0, rsp.len() - END.len()), 0)
+            }
+            Some(rsp) if !(rsp.len() == END.len() && rsp.start_with(0, END)) => {
+                w.write_slice(rsp, 0)
+            }
+            None if !more => w.write(cfg.padding_rsp()),
+            _ => Ok(()),
+        }
+    }
+
+    // 如果是写请求，转换为noreply请求，cas请求转换为set请求。
+    // 如果是读请求，则通过response重新构建一个新的set请求。
+    #[inline]
+    fn build_writeback_request<C, M, I>(
+        &self,
+        ctx: &mut C,
+        response: &Command,
+        exp_sec: u32,
+    ) -> Option<HashedCommand>
+    where
+        C: Commander<M, I>,
+        M: Metric<I>,
+        I: MetricItem,
+    {
+        let req = ctx.request();
+        if req.operation().is_retrival() {
+            self.build_write_back_get(req, response, exp_sec)
+        } else {
+            self.build_write_back_store(req)
+        }
+    }
+}
+
+impl MemcacheText {
+    // get/gets <key>*\r\n：单个key直接发送，多个key拆分成多个单key请求
+    #[inline]
+    fn parse_get<S: Stream, H: Hash, P: RequestProcessor>(
+        &self,
+        data: &mut S,
+        cfg: &CommandProperties,
+        line: usize,
+        tokens: Tokens,
+        alg: &H,
+        process: &mut P,
+    ) -> Result<()> {
+        let mut num = 0;
+        for key in tokens.clone() {
+            if !key.valid_key() {
+                return Err(McTextError::ReqInvalidKey.into());
+            }
+            num += 1;
+        }
+        if num == 0 {
+            return Err(McTextError::ReqInvalid.into());
+        }
+
+        if num == 1 {
+            let hash = alg.hash(&tokens.clone().next().expect("key"));
+            let flag = Flag::from_op(cfg.op_code, cfg.op);
+            let cmd = HashedCommand::new(data.take(line + CRLF.len()), hash, flag);
+            process.process(cmd, true);
+            return Ok(());
+        }
+
+        let op = match cfg.op {
+            Operation::Get => Operation::MGet,
+            op => op,
+        };
+        // 子请求构建完毕前，需要持有原始请求，确保key的内存有效
+        let _origin = data.take(line + CRLF.len());
+        for (i, key) in tokens.enumerate() {
+            let last = i + 1 == num;
+            let mut flag = Flag::from_op(cfg.op_code, op);
+            if !last {
+                flag.set(MKEY_MORE_SHIFT);
+            }
+            let mut cmd = Vec::with_capacity(cfg.name.len() + key.len() + 1 + CRLF.len());
+            cmd.write(cfg.name);
+            cmd.push(b' ');
+            cmd.write_slice(&key);
+            cmd.write(CRLF);
+            let req = HashedCommand::new(MemGuard::from_vec(cmd), alg.hash(&key), flag);
+            process.process(req, last);
+        }
+        Ok(())
+    }
+    #[inline]
+    fn build_write_back_get(
+        &self,
+        req: &HashedCommand,
+        resp: &Command,
+        exp_sec: u32,
+    ) -> Option<HashedCommand> {
+        // 只为status为ok的resp构建回种req
+        assert!(resp.ok(), "resp: {:?}", resp);
+        // VALUE <key> <flags> <bytes> [<cas unique>]\r\n<data block>\r\nEND\r\n
+        let line = resp.line_end(0)?;
+        let mut tokens = resp.tokens(0, line).skip(1);
+        let (key, flags, bytes) = (tokens.next()?, tokens.next()?, tokens.next()?);
+        let data = resp.sub_slice(line + CRLF.len(), bytes.num()? + CRLF.len());
+
+        // set <key> <flags> <exptime> <bytes> noreply\r\n<data block>\r\n
+        let mut cmd = Vec::with_capacity(resp.len() + 32);
+        cmd.write("set ");
+        cmd.write_slice(&key);
+        cmd.push(b' ');
+        cmd.write_slice(&flags);
+        cmd.push(b' ');
+        cmd.write(exp_sec.to_string());
+        cmd.push(b' ');
+        cmd.write_slice(&bytes);
+        cmd.push(b' ');
+        cmd.write(NOREPLY);
+        cmd.write(CRLF);
+        cmd.write_slice(&data);
+
+        let mut flag = Flag::from_op(command::OP_SET, Operation::Store);
+        flag.set_sentonly(true);
+        Some(HashedCommand::new(
+            MemGuard::from_vec(cmd),
+            req.hash(),
+            flag,
+        ))
+    }
+    #[inline]
+    fn build_write_back_store(&self, req: &HashedCommand) -> Option<HashedCommand> {
+        let cfg = command::get_cfg(req.op_code()).ok()?;
+        // cas请求回写时转换为set请求，去掉cas unique
+        let (cfg, req_type) = match cfg.req_type {
+            RequestType::Cas => (
+                command::get_cfg(command::OP_SET).ok()?,
+                RequestType::Storage,
+            ),
+            t => (cfg, t),
+        };
+        let line = req.line_end(0)?;
+        let mut cmd = Vec::with_capacity(req.len() + NOREPLY.len() + 1);
+        cmd.write(cfg.name);
+        for arg in req.tokens(0, line).skip(1).take(1 + req_type.args()) {
+            cmd.push(b' ');
+            cmd.write_slice(&arg);
+        }
+        cmd.push(b' ');
+        cmd.write(NOREPLY);
+        cmd.write(CRLF);
+        if req_type.has_val() {
+            let oft = line + CRLF.len();
+            cmd.write_slice(&req.sub_slice(oft, req.len() - oft));
+        }
+
+        let mut flag = Flag::from_op(cfg.op_code, cfg.op);
+        // 设置只发送标签，发送完成即请求完成。
+        flag.set_sentonly(true);
+        Some(HashedCommand::new(
+            MemGuard::from_vec(cmd),
+            req.hash(),
+            flag,
+        ))
+    }
+}
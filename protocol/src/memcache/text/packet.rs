@@ -0,0 +1,154 @@
+use ds::RingSlice;
+
+use super::error::McTextError;
+use crate::{Error, Result};
+
+pub(super) const CRLF: &[u8] = b"\r\n";
+pub(super) const END: &[u8] = b"END\r\n";
+pub(super) const NOREPLY: &[u8] = b"noreply";
+const VALUE: &[u8] = b"VALUE";
+const MAX_KEY_LEN: usize = 250;
+// 单个value的最大长度，与memcached默认的item_size_max一致
+pub(super) const MAX_ITEM_LEN: usize = 1024 * 1024;
+// 请求行的最大长度，足够容纳数百个key的multi-get；超过时与memcached一样返回line too long
+pub(super) const MAX_LINE_LEN: usize = 64 * 1024;
+
+// 单行响应的首个token，及其对应的状态。END为读请求未命中。
+// NOT_FOUND认为是ok，与binary的del语义保持一致：不管什么状态都认为成功，然后继续更新其他layers
+const STATUS_TABLE: [(&[u8], bool); 12] = [
+    (b"END", false),
+    (b"STORED", true),
+    (b"DELETED", true),
+    (b"TOUCHED", true),
+    (b"NOT_FOUND", true),
+    (b"VERSION", true),
+    (b"OK", true),
+    (b"NOT_STORED", false),
+    (b"EXISTS", false),
+    (b"ERROR", false),
+    (b"CLIENT_ERROR", false),
+    (b"SERVER_ERROR", false),
+];
+
+// 按空格拆分一行中的token，不包含行尾的\r\n
+#[derive(Clone)]
+pub(super) struct Tokens {
+    data: RingSlice,
+    oft: usize,
+    end: usize,
+}
+
+impl Iterator for Tokens {
+    type Item = RingSlice;
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.oft < self.end && self.data.at(self.oft) == b' ' {
+            self.oft += 1;
+        }
+        if self.oft >= self.end {
+            return None;
+        }
+        let start = self.oft;
+        while self.oft < self.end && self.data.at(self.oft) != b' ' {
+            self.oft += 1;
+        }
+        Some(self.data.sub_slice(start, self.oft - start))
+    }
+}
+
+pub(super) trait Text {
+    // 从oft开始的一行的结束位置，即'\r\n'中'\r'的位置
+    fn line_end(&self, oft: usize) -> Option<usize>;
+    // [oft, end)之间的所有token
+    fn tokens(&self, oft: usize, end: usize) -> Tokens;
+    // 整个slice是否为十进制数字，是则返回对应的值
+    fn num(&self) -> Option<usize>;
+    fn equal(&self, s: &[u8]) -> bool;
+    fn valid_key(&self) -> bool;
+    // 解析一个完整的响应，返回响应是否ok，oft为响应的长度；
+    // 数据不完整时返回ProtocolIncomplete，此时oft为已知的响应最小长度
+    fn parse_rsp(&self, oft: &mut usize) -> Result<bool>;
+}
+
+impl Text for RingSlice {
+    #[inline]
+    fn line_end(&self, oft: usize) -> Option<usize> {
+        if oft + CRLF.len() > self.len() {
+            return None;
+        }
+        self.find_lf_cr(oft)
+    }
+    #[inline]
+    fn tokens(&self, oft: usize, end: usize) -> Tokens {
+        debug_assert!(oft <= end && end <= self.len());
+        Tokens {
+            data: *self,
+            oft,
+            end,
+        }
+    }
+    #[inline]
+    fn num(&self) -> Option<usize> {
+        // u64最大为20位
+        if self.len() == 0 || self.len() > 20 {
+            return None;
+        }
+        // 长度由client控制，溢出时视为非法
+        let mut n = Some(0usize);
+        self.visit(|b| {
+            n = n
+                .filter(|_| b.is_ascii_digit())
+                .and_then(|n| n.checked_mul(10))
+                .and_then(|n| n.checked_add((b - b'0') as usize));
+        });
+        n
+    }
+    #[inline]
+    fn equal(&self, s: &[u8]) -> bool {
+        self.len() == s.len() && self.start_with(0, s)
+    }
+    #[inline]
+    fn valid_key(&self) -> bool {
+        if self.len() == 0 || self.len() > MAX_KEY_LEN {
+            return false;
+        }
+        let mut valid = true;
+        self.visit(|b| valid &= !b.is_ascii_control());
+        valid
+    }
+    fn parse_rsp(&self, oft: &mut usize) -> Result<bool> {
+        let line = self.line_end(0).ok_or(Error::ProtocolIncomplete)?;
+        if !self.start_with(0, VALUE) {
+            *oft = line + CRLF.len();
+            let status = self.tokens(0, line).next();
+            return status
+                .and_then(|s| STATUS_TABLE.iter().find(|(name, _)| s.equal(name)))
+                .map(|(_, ok)| *ok)
+                .or_else(|| status.and_then(|s| s.num()).map(|_| true)) // incr/decr的响应
+                .ok_or_else(|| McTextError::RspInvalid.into());
+        }
+
+        // VALUE <key> <flags> <bytes> [<cas unique>]\r\n<data block>\r\n ... END\r\n
+        let mut start = 0;
+        while self.start_with(start, VALUE) {
+            let line = self.line_end(start).ok_or(Error::ProtocolIncomplete)?;
+            let bytes = self.tokens(start, line).nth(3).and_then(|b| b.num());
+            start = line + CRLF.len() + bytes.ok_or(McTextError::RspInvalid)? + CRLF.len();
+            *oft = start + END.len();
+            if start > self.len() {
+                return Err(Error::ProtocolIncomplete);
+            }
+            if !self.start_with(start - CRLF.len(), CRLF) {
+                return Err(McTextError::RspInvalid.into());
+            }
+        }
+        *oft = start + END.len();
+        if *oft > self.len() {
+            return Err(Error::ProtocolIncomplete);
+        }
+        if !self.start_with(start, END) {
+            return Err(McTextError::RspInvalid.into());
+        }
+        Ok(true)
+    }
+}
@@ -3,16 +3,17 @@ use enum_dispatch::enum_dispatch;
 use sharding::hash::Hash;
 
 use crate::kv::Kv;
-use crate::memcache::MemcacheBinary;
+use crate::memcache::{MemcacheBinary, MemcacheText};
 use crate::msgque::MsgQue;
 use crate::redis::Redis;
 use crate::uuid::Uuid;
-use crate::{Error, Flag, OpCode, Operation, Result, Stream, Writer};
+use crate::{Dedicated, Error, Flag, OpCode, Operation, Result, Stream, Writer};
 
 #[derive(Clone)]
 #[enum_dispatch(Proto)]
 pub enum Parser {
     McBin(MemcacheBinary),
+    McText(MemcacheText),
     Redis(Redis),
     MsgQue(MsgQue),
     // TODO 暂时保留，待client修改上线完毕后，清理
@@ -24,6 +25,7 @@ impl Parser {
     pub fn try_from(name: &str) -> Result<Self> {
         match name {
             "mc" => Ok(Self::McBin(Default::default())),
+            "mctext" => Ok(Self::McText(Default::default())),
             "redis" | "phantom" => Ok(Self::Redis(Default::default())),
             "msgque" => Ok(Self::MsgQue(Default::default())),
             "kv" => Ok(Self::Kv(Default::default())),
@@ -47,11 +49,44 @@ impl Parser {
 // #[derive(Default)]
 // pub enum AuthMethod {}
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, PartialEq)]
 pub struct ResOption {
     // pub method: AuthMethod,
     pub token: String,
     pub username: String,
+    // 建连后需要选择的db，目前只有redis使用
+    pub db: u16,
+    // 与后端的连接是否使用tls
+    pub tls: bool,
+    // 后端实例的熔断阈值
+    pub breaker: BreakerOption,
+}
+
+// 后端实例的熔断阈值：按窗口统计错误率，超过阈值时从副本选择中摘除
+#[derive(Clone, PartialEq, Debug)]
+pub struct BreakerOption {
+    // 错误率的统计窗口
+    pub window_ms: u32,
+    // 窗口内请求数过少时不摘除，避免个别错误导致摘除
+    pub min_reqs: u32,
+    // 错误（超时、连接异常、慢请求）占比超过该值时摘除
+    pub err_percent: u32,
+    // 实例被摘除后，经过该时长进行半开探测
+    pub cooldown_ms: u32,
+    // 耗时超过超时时间的该百分比的请求计为慢请求
+    pub slow_percent: u32,
+}
+
+impl Default for BreakerOption {
+    fn default() -> Self {
+        Self {
+            window_ms: 10_000,
+            min_reqs: 32,
+            err_percent: 50,
+            cooldown_ms: 5_000,
+            slow_percent: 50,
+        }
+    }
 }
 
 #[derive(Default, Clone)]
@@ -79,6 +114,15 @@ pub trait Proto: Unpin + Clone + Send + Sync + 'static {
         process: &mut P,
     ) -> Result<()>;
     fn parse_response<S: Stream>(&self, data: &mut S) -> Result<Option<Command>>;
+    // 解析req对应的response。默认与req无关，一个req对应多个response时需要覆盖
+    #[inline]
+    fn parse_response_of<S: Stream>(
+        &self,
+        _req: &HashedCommand,
+        data: &mut S,
+    ) -> Result<Option<Command>> {
+        self.parse_response(data)
+    }
     fn write_response<C, W, M, I>(
         &self,
         ctx: &mut C,
@@ -115,6 +159,121 @@ pub trait Proto: Unpin + Clone + Send + Sync + 'static {
     fn config(&self) -> Config {
         Config::default()
     }
+    // client连接数超过限制时，关闭连接前返回的错误，为空时直接关闭
+    #[inline]
+    fn conn_limited(&self) -> &'static [u8] {
+        b""
+    }
+    // 请求中key的长度，用于big key检测，无key的请求返回0
+    #[inline]
+    fn key_len(&self, _req: &HashedCommand) -> usize {
+        0
+    }
+    // 请求超过qps限制时返回的错误，为空时按无响应处理。mc二进制协议的响应需要复制请求的opaque
+    #[inline]
+    fn rate_limited(&self, _req: &HashedCommand) -> Vec<u8> {
+        Vec::new()
+    }
+    // big key/value请求被拒绝时返回的错误，为空时按无响应处理。mc二进制协议的响应需要复制请求的opaque
+    #[inline]
+    fn big_rejected(&self, _req: &HashedCommand) -> Vec<u8> {
+        Vec::new()
+    }
+    // 请求与client独占连接（如redis的watch）不在同一分片时返回的错误，为空时按无响应处理
+    #[inline]
+    fn cross_shard(&self) -> &'static [u8] {
+        b""
+    }
+    // client独占连接（如redis的阻塞指令）的后端鉴权失败时返回的错误，为空时按无响应处理
+    #[inline]
+    fn auth_failed(&self) -> &'static [u8] {
+        b""
+    }
+    // 请求的key，用于hot key采样，以及near cache的读写、清理。无key的请求返回None
+    #[inline]
+    fn sample_key(&self, _req: &HashedCommand) -> Option<ds::RingSlice> {
+        None
+    }
+    // 请求的响应能否被相同指令、相同key的请求复用，即能否写入near cache
+    #[inline]
+    fn rsp_reusable(&self, _req: &HashedCommand) -> bool {
+        false
+    }
+    // 复用near cache中的响应前，修正响应中与请求相关的部分，如mc二进制协议的opaque
+    #[inline]
+    fn reuse_rsp(&self, _req: &HashedCommand, _rsp: &mut [u8]) {}
+    // 请求有响应但响应不ok时，协议层面是否允许重试，默认由config决定
+    #[inline]
+    fn can_retry_on_rsp_notok(&self, _req: &HashedCommand) -> bool {
+        self.config().retry_on_rsp_notok
+    }
+    // 订阅连接上后端推送的数据（如redis pub/sub的订阅确认及消息），写入client。
+    // shard、pattern标识推送数据的连接，订阅数需要按client维度修正
+    #[inline]
+    fn write_push<W: Writer>(
+        &self,
+        _subs: &mut Subscriptions,
+        _shard: usize,
+        _pattern: bool,
+        _data: &Command,
+        _w: &mut W,
+    ) -> Result<()> {
+        Err(Error::ProtocolNotSupported)
+    }
+}
+
+// client在各订阅连接上的订阅数。
+// 普通订阅按hash分布在各分片的连接上，需要累加；
+// 模式订阅会发送到所有分片，每个连接上的订阅数都相同，只记录第0个分片的。
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    channels: Vec<usize>,
+    patterns: usize,
+    subscribing: bool,
+    // client协商的协议版本，推送数据的格式与之相关，如redis RESP3的push类型
+    resp3: bool,
+}
+
+impl Subscriptions {
+    // 更新连接上的订阅数，返回client总的订阅数。总订阅数为0时退出订阅模式
+    #[inline]
+    pub fn update(&mut self, shard: usize, pattern: bool, count: usize) -> usize {
+        if pattern {
+            debug_assert_eq!(shard, 0);
+            self.patterns = count;
+        } else {
+            if self.channels.len() <= shard {
+                self.channels.resize(shard + 1, 0);
+            }
+            self.channels[shard] = count;
+        }
+        let total = self.total();
+        if total == 0 {
+            self.subscribing = false;
+        }
+        total
+    }
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.channels.iter().sum::<usize>() + self.patterns
+    }
+    // 发送订阅请求后即进入订阅模式
+    #[inline]
+    pub fn set_subscribing(&mut self) {
+        self.subscribing = true;
+    }
+    #[inline]
+    pub fn subscribing(&self) -> bool {
+        self.subscribing
+    }
+    #[inline]
+    pub fn set_resp3(&mut self, resp3: bool) {
+        self.resp3 = resp3;
+    }
+    #[inline]
+    pub fn resp3(&self) -> bool {
+        self.resp3
+    }
 }
 
 pub trait RequestProcessor {
@@ -124,6 +283,47 @@ pub trait RequestProcessor {
     // 2. 请求被拆分成了多个子请求；
     // 3. 当前子请求为最后一个；
     fn process(&mut self, req: HashedCommand, last: bool);
+    // hash对应的分片，用于校验多个key是否落在同一个分片，如redis事务
+    fn shard_idx(&self, hash: i64) -> usize;
+    // client是否处于订阅模式，订阅模式下部分指令的响应格式不同，如redis的ping
+    #[inline]
+    fn subscribing(&self) -> bool {
+        false
+    }
+    // 分片数，用于拆分跨分片的请求
+    #[inline]
+    fn shards(&self) -> usize {
+        1
+    }
+    // client协商的协议版本，如redis通过hello切换到RESP3
+    #[inline]
+    fn resp3(&self) -> bool {
+        false
+    }
+    #[inline]
+    fn set_resp3(&mut self, _resp3: bool) {}
+    // client是否已通过鉴权，未配置鉴权时为true
+    #[inline]
+    fn authed(&self) -> bool {
+        true
+    }
+    // 校验client的鉴权密码，返回是否通过
+    #[inline]
+    fn auth(&mut self, _password: &ds::RingSlice) -> bool {
+        true
+    }
+    // 拒绝big value时，value长度的上限，解析时声明的长度超过上限即拒绝，不再等待数据；为0时不限制
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        0
+    }
+    // 解析时被拒绝的big value请求：req为已读到的请求头，len为声明的value长度。
+    // 与其他请求一样按顺序响应，响应由big_rejected构建，noforward的请求由协议自行响应
+    #[inline]
+    fn reject_big(&mut self, _req: HashedCommand, _len: usize) {}
+    // 被拒绝请求尚未读到的数据长度，读到后直接丢弃，不再解析
+    #[inline]
+    fn skip(&mut self, _n: usize) {}
 }
 
 pub struct Command {
@@ -152,6 +352,10 @@ impl Command {
     pub fn ok(&self) -> bool {
         self.ok
     }
+    #[inline]
+    pub fn set_ok(&mut self, ok: bool) {
+        self.ok = ok;
+    }
 }
 impl std::ops::Deref for Command {
     type Target = MemGuard;
@@ -191,6 +395,13 @@ impl HashedCommand {
             origin_cmd: None,
         }
     }
+    // 复制出一个不引用client buffer的请求，用于镜像等异步发送的场景
+    #[inline]
+    pub fn copied(&self) -> Self {
+        let mut data = Vec::with_capacity(self.len());
+        self.copy_to_vec(&mut data);
+        Self::new(MemGuard::from_vec(data), self.hash, self.flag.clone())
+    }
     #[inline]
     pub fn reset_flag(&mut self, op_code: u16, op: Operation) {
         self.flag.reset_flag(op_code, op);
@@ -220,6 +431,10 @@ impl HashedCommand {
         self.flag.noforward()
     }
     #[inline]
+    pub fn dedicated(&self) -> Dedicated {
+        self.flag.dedicated()
+    }
+    #[inline]
     pub fn flag(&self) -> &Flag {
         &self.flag
     }
@@ -281,6 +496,8 @@ pub trait Commander<M: Metric<I>, I: MetricItem> {
     fn request_shard(&self) -> usize;
     fn metric(&self) -> &M;
     fn ctx(&self) -> u64;
+    // 跨分片请求已返回的各分片响应，最后一个分片响应时合并
+    fn merged(&mut self) -> &mut Vec<u8>;
 }
 
 pub enum MetricName {
@@ -0,0 +1,53 @@
+use super::{
+    command::{CommandProperties, CommandType},
+    local::{self, LocalRsp},
+    packet::RequestPacket,
+    Redis,
+};
+use crate::{RequestProcessor, Result, Stream};
+use ds::RingSlice;
+
+// client鉴权：namespace配置了client密码时，client需要先鉴权：
+//   1. auth [username] password，只校验password，忽略username；
+//   2. hello protover AUTH username password，鉴权失败时不切换协议；
+//   3. 鉴权前只允许auth、hello、ping、quit，其他指令返回NOAUTH；
+//   4. 未配置client密码时，auth直接返回OK。
+impl Redis {
+    // 返回true表示请求已在本地响应
+    #[inline]
+    pub(super) fn parse_auth<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        process: &mut P,
+    ) -> Result<bool> {
+        let rsp = match cfg.cmd_type {
+            CommandType::Auth => {
+                let mut password = packet.parse_key()?;
+                if packet.has_bulk() {
+                    password = packet.parse_key()?;
+                }
+                packet.ignore_all_bulks()?;
+                match process.auth(&password) {
+                    true => LocalRsp::Ok,
+                    false => LocalRsp::WrongPass,
+                }
+            }
+            CommandType::Ping | CommandType::Hello => return Ok(false),
+            _ if cfg.quit => return Ok(false),
+            _ => {
+                packet.ignore_all_bulks()?;
+                LocalRsp::NoAuth
+            }
+        };
+        let _ = packet.take();
+        process.process(local::build(cfg, rsp), true);
+        Ok(true)
+    }
+}
+
+// hello的AUTH选项
+#[inline]
+pub(super) fn is_auth_option(opt: &RingSlice) -> bool {
+    opt.len() == 4 && (0..4).all(|i| opt.at(i).to_ascii_uppercase() == b"AUTH"[i])
+}
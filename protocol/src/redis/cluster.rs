@@ -0,0 +1,186 @@
+use super::{flag::RedisFlager, packet::Packet, Redis};
+use crate::{Command, Error, HashedCommand, Result, Stream};
+use ds::{MemGuard, RingSlice};
+
+const ASKING: &[u8] = b"*1\r\n$6\r\nASKING\r\n";
+const CLUSTER_SLOTS: &[u8] = b"*2\r\n$7\r\nCLUSTER\r\n$5\r\nSLOTS\r\n";
+const RSP_OK: &[u8] = b"+OK\r\n";
+
+// redis cluster的重定向响应：
+//   1. -MOVED <slot> <ip:port>：slot已迁移到新节点，需要更新slot map；
+//   2. -ASK <slot> <ip:port>：slot迁移中，只对本次请求生效，请求前需要先发送ASKING。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redirect {
+    Moved(u16, String),
+    Ask(u16, String),
+}
+
+impl Redirect {
+    #[inline]
+    pub fn parse(rsp: &RingSlice) -> Option<Self> {
+        if !is_redirect(rsp) {
+            return None;
+        }
+        let line = rsp.as_string_lossy();
+        let mut parts = line.trim_end().split(' ');
+        let kind = parts.next()?;
+        let slot = parts.next()?.parse::<u16>().ok()?;
+        let addr = parts.next()?.to_string();
+        match kind {
+            "-MOVED" => Some(Self::Moved(slot, addr)),
+            _ => Some(Self::Ask(slot, addr)),
+        }
+    }
+    #[inline]
+    pub fn addr(&self) -> &str {
+        match self {
+            Self::Moved(_, addr) | Self::Ask(_, addr) => addr,
+        }
+    }
+}
+
+#[inline]
+pub(super) fn is_redirect(rsp: &RingSlice) -> bool {
+    rsp.len() > 0
+        && rsp.at(0) == b'-'
+        && (rsp.start_with(0, b"-MOVED ") || rsp.start_with(0, b"-ASK "))
+}
+
+// ASK重定向时，在请求前加上ASKING，并标记请求，解析响应时跳过ASKING的响应
+#[inline]
+pub fn asking(req: &mut HashedCommand) {
+    let mut data = Vec::with_capacity(ASKING.len() + req.len());
+    data.extend_from_slice(ASKING);
+    req.copy_to_vec(&mut data);
+    req.reshape(MemGuard::from_vec(data));
+    req.set_asking();
+}
+
+impl Redis {
+    // 跳过ASKING的+OK；ASKING失败时，返回ASKING的响应，并丢弃请求的响应
+    #[inline]
+    pub(super) fn parse_asking_response<S: Stream>(
+        &self,
+        s: &mut S,
+        oft: &mut usize,
+    ) -> Result<Option<Command>> {
+        let data: Packet = s.slice().into();
+        data.skip_rsp(oft)?;
+        let first = *oft;
+        data.skip_rsp(oft)?;
+        if *oft > data.len() {
+            return Ok(None);
+        }
+        if first == RSP_OK.len() && data.start_with(0, RSP_OK) {
+            s.ignore(first);
+            let rsp = s.take(*oft - first);
+            return Ok(Some(Command::from(!is_redirect(&rsp), rsp)));
+        }
+        let rsp = s.take(first);
+        s.ignore(*oft - first);
+        Ok(Some(Command::from(false, rsp)))
+    }
+}
+
+// slot map中的一段连续slot，nodes[0]为master，其他为replica
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub nodes: Vec<String>,
+}
+
+// 获取slot map的请求。鉴权由后端连接的握手完成，cluster不支持select，忽略db
+#[inline]
+pub fn slots_request() -> &'static [u8] {
+    CLUSTER_SLOTS
+}
+
+// 解析slots_request的响应，数据不完整时返回ProtocolIncomplete。
+// CLUSTER SLOTS的响应：*n [*m :start :end [*k $ip :port $id ...] ...] ...
+// ip为空时，表示与当前请求的节点相同，使用host代替。
+pub fn parse_slots(data: &[u8], host: &str) -> Result<Vec<SlotRange>> {
+    let packet: Packet = RingSlice::from_slice(data).into();
+    // 整个响应完整后再解析
+    let mut oft = 0;
+    packet.skip_rsp(&mut oft)?;
+    if oft > data.len() {
+        return Err(Error::ProtocolIncomplete);
+    }
+    let mut oft = 0;
+    if packet.at(oft) != b'*' {
+        log::warn!("cluster slots failed:{:?}", packet);
+        return Err(Error::ResponseProtocolInvalid);
+    }
+    let ranges = packet.num_of_bulks(&mut oft)?;
+    let mut slots = Vec::with_capacity(ranges);
+    for _ in 0..ranges {
+        let end = {
+            let mut end = oft;
+            packet.skip_rsp(&mut end)?;
+            end
+        };
+        let fields = expect(&packet, &mut oft, b'*', |p, o| p.num_of_bulks(o))?;
+        if fields < 3 {
+            return Err(Error::ResponseProtocolInvalid);
+        }
+        let start = int(&packet, &mut oft)? as u16;
+        let last = int(&packet, &mut oft)? as u16;
+        let mut nodes = Vec::with_capacity(fields - 2);
+        for _ in 2..fields {
+            let node_end = {
+                let mut e = oft;
+                packet.skip_rsp(&mut e)?;
+                e
+            };
+            let n = expect(&packet, &mut oft, b'*', |p, o| p.num_of_bulks(o))?;
+            if n < 2 {
+                return Err(Error::ResponseProtocolInvalid);
+            }
+            let len = expect(&packet, &mut oft, b'$', |p, o| p.num_of_string(o))?;
+            let ip = packet.sub_slice(oft, len).as_string_lossy();
+            oft += len + 2;
+            let port = int(&packet, &mut oft)?;
+            let ip = if ip.is_empty() || ip == "?" {
+                host
+            } else {
+                &ip
+            };
+            nodes.push(format!("{}:{}", ip, port));
+            oft = node_end;
+        }
+        slots.push(SlotRange {
+            start,
+            end: last,
+            nodes,
+        });
+        oft = end;
+    }
+    Ok(slots)
+}
+
+#[inline]
+fn expect(
+    packet: &Packet,
+    oft: &mut usize,
+    t: u8,
+    f: impl Fn(&Packet, &mut usize) -> Result<usize>,
+) -> Result<usize> {
+    packet.check_onetoken(*oft)?;
+    match packet.at(*oft) == t {
+        true => f(packet, oft),
+        false => Err(Error::ResponseProtocolInvalid),
+    }
+}
+
+// :num\r\n
+#[inline]
+fn int(packet: &Packet, oft: &mut usize) -> Result<usize> {
+    packet.check_onetoken(*oft)?;
+    if packet.at(*oft) != b':' {
+        return Err(Error::ResponseProtocolInvalid);
+    }
+    let start = *oft + 1;
+    packet.line(oft)?;
+    Ok(packet.str_num(start..*oft - 2))
+}
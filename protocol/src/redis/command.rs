@@ -1,4 +1,4 @@
-use crate::{Flag, HashedCommand, OpCode, Operation, Result};
+use crate::{Dedicated, Flag, HashedCommand, OpCode, Operation, Result};
 use ds::{MemGuard, RingSlice};
 //use sharding::hash::{Bkdr, Hash, HashKey, UppercaseHashKey};
 
@@ -20,6 +20,36 @@ pub(crate) enum CommandType {
     SpecLocalCmdHashkey,
     // 计算批量key的分片索引
     SpecLocalCmdKeyshard,
+    //============== 事务指令，由mesh缓存整个事务 ==============//
+    Multi,
+    Exec,
+    Discard,
+    // 订阅模式下响应格式不同
+    Ping,
+    // 协商协议版本，本地响应
+    Hello,
+    // client鉴权，本地响应
+    Auth,
+    // key在STREAMS之后，数量不固定，如xread、xreadgroup
+    StreamRead,
+    //============== 跨分片指令 ==============//
+    // cursor中编码了分片索引，依次遍历所有分片
+    Scan,
+    // 发送到所有分片，合并所有分片的响应
+    Keys,
+    DbSize,
+    Info,
+}
+
+// 后端按RESP2响应，client协商RESP3后，顶层array需要按指令转换的类型
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Reply3 {
+    #[default]
+    Array,
+    // 如hgetall，field、value交替的array转为map
+    Map,
+    // 如smembers，array转为set
+    Set,
 }
 
 #[derive(Default)]
@@ -97,6 +127,10 @@ pub(crate) struct CommandProperties {
     pub(crate) quit: bool,               // 是否需要quit掉连接
     pub(crate) cmd_type: CommandType,    //用来标识自身，opcode非静态可知
     pub(crate) effect_on_next_req: bool, //对下一条指令有影响
+    pub(crate) dedicated: Dedicated,     // 是否需要client独占的后端连接，如阻塞、订阅类指令
+    pub(crate) master_only: bool,        // 读指令是否只能发送到master，如消费组相关的读取
+    pub(crate) reply3: Reply3,           // RESP3协议下响应的聚合类型
+    pub(crate) miss: &'static [u8], // key不存在时除nil之外的响应，如exists的:0，用于判断读请求是否未命中
 }
 
 // 默认响应
@@ -112,6 +146,12 @@ const PADDING_RSP_TABLE: [&str; 8] = [
     ":-10\r\n",                          //phantom -1返回已被服务端占用
 ];
 
+// key不存在时的响应，nil之外的部分
+const MISS_ZERO: &[u8] = b":0\r\n";
+const MISS_EMPTY: &[u8] = b"*0\r\n";
+const MISS_EMPTY_STR: &[u8] = b"$0\r\n\r\n";
+const MISS_TTL: &[u8] = b":-2\r\n";
+
 // 调用式确保idx < PADDING_RSP_TABLE.len()
 // 这个idx通常来自于CommandProperties.padding_rsp
 impl CommandProperties {
@@ -142,11 +182,36 @@ impl CommandProperties {
         Err(RedisError::ReqInvalidBulkNum.into())
     }
 
+    #[inline]
+    pub(crate) fn is_cross_shard(&self) -> bool {
+        matches!(
+            self.cmd_type,
+            CommandType::Scan | CommandType::Keys | CommandType::DbSize | CommandType::Info
+        )
+    }
+
+    #[inline]
+    pub(crate) fn is_txn(&self) -> bool {
+        matches!(
+            self.cmd_type,
+            CommandType::Multi | CommandType::Exec | CommandType::Discard
+        )
+    }
+
     pub(crate) fn flag(&self) -> crate::Flag {
         let mut flag = crate::Flag::from_op(self.op_code, self.op);
         flag.set_noforward(self.noforward);
+        flag.set_dedicated(self.dedicated);
+        if self.master_only {
+            use super::flag::RedisFlager;
+            flag.set_master_only();
+        }
         flag
     }
+    #[inline]
+    pub(crate) fn first_key_index(&self) -> u8 {
+        self.first_key_index
+    }
 
     // bulk_num只有在first=true时才有意义。
     #[inline]
@@ -195,6 +260,7 @@ impl CommandProperties {
 
 // https://redis.io/commands 一共145大类命令。使用 crate::sharding::Hash::Crc32
 // 算法能够完整的将其映射到0~4095这个区间。因为使用这个避免大量的match消耗。
+// 命令增多后hash会冲突（如discard与lsmexists），冲突时线性探测下一个空位，解析时比对命令名
 pub(super) struct Commands {
     supported: [CommandProperties; Self::MAPPING_RANGE],
     // hash: Crc32,
@@ -221,12 +287,40 @@ impl Commands {
         }
     }
 
+    // 根据命令名的hash查找命令，hash冲突时依次比对命令名
+    #[inline]
+    pub(crate) fn get_by_name(
+        &self,
+        hash: u16,
+        name: &RingSlice,
+    ) -> crate::Result<&CommandProperties> {
+        let mut idx = hash as usize;
+        loop {
+            let cmd = self.get_by_op(idx as u16)?;
+            if cmd.name.len() == name.len()
+                && (0..name.len())
+                    .all(|i| name.at(i).to_ascii_lowercase() == cmd.name.as_bytes()[i])
+            {
+                return Ok(cmd);
+            }
+            idx = Self::next(idx);
+        }
+    }
+    // 跳过0
+    #[inline]
+    fn next(idx: usize) -> usize {
+        idx % (Self::MAPPING_RANGE - 1) + 1
+    }
+
     #[inline]
     fn add_support(&mut self, mut c: CommandProperties) {
-        let idx = CommandHasher::hash_bytes(c.name.as_bytes()) as usize;
+        let mut idx = CommandHasher::hash_bytes(c.name.as_bytes()) as usize;
         assert!(idx > 0 && idx < self.supported.len(), "idx:{}", idx);
-        // 之前没有添加过。
-        assert!(!self.supported[idx].supported);
+        // hash冲突，线性探测下一个空位
+        while self.supported[idx].supported {
+            assert_ne!(self.supported[idx].name, c.name, "duplicated cmd");
+            idx = Self::next(idx);
+        }
         c.supported = true;
         c.op_code = idx as u16;
 
@@ -246,6 +340,14 @@ pub(crate) fn get_cfg(op_code: u16) -> crate::Result<&'static CommandProperties>
     SUPPORTED.get_by_op(op_code)
 }
 
+#[inline(always)]
+pub(crate) fn get_cfg_by_name(
+    hash: u16,
+    name: &RingSlice,
+) -> crate::Result<&'static CommandProperties> {
+    SUPPORTED.get_by_name(hash, name)
+}
+
 use Operation::*;
 type Cmd = CommandProperties;
 #[ctor::ctor]
@@ -256,12 +358,14 @@ pub(super) static SUPPORTED: Commands = {
     // TODO：后续增加新指令时，当multi/need_bulk_num 均为true时，需要在add_support中进行nil转换，避免将err返回到client fishermen
     for c in vec![
         //// meta 指令
-        //// 不支持select 0以外的请求。所有的select请求直接返回，默认使用db0
+        //// client的select请求直接返回，后端使用namespace配置的db（默认db0），在建连时select
         //// hello 参数应该是-1，可以不带或者带多个
         Cmd::new("command").arity(-1).op(Meta).padding(pt[1]).nofwd(),
-        Cmd::new("ping").arity(-1).op(Meta).padding(pt[2]).nofwd(),
+        Cmd::new("ping").arity(-1).op(Meta).padding(pt[2]).nofwd().cmd_type(CommandType::Ping),
         Cmd::new("select").arity(2).op(Meta).padding(pt[1]).nofwd(),
-        Cmd::new("hello").arity(-1).op(Meta).padding(pt[4]).nofwd(),
+        Cmd::new("hello").arity(-1).op(Meta).padding(pt[4]).nofwd().cmd_type(CommandType::Hello),
+        //// auth [username] password，校验namespace配置的client密码，本地响应
+        Cmd::new("auth").arity(-2).op(Meta).padding(pt[1]).nofwd().cmd_type(CommandType::Auth),
         // quit、master的指令token数/arity应该都是1,quit 的padding设为1 
         Cmd::new("quit").arity(1).op(Meta).padding(pt[1]).nofwd().quit(),
 
@@ -289,7 +393,7 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("del").arity(-2).op(Store).first(1).last(-1).step(1).padding(pt[3]).multi().key(),
 
         // 即便应对多语言，exists 也只支持一个key，否则需要计算多个后端数据，作为一个数字返回 fishermen
-        Cmd::new("exists").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("exists").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
         Cmd::new("expire").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("expireat").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("pexpire").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
@@ -303,18 +407,18 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("zremrangebyrank").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("zremrangebyscore").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("zremrangebylex").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zrevrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zcard").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("zrevrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
+        Cmd::new("zcard").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
+        Cmd::new("zrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
         Cmd::new("zrank").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zrangebyscore").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("zrangebyscore").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
 
         Cmd::new("zrevrank").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zrevrangebyscore").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("zrevrangebyscore").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
         Cmd::new("zrangebylex").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("zrevrangebylex").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zcount").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("zlexcount").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("zcount").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
+        Cmd::new("zlexcount").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
         Cmd::new("zscore").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("zscan").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
 
@@ -326,16 +430,16 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("hincrbyfloat").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
         Cmd::new("hdel").arity(-3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("hget").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("hgetall").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("hlen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("hkeys").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("hgetall").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().reply3(Reply3::Map).miss(MISS_EMPTY),
+        Cmd::new("hlen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
+        Cmd::new("hkeys").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
         Cmd::new("hmget").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("hvals").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("hexists").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("hvals").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
+        Cmd::new("hexists").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
         Cmd::new("hscan").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
 
-        Cmd::new("ttl").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("pttl").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("ttl").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_TTL),
+        Cmd::new("pttl").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_TTL),
         Cmd::new("setnx").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
         Cmd::new("setex").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
         Cmd::new("append").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
@@ -359,9 +463,9 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("lset").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
         Cmd::new("rpop").arity(2).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("lpop").arity(2).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("llen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("llen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
         Cmd::new("lindex").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("lrange").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("lrange").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY),
         Cmd::new("ltrim").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("lrem").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
 
@@ -372,9 +476,9 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("bitpos").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("bitfield").arity(-2).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("setrange").arity(4).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
-        Cmd::new("getrange").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("getrange").arity(4).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_EMPTY_STR),
         Cmd::new("getset").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
-        Cmd::new("strlen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("strlen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
 
         // 测试完毕后规整到incr附近
         Cmd::new("incrby").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
@@ -384,16 +488,16 @@ pub(super) static SUPPORTED: Commands = {
         // set 相关指令
         Cmd::new("sadd").arity(-3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
         Cmd::new("srem").arity(-3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("sismember").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("scard").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("sismember").arity(3).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
+        Cmd::new("scard").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().miss(MISS_ZERO),
         Cmd::new("spop").arity(-2).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("srandmember").arity(-2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
-        Cmd::new("smembers").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("smembers").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key().reply3(Reply3::Set).miss(MISS_EMPTY),
         Cmd::new("sscan").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
         // set 多个key相关的指令
-        Cmd::new("sinter").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
-        Cmd::new("sunion").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
-        Cmd::new("sdiff").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
+        Cmd::new("sinter").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key().reply3(Reply3::Set),
+        Cmd::new("sunion").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key().reply3(Reply3::Set),
+        Cmd::new("sdiff").arity(-2).op(Get).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key().reply3(Reply3::Set),
         Cmd::new("sunionstore").arity(-3).op(Store).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
         Cmd::new("sinterstore").arity(-3).op(Store).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
         Cmd::new("sdiffstore").arity(-3).op(Store).first(1).last(-1).step(1).padding(pt[4]).need_resv_hash().key(),
@@ -430,6 +534,57 @@ pub(super) static SUPPORTED: Commands = {
         Cmd::new("evalsha").arity(-3).op(Store).padding(pt[3]).need_resv_hash(),
         Cmd::new("eval").arity(-3).op(Store).padding(pt[3]).need_resv_hash(),
 
+        // 事务指令：multi之后的指令缓存在mesh，要求所有key落在同一分片，exec时整体发送到该分片的master
+        // exec 只在事务中转发，padding用于后端不可用；事务外的exec、discard在解析时直接响应
+        // watch发送到client独占的master连接，之后的exec、discard、unwatch也发送到该连接，事务需要与watch落在同一分片；
+        // 没有watch时，unwatch直接返回+OK
+        Cmd::new("multi").arity(1).op(Meta).padding(pt[1]).nofwd().cmd_type(CommandType::Multi),
+        Cmd::new("exec").arity(1).op(Store).padding(pt[3]).cmd_type(CommandType::Exec),
+        Cmd::new("discard").arity(1).op(Meta).padding(pt[1]).nofwd().cmd_type(CommandType::Discard),
+        Cmd::new("watch").arity(-2).op(Meta).first(1).last(-1).step(1).padding(pt[3]).key().dedicated(Dedicated::Watch),
+        Cmd::new("unwatch").arity(1).op(Meta).padding(pt[1]).nofwd().dedicated(Dedicated::Unwatch),
+
+        // 阻塞指令：占用后端连接直到有数据或超时，发送到client独占的分片master连接，多个key需要落在同一分片
+        Cmd::new("blpop").arity(-3).op(Store).first(1).last(-2).step(1).padding(pt[3]).key().dedicated(Dedicated::Block),
+        Cmd::new("brpop").arity(-3).op(Store).first(1).last(-2).step(1).padding(pt[3]).key().dedicated(Dedicated::Block),
+        Cmd::new("bzpopmin").arity(-3).op(Store).first(1).last(-2).step(1).padding(pt[3]).key().dedicated(Dedicated::Block),
+        Cmd::new("bzpopmax").arity(-3).op(Store).first(1).last(-2).step(1).padding(pt[3]).key().dedicated(Dedicated::Block),
+
+        // 订阅类指令：publish按channel路由；订阅按channel拆分，发送到client独占的分片master连接，响应均由后端推送；
+        // 模式订阅无法确定channel，发送到所有分片；不带channel的unsubscribe发送到所有已建立的订阅连接
+        Cmd::new("publish").arity(3).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
+        Cmd::new("subscribe").m("subscribe").arity(-2).op(Meta).first(1).last(-1).step(1).padding(pt[1]).multi().key().nofwd().dedicated(Dedicated::Subscribe),
+        Cmd::new("unsubscribe").m("unsubscribe").arity(-1).op(Meta).first(1).last(-1).step(1).padding(pt[1]).multi().key().nofwd().dedicated(Dedicated::Unsubscribe),
+        Cmd::new("psubscribe").arity(-2).op(Meta).padding(pt[1]).nofwd().dedicated(Dedicated::Pattern),
+        Cmd::new("punsubscribe").arity(-1).op(Meta).padding(pt[1]).nofwd().dedicated(Dedicated::Pattern),
+
+        // 跨分片指令：scan返回的cursor中编码了分片索引，client按cursor遍历即可访问所有分片，
+        // 不同节点的cursor不通用，scan只发送到master；
+        // keys、dbsize、info发送到所有分片，合并响应，info只返回合并后的keyspace
+        Cmd::new("scan").arity(-2).op(Get).padding(pt[3]).master_only().cmd_type(CommandType::Scan),
+        Cmd::new("keys").arity(2).op(Get).padding(pt[3]).cmd_type(CommandType::Keys),
+        Cmd::new("dbsize").arity(1).op(Get).padding(pt[3]).cmd_type(CommandType::DbSize),
+        Cmd::new("info").arity(-1).op(Get).padding(pt[3]).cmd_type(CommandType::Info),
+
+        // stream 相关指令
+        // xgroup、xinfo的key在子命令之后；消费组的状态只在master上是准确的，相关的读取只发送到master
+        // xread、xreadgroup的key在STREAMS之后，所有key需要落在同一个分片，带BLOCK时发送到client独占的分片master连接
+        Cmd::new("xadd").arity(-5).op(Store).first(1).last(1).step(1).padding(pt[3]).key().val(),
+        Cmd::new("xlen").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xrevrange").arity(-4).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xdel").arity(-3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xtrim").arity(-4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xsetid").arity(-3).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xack").arity(-4).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xclaim").arity(-6).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xautoclaim").arity(-6).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
+        Cmd::new("xgroup").arity(-3).op(Store).first(2).last(2).step(1).padding(pt[3]).key(),
+        Cmd::new("xpending").arity(-3).op(Get).first(1).last(1).step(1).padding(pt[3]).key().master_only(),
+        Cmd::new("xinfo").arity(-2).op(Get).first(2).last(2).step(1).padding(pt[3]).key().master_only(),
+        Cmd::new("xread").arity(-4).op(Get).padding(pt[3]).cmd_type(CommandType::StreamRead),
+        Cmd::new("xreadgroup").arity(-7).op(Store).padding(pt[3]).cmd_type(CommandType::StreamRead),
+
         //phantom
         Cmd::new("bfget").arity(2).op(Get).first(1).last(1).step(1).padding(pt[3]).key(),
         Cmd::new("bfset").arity(2).op(Store).first(1).last(1).step(1).padding(pt[3]).key(),
@@ -470,16 +625,9 @@ pub(super) static SUPPORTED: Commands = {
         // {"debug",debugCommand,-2,0,NULL,0,0,0},
 
         // 订阅类指令，暂不支持
-        // {"unsubscribe",unsubscribeCommand,-1,0,NULL,0,0,0},
-        // {"psubscribe",psubscribeCommand,-2,0,NULL,0,0,0},
-        // {"punsubscribe",punsubscribeCommand,-1,0,NULL,0,0,0},
-        // {"publish",publishCommand,3,REDIS_CMD_FORCE_REPLICATION|REDIS_CMD_WRITE,NULL,0,0,0},
-        // "subscribe" => (-2, Operation::Get, 0, 0, 0),
         // {"pubsub", pubsubCommand, -2, REDIS_CMD_READONLY|REDIS_CMD_PUBSUB, NULL, 0, 0, 0},
 
         // 特殊指令，暂不支持
-        // {"watch",watchCommand,-2,0,NULL,1,-1,1},
-        // {"unwatch",unwatchCommand,1,0,NULL,0,0,0},
         // {"object",objectCommand,-2,0,NULL,2,2,1},
 
         // 涉及多个key，先不支持了
@@ -493,8 +641,6 @@ pub(super) static SUPPORTED: Commands = {
         // "psetex" => (4, Operation::Store, 1, 1, 1),
         // "substr" => (4, Operation::Get, 1, 1, 1),
         // "rpoplpush" => (3, Operation::Store, 1, 2, 1),
-        // "brpop" => (-3, Operation::Store, 1, -2, 1),
-        // "blpop" => (-3, Operation::Store, 1, -2, 1),
         // "brpoplpush" => (4, Operation::Store, 1, 2, 1),
 
         // 涉及多个key的操作，暂不支持
@@ -510,23 +656,14 @@ pub(super) static SUPPORTED: Commands = {
         // "move" => (3, Operation::Store, 1, 1, 1),
         // "rename" => (3, Operation::Store, 1, 2, 1),
         // "renamenx" => (3, Operation::Store, 1, 2, 1),
-        // "keys" => (2, Operation::Get, 0, 0, 0),
-        // "scan" => (-2, Operation::Get, 0, 0, 0),
-        // "dbsize" => (1, Operation::Get, 0, 0, 0),
         // "auth" => (2, Operation::Meta, 0, 0, 0),
         // "echo" => (2, Operation::Meta, 0, 0, 0),
-        // info 先不在client支持
-        // "info" => (-1, Operation::Meta, 0, 0, 0),
 
         // "config" => (-2, Operation::Meta, 0, 0, 0),
 
         // "time" => (1, Operation::Get, 0, 0, 0),
 
         // ********** 二期实现
-        // 事务类、脚本类cmd，暂时先不支持，二期再处理 fishermen
-        // "multi" => (1, Operation::Store, 0, 0, 0),
-        // "exec" => (1, Operation::Store, 0, 0, 0),
-        // "discard" => (1, Operation::Get, 0, 0, 0),
         // "sort" => (-2, Operation::Store, 1, 1, 1),
         // "client" => (-2, Operation::Meta, 0, 0, 0),
 
@@ -632,4 +769,20 @@ impl CommandProperties {
         self.cmd_type = cmd_type;
         self
     }
+    pub(crate) fn dedicated(mut self, dedicated: Dedicated) -> Self {
+        self.dedicated = dedicated;
+        self
+    }
+    pub(crate) fn master_only(mut self) -> Self {
+        self.master_only = true;
+        self
+    }
+    pub(crate) fn miss(mut self, miss: &'static [u8]) -> Self {
+        self.miss = miss;
+        self
+    }
+    pub(crate) fn reply3(mut self, reply3: Reply3) -> Self {
+        self.reply3 = reply3;
+        self
+    }
 }
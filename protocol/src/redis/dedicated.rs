@@ -0,0 +1,155 @@
+use super::{
+    command::CommandProperties,
+    local::{self, LocalRsp},
+    packet::{Packet, RequestPacket},
+    resp3, Redis,
+};
+use crate::{
+    Command, Dedicated, HashedCommand, RequestProcessor, Result, Stream, Subscriptions, Writer,
+};
+use sharding::hash::Hash;
+
+// 订阅、取消订阅的确认中，第一个元素的取值
+const SUB_KINDS: [&[u8]; 4] = [b"subscribe", b"unsubscribe", b"psubscribe", b"punsubscribe"];
+
+// 阻塞、订阅类指令需要client独占的后端连接，由pipeline按分片建立：
+//   1. 阻塞指令、watch整体发送，所有key需要落在同一分片，否则响应CROSSSLOT；
+//   2. subscribe、unsubscribe按channel拆分，每个channel对应一个确认；
+//   3. 模式订阅及不带channel的unsubscribe整体发送，由pipeline发送到多个连接。
+// 订阅类指令在本地不响应，确认及消息都由后端推送，通过write_push返回给client。
+impl Redis {
+    #[inline]
+    pub(super) fn parse_dedicated<S: Stream, H: Hash, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        alg: &H,
+        process: &mut P,
+    ) -> Result<()> {
+        match cfg.dedicated {
+            Dedicated::Block | Dedicated::Watch => {
+                let flag = packet.flag(cfg);
+                let hash = packet.hash(cfg, alg)?;
+                // 阻塞指令最后一个参数是timeout，watch的参数都是key
+                let tail = (cfg.dedicated == Dedicated::Block) as u16;
+                let same = packet.check_keys_shard(hash, tail, alg, process)?;
+                packet.ignore_all_bulks()?;
+                let cmd = packet.take();
+                let req = match same {
+                    true => HashedCommand::new(cmd, hash, flag),
+                    false => local::build(cfg, LocalRsp::CrossShard),
+                };
+                process.process(req, true);
+            }
+            Dedicated::Subscribe | Dedicated::Unsubscribe if packet.has_bulk() => {
+                packet.multi_ready();
+                while packet.has_bulk() {
+                    let bulk = packet.bulk();
+                    let first = packet.first();
+                    let flag = packet.flag(cfg);
+                    let hash = packet.hash(cfg, alg)?;
+                    let channel = packet.take();
+                    let req = cfg.build_request(hash, bulk, first, flag, &channel);
+                    process.process(req, packet.complete());
+                }
+            }
+            _ => {
+                let mut flag = packet.flag(cfg);
+                if cfg.dedicated == Dedicated::Unsubscribe {
+                    flag.set_dedicated(Dedicated::UnsubscribeAll);
+                }
+                packet.ignore_all_bulks()?;
+                let req = HashedCommand::new(packet.take(), 0, flag);
+                process.process(req, true);
+            }
+        }
+        Ok(())
+    }
+
+    // 订阅连接推送的数据：
+    //   1. 订阅确认中的订阅数是单个连接的，需要修正为client总的订阅数；
+    //   2. 模式订阅发送到了所有分片，只返回第0个分片的确认；
+    //   3. 连接上没有订阅时，取消订阅的确认中channel为nil，client还有其他订阅时丢弃；
+    //   4. 消息等其他数据直接返回；
+    //   5. RESP3的client，推送数据的顶层array转为push类型（>），nil转为null。
+    #[inline]
+    pub(super) fn write_push_inner<W: Writer>(
+        &self,
+        subs: &mut Subscriptions,
+        shard: usize,
+        pattern: bool,
+        data: &Command,
+        w: &mut W,
+    ) -> Result<()> {
+        let packet: Packet = data.sub_slice(0, data.len()).into();
+        // RESP3的client，推送的array转为push类型
+        let resp3 = subs.resp3() && packet.at(0) == b'*';
+        let Some((nil, oft)) = sub_confirm(&packet) else {
+            return match resp3 {
+                true => {
+                    w.write_u8(b'>')?;
+                    w.write_slice(data, 1)
+                }
+                false => w.write_slice(data, 0),
+            };
+        };
+        if pattern && shard > 0 {
+            return Ok(());
+        }
+        let mut count = 0usize;
+        for i in oft + 1..packet.len() {
+            match packet.at(i) {
+                b'\r' => break,
+                d if d.is_ascii_digit() => {
+                    count = count.saturating_mul(10).saturating_add((d - b'0') as usize)
+                }
+                // 订阅数不合法，不再修正，直接返回
+                _ => return w.write_slice(data, 0),
+            }
+        }
+        let total = subs.update(shard, pattern, count);
+        if nil && total > 0 {
+            return Ok(());
+        }
+        match (resp3, nil) {
+            (false, _) => packet.copy_to(0..oft, w)?,
+            (true, false) => {
+                w.write_u8(b'>')?;
+                packet.copy_to(1..oft, w)?;
+            }
+            // RESP3中nil的channel为null
+            (true, true) => {
+                w.write_u8(b'>')?;
+                packet.copy_to(1..oft - resp3::NIL.len(), w)?;
+                w.write(resp3::NULL)?;
+            }
+        }
+        w.write(b":")?;
+        w.write_str_num(total)?;
+        w.write(b"\r\n")
+    }
+}
+
+// 订阅确认的格式：*3\r\n$kind\r\n$channel\r\n:count\r\n；
+// 是订阅确认时，返回channel是否为nil，以及count的位置
+#[inline]
+fn sub_confirm(data: &Packet) -> Option<(bool, usize)> {
+    let mut oft = 0;
+    if data.len() < 4 || data.at(0) != b'*' || data.num_of_bulks(&mut oft).ok()? != 3 {
+        return None;
+    }
+    if data.at(oft) != b'$' {
+        return None;
+    }
+    let len = data.num_of_string(&mut oft).ok()?;
+    if !SUB_KINDS
+        .iter()
+        .any(|k| k.len() == len && data.start_with(oft, k))
+    {
+        return None;
+    }
+    oft += len + 2;
+    let nil = data.at(oft + 1) == b'-';
+    oft += data.num_of_string(&mut oft).ok()? + 2;
+    (oft < data.len() && data.at(oft) == b':').then_some((nil, oft))
+}
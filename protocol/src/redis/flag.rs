@@ -11,7 +11,24 @@ const MASTER_ONLY_SHIFT: u8 = MKEY_FIRST_SHIFT + MKEY_FIRST_BIT;
 const MASTER_ONLY_BIT: u8 = 1;
 // [18]: sendto_all
 const SENDTO_ALL_SHIFT: u8 = MASTER_ONLY_SHIFT + MASTER_ONLY_BIT;
-const _SENDTO_ALL_BIT: u8 = 1;
+const SENDTO_ALL_BIT: u8 = 1;
+// [19]: 事务请求，即multi...exec整体发送，此时key count为事务中的指令数
+const TXN_SHIFT: u8 = SENDTO_ALL_SHIFT + SENDTO_ALL_BIT;
+const TXN_BIT: u8 = 1;
+// [20..25]: 本地构建的响应，如事务、订阅模式下的ping
+const LOCAL_RSP_SHIFT: u8 = TXN_SHIFT + TXN_BIT;
+const LOCAL_RSP_BITS: u8 = 5;
+const LOCAL_RSP_MASK: u64 = (1 << LOCAL_RSP_BITS) - 1;
+// [25]: client协商使用RESP3，响应需要按RESP3构建
+const RESP3_SHIFT: u8 = LOCAL_RSP_SHIFT + LOCAL_RSP_BITS;
+const RESP3_BIT: u8 = 1;
+// [26]: redis cluster的ASK重定向，请求前加了ASKING，响应需要跳过ASKING的响应
+const ASKING_SHIFT: u8 = RESP3_SHIFT + RESP3_BIT;
+const ASKING_BIT: u8 = 1;
+// [27]: 分片迁移期间的读请求，未命中的响应标记为!ok，由topo到迁移前的分片重试
+const MISS_NOTOK_SHIFT: u8 = ASKING_SHIFT + ASKING_BIT;
+// 支持跨分片请求的最大分片数，分片数记录在key count中
+pub(crate) const MAX_SHARDS: usize = KEY_COUNT_MASK as usize;
 
 pub trait RedisFlager {
     fn set_key_count(&mut self, cnt: u16);
@@ -26,6 +43,20 @@ pub trait RedisFlager {
     fn master_only(&self) -> bool;
     fn set_sendto_all(&mut self);
     fn sendto_all(&self) -> bool;
+    fn set_txn(&mut self);
+    fn txn(&self) -> bool;
+    fn set_local_rsp(&mut self, rsp: u8);
+    fn local_rsp(&self) -> u8;
+    // 跨分片指令（如scan、keys）的子请求复用sendto_all，key count记录解析时的分片数，
+    // 请求的hash为指定的分片索引
+    fn set_cross_shard(&mut self, shards: usize);
+    fn cross_shards(&self) -> Option<usize>;
+    fn set_resp3(&mut self);
+    fn resp3(&self) -> bool;
+    fn set_asking(&mut self);
+    fn asking(&self) -> bool;
+    fn set_miss_notok(&mut self);
+    fn miss_notok(&self) -> bool;
 
     // fn set_ignore_rsp(&mut self, ignore_rsp: bool);
     // fn ignore_rs(&self) -> bool;
@@ -84,4 +115,56 @@ impl<T: Ext> RedisFlager for T {
     fn sendto_all(&self) -> bool {
         self.get(SENDTO_ALL_SHIFT)
     }
+    #[inline]
+    fn set_txn(&mut self) {
+        self.set(TXN_SHIFT);
+    }
+    #[inline]
+    fn txn(&self) -> bool {
+        self.get(TXN_SHIFT)
+    }
+    #[inline]
+    fn set_local_rsp(&mut self, rsp: u8) {
+        self.mask_set(LOCAL_RSP_SHIFT, LOCAL_RSP_MASK, rsp as u64)
+    }
+    #[inline]
+    fn local_rsp(&self) -> u8 {
+        self.mask_get(LOCAL_RSP_SHIFT, LOCAL_RSP_MASK) as u8
+    }
+    #[inline]
+    fn set_cross_shard(&mut self, shards: usize) {
+        debug_assert!(shards > 0 && shards <= MAX_SHARDS, "{}", shards);
+        self.set_sendto_all();
+        self.set_key_count(shards as u16);
+    }
+    // multi指令的第一个子请求也会设置key count，需要排除
+    #[inline]
+    fn cross_shards(&self) -> Option<usize> {
+        let shards = self.key_count() as usize;
+        (self.sendto_all() && !self.mkey_first() && shards > 0).then_some(shards)
+    }
+    #[inline]
+    fn set_resp3(&mut self) {
+        self.set(RESP3_SHIFT);
+    }
+    #[inline]
+    fn resp3(&self) -> bool {
+        self.get(RESP3_SHIFT)
+    }
+    #[inline]
+    fn set_asking(&mut self) {
+        self.set(ASKING_SHIFT);
+    }
+    #[inline]
+    fn asking(&self) -> bool {
+        self.get(ASKING_SHIFT)
+    }
+    #[inline]
+    fn set_miss_notok(&mut self) {
+        self.set(MISS_NOTOK_SHIFT);
+    }
+    #[inline]
+    fn miss_notok(&self) -> bool {
+        self.get(MISS_NOTOK_SHIFT)
+    }
 }
@@ -0,0 +1,76 @@
+use super::{packet::Packet, Redis};
+use crate::{HandShake, ResOption, Result, Stream};
+
+// 后端连接的握手状态，保存在stream context中：[0]为状态，[1]为待接收的响应数
+const STATUS_IDX: usize = 0;
+const PENDING_IDX: usize = 1;
+const STATUS_INIT: u8 = 0;
+const STATUS_SENT: u8 = 1;
+
+const RSP_OK: &[u8] = b"+OK\r\n";
+
+impl Redis {
+    // 在handler启动前完成握手，AUTH及SELECT一次性发出，然后依次校验响应：
+    //   1. 配置了password时发送AUTH：user为空时AUTH <password>；否则AUTH <user> <password>（redis 6.0+ ACL）；
+    //   2. 配置了非0的db时发送SELECT <db>。
+    // 使用AUTH而非HELLO，以兼容6.0之前的redis及phantom。
+    #[inline]
+    pub(super) fn handshake_inner<S: Stream>(
+        &self,
+        stream: &mut S,
+        option: &ResOption,
+    ) -> Result<HandShake> {
+        if stream.context()[STATUS_IDX] == STATUS_INIT {
+            let mut pending = 0;
+            if !option.token.is_empty() {
+                let mut args = vec!["AUTH"];
+                if !option.username.is_empty() {
+                    args.push(&option.username);
+                }
+                args.push(&option.token);
+                self.write_cmd(stream, &args)?;
+                pending += 1;
+            }
+            if option.db > 0 {
+                self.write_cmd(stream, &["SELECT", &option.db.to_string()])?;
+                pending += 1;
+            }
+            // 无需握手
+            if pending == 0 {
+                return Ok(HandShake::Success);
+            }
+            stream.context()[STATUS_IDX] = STATUS_SENT;
+            stream.context()[PENDING_IDX] = pending;
+            return Ok(HandShake::Continue);
+        }
+
+        while stream.context()[PENDING_IDX] > 0 {
+            let data: Packet = stream.slice().into();
+            let mut oft = 0;
+            data.check_onetoken(oft)?;
+            data.line(&mut oft)?;
+            let ok = oft == RSP_OK.len() && data.start_with(0, RSP_OK);
+            if !ok {
+                log::warn!("+++ redis handshake failed:{:?}", data.sub_slice(0, oft));
+            }
+            stream.ignore(oft);
+            if !ok {
+                return Ok(HandShake::Failed);
+            }
+            stream.context()[PENDING_IDX] -= 1;
+        }
+        // 握手结束，清理状态，context后续由handler使用
+        *stream.context() = Default::default();
+        Ok(HandShake::Success)
+    }
+
+    // *n\r\n$len\r\narg\r\n...
+    #[inline]
+    fn write_cmd<S: Stream>(&self, stream: &mut S, args: &[&str]) -> Result<()> {
+        let mut cmd = format!("*{}\r\n", args.len());
+        for arg in args {
+            cmd.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        stream.write(cmd.as_bytes())
+    }
+}
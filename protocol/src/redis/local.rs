@@ -0,0 +1,124 @@
+use super::{command::CommandProperties, flag::RedisFlager};
+use crate::{Dedicated, Flag, HashedCommand, Operation, Result, Writer};
+use ds::MemGuard;
+
+// 本地构建的响应，记录在flag中，由write_response直接返回给client
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum LocalRsp {
+    Ok = 1,
+    Queued,
+    ExecWithoutMulti,
+    DiscardWithoutMulti,
+    ExecAbort,
+    Nested,
+    NotAllowed,
+    NoShard,
+    CrossShard,
+    Empty,
+    SubPong,
+    Hello,
+    NoProto,
+    NoAuth,
+    WrongPass,
+    WatchInMulti,
+    TxnTooLarge,
+    SyntaxErr,
+    TooLarge,
+}
+
+// 第0个表示非本地响应
+const LOCAL_RSP_TABLE: [&str; 20] = [
+    "",
+    "+OK\r\n",
+    "+QUEUED\r\n",
+    "-ERR EXEC without MULTI\r\n",
+    "-ERR DISCARD without MULTI\r\n",
+    "-EXECABORT Transaction discarded because of previous errors.\r\n",
+    "-ERR MULTI calls can not be nested\r\n",
+    "-ERR command not allowed in MULTI\r\n",
+    "-ERR no key to locate shard in MULTI(try hashkey before multi)\r\n",
+    "-CROSSSLOT Keys in request don't hash to the same shard\r\n",
+    "*0\r\n",
+    // 订阅模式下ping的响应，忽略ping的参数
+    "*2\r\n$4\r\npong\r\n$0\r\n\r\n",
+    // hello的响应按协商的协议版本构建，见write_hello
+    "",
+    "-NOPROTO unsupported protocol version\r\n",
+    // client鉴权
+    "-NOAUTH Authentication required.\r\n",
+    "-WRONGPASS invalid username-password pair or user is disabled.\r\n",
+    "-ERR WATCH inside MULTI is not allowed\r\n",
+    "-ERR MULTI transaction too large\r\n",
+    "-ERR syntax error\r\n",
+    // key或value超过上限
+    TOO_LARGE,
+];
+
+pub(super) const TOO_LARGE: &str = "-ERR key or value too large\r\n";
+
+#[inline]
+pub(super) fn rsp(req: &HashedCommand) -> Option<&'static [u8]> {
+    match req.local_rsp() {
+        0 => None,
+        idx => Some(LOCAL_RSP_TABLE[idx as usize].as_bytes()),
+    }
+}
+
+// hello的响应字段，proto为协商的协议版本
+const HELLO_FIELDS: [(&str, &str); 6] = [
+    ("server", "$5\r\nredis\r\n"),
+    ("version", "$5\r\n6.0.0\r\n"),
+    ("id", ":0\r\n"),
+    ("mode", "$10\r\nstandalone\r\n"),
+    ("role", "$6\r\nmaster\r\n"),
+    ("modules", "*0\r\n"),
+];
+
+// hello的响应，RESP2为数组，RESP3为map
+#[inline]
+pub(super) fn write_hello<W: Writer>(resp3: bool, w: &mut W) -> Result<()> {
+    let (head, proto): (&[u8], &[u8]) = match resp3 {
+        true => (b"%7\r\n", b":3\r\n"),
+        false => (b"*14\r\n", b":2\r\n"),
+    };
+    w.write(head)?;
+    for (i, (name, val)) in HELLO_FIELDS.iter().enumerate() {
+        // proto位于version之后
+        if i == 2 {
+            w.write(b"$5\r\nproto\r\n")?;
+            w.write(proto)?;
+        }
+        w.write(b"$")?;
+        w.write_str_num(name.len())?;
+        w.write(b"\r\n")?;
+        w.write(name.as_bytes())?;
+        w.write(b"\r\n")?;
+        w.write(val.as_bytes())?;
+    }
+    Ok(())
+}
+
+// 本地响应的请求，不发送到后端，按meta统计
+#[inline]
+pub(super) fn build(cfg: &CommandProperties, rsp: LocalRsp) -> HashedCommand {
+    let mut flag = Flag::from_op(cfg.op_code, Operation::Meta);
+    flag.set_noforward(true);
+    flag.set_local_rsp(rsp as u8);
+    HashedCommand::new(MemGuard::from_vec(cfg.name.as_bytes().to_vec()), 0, flag)
+}
+
+// 事务中的discard：本地响应+OK，同时清理watch。
+// 有watch时由pipeline把unwatch发送到watch所在的独占连接，后端的响应丢弃
+#[inline]
+pub(super) fn discard(cfg: &CommandProperties) -> HashedCommand {
+    let mut flag = Flag::from_op(cfg.op_code, Operation::Meta);
+    flag.set_noforward(true);
+    flag.set_local_rsp(LocalRsp::Ok as u8);
+    flag.set_dedicated(Dedicated::Unwatch);
+    HashedCommand::new(
+        MemGuard::from_vec(b"*1\r\n$7\r\nUNWATCH\r\n".to_vec()),
+        0,
+        flag,
+    )
+}
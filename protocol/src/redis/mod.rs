@@ -1,14 +1,22 @@
+mod client_auth;
+pub mod cluster;
 pub(crate) mod command;
+mod dedicated;
 pub(crate) mod error;
 pub(crate) mod flag;
+mod handshake;
+mod local;
+mod resp3;
+mod shards;
+mod streams;
+mod txn;
 pub use flag::RedisFlager;
 pub(crate) mod packet;
 
 use crate::{
-    redis::command::CommandType,
-    redis::{error::RedisError, packet::RequestPacket},
-    Command, Commander, Error, HashedCommand, Metric, MetricItem, MetricName, Protocol,
-    RequestProcessor, Result, Stream, Writer,
+    redis::command::CommandType, redis::packet::RequestPacket, Command, Commander, Dedicated,
+    Error, HandShake, HashedCommand, Metric, MetricItem, MetricName, Protocol, RequestProcessor,
+    ResOption, Result, Stream, Subscriptions, Writer,
 };
 pub use packet::Packet;
 use sharding::hash::Hash;
@@ -29,9 +37,49 @@ impl Redis {
         // TODO 先保留到2022.12，用于快速定位协议问题 fishermen
         log::debug!("+++ rec redis req:{:?}", packet.inner_data());
         while packet.available() {
+            if packet.discarding() {
+                packet.discard()?;
+                continue;
+            }
+            if packet.in_txn() {
+                self.parse_txn(packet, alg, process)?;
+                continue;
+            }
             packet.parse_bulk_num()?;
             let cfg = packet.parse_cmd()?;
+            if !process.authed() || cfg.cmd_type == CommandType::Auth {
+                // 未鉴权时，只允许auth、hello、ping、quit
+                if self.parse_auth(packet, cfg, process)? {
+                    packet.clear_status(cfg);
+                    continue;
+                }
+            }
+            if cfg.is_txn() {
+                self.parse_txn_start(packet, cfg, process)?;
+                continue;
+            }
+            if cfg.cmd_type == CommandType::Hello {
+                self.parse_hello(packet, cfg, process)?;
+                packet.clear_status(cfg);
+                continue;
+            }
+            if cfg.is_cross_shard() {
+                self.parse_cross_shard(packet, cfg, process)?;
+                packet.clear_status(cfg);
+                continue;
+            }
+            if cfg.cmd_type == CommandType::StreamRead {
+                self.parse_stream_read(packet, cfg, alg, process)?;
+                packet.clear_status(cfg);
+                continue;
+            }
+            if cfg.dedicated != Dedicated::None {
+                self.parse_dedicated(packet, cfg, alg, process)?;
+                packet.clear_status(cfg);
+                continue;
+            }
             if cfg.multi {
+                packet.check_bulks()?;
                 packet.multi_ready();
                 while packet.has_bulk() {
                     // take会将first变为false, 需要在take之前调用。
@@ -50,11 +98,15 @@ impl Redis {
                     process.process(req, packet.complete());
                 }
             } else {
-                let (flag, hash) = if cfg.effect_on_next_req {
+                let (mut flag, hash) = if cfg.effect_on_next_req {
                     packet.proc_effect_on_next_req_cmd(&cfg, alg)?
                 } else {
                     (packet.flag(cfg), packet.hash(cfg, alg)?)
                 };
+                // 订阅模式下ping的响应格式不同
+                if cfg.cmd_type == CommandType::Ping && process.subscribing() {
+                    flag.set_local_rsp(local::LocalRsp::SubPong as u8);
+                }
 
                 packet.ignore_all_bulks()?;
                 let cmd = packet.take();
@@ -77,21 +129,34 @@ impl Redis {
     ) -> Result<Option<Command>> {
         let data: Packet = s.slice().into();
         log::debug!("+++ will parse redis rsp:{:?}", data);
-        data.check_onetoken(*oft)?;
-
-        match data.at(0) {
-            b'-' | b':' | b'+' => data.line(oft)?,
-            b'$' => {
-                *oft += data.num_of_string(oft)? + 2;
-            }
-            b'*' => data.skip_all_bulk(oft)?,
-            _ => {
-                log::error!("+++ found malformed redis rsp:{:?}", data);
-                return Err(RedisError::RespInvalid.into());
-            } // _ => panic!("not supported:{:?}", data),
-        }
+        data.skip_rsp(oft)?;
+
+        // cluster的重定向响应标记为!ok，由topo重试
+        Ok((*oft <= data.len()).then(|| {
+            let rsp = s.take(*oft);
+            Command::from(!cluster::is_redirect(&rsp), rsp)
+        }))
+    }
 
-        Ok((*oft <= data.len()).then(|| Command::from_ok(s.take(*oft))))
+    // bulk超过上限，拒绝整个指令后连接仍然可用。事务中的指令被拒绝时，事务被丢弃，exec时响应EXECABORT；
+    // 丢弃剩余bulk的过程中再次超限时，指令已经响应过，只跳过数据
+    #[inline]
+    fn reject_big<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        process: &mut P,
+    ) {
+        let rsp = match (packet.discarding(), packet.in_txn()) {
+            (true, _) => None,
+            (false, true) => Some(local::LocalRsp::TxnTooLarge),
+            (false, false) => Some(local::LocalRsp::TooLarge),
+        };
+        let cfg = command::get_cfg(packet.op_code());
+        let (skip, len) = packet.reject_big();
+        if let (Some(rsp), Ok(cfg)) = (rsp, cfg) {
+            process.reject_big(local::build(cfg, rsp), len);
+        }
+        process.skip(skip);
     }
 }
 
@@ -100,10 +165,53 @@ impl Protocol for Redis {
     fn config(&self) -> crate::Config {
         crate::Config {
             pipeline: true,
+            need_auth: true,
             ..Default::default()
         }
     }
     #[inline]
+    fn conn_limited(&self) -> &'static [u8] {
+        b"-ERR max number of clients reached\r\n"
+    }
+    #[inline]
+    fn rate_limited(&self, _req: &HashedCommand) -> Vec<u8> {
+        b"-ERR rate limited\r\n".to_vec()
+    }
+    #[inline]
+    fn big_rejected(&self, _req: &HashedCommand) -> Vec<u8> {
+        local::TOO_LARGE.as_bytes().to_vec()
+    }
+    #[inline]
+    fn cross_shard(&self) -> &'static [u8] {
+        b"-CROSSSLOT Keys in request don't hash to the same shard\r\n"
+    }
+    #[inline]
+    fn auth_failed(&self) -> &'static [u8] {
+        b"-ERR backend auth failed\r\n"
+    }
+    #[inline]
+    fn key_len(&self, req: &HashedCommand) -> usize {
+        let cfg = match command::get_cfg(req.op_code()) {
+            Ok(cfg) if cfg.has_key => cfg,
+            _ => return 0,
+        };
+        Packet::from(***req)
+            .key_len(cfg.first_key_index())
+            .unwrap_or_default()
+    }
+    // 未配置密码及db时，直接返回Success
+    #[inline]
+    fn handshake(&self, stream: &mut impl Stream, option: &mut ResOption) -> Result<HandShake> {
+        match self.handshake_inner(stream, option) {
+            Err(Error::ProtocolIncomplete) => Ok(HandShake::Continue),
+            Err(e) => {
+                log::warn!("+++ found err when redis auth:{:?}", e);
+                Err(e)
+            }
+            h => h,
+        }
+    }
+    #[inline]
     fn parse_request<S: Stream, H: Hash, P: RequestProcessor>(
         &self,
         stream: &mut S,
@@ -111,13 +219,18 @@ impl Protocol for Redis {
         process: &mut P,
     ) -> Result<()> {
         let mut packet = RequestPacket::new(stream);
-        match self.parse_request_inner(&mut packet, alg, process) {
+        packet.max_bulk(process.max_value_len());
+        match self.parse_request_inner(&mut packet, alg, &mut resp3::Proto(process)) {
             Ok(_) => Ok(()),
             Err(Error::ProtocolIncomplete) => {
                 // 如果解析数据不够，提前reserve stream的空间
                 packet.reserve_stream_buff();
                 Ok(())
             }
+            Err(Error::BigRequest) => {
+                self.reject_big(&mut packet, &mut resp3::Proto(process));
+                Ok(())
+            }
             e => {
                 log::warn!("redis parsed err: {:?}, req: {:?} ", e, packet.inner_data());
                 e
@@ -143,6 +256,39 @@ impl Protocol for Redis {
         }
     }
 
+    // 事务请求、ASK重定向后的请求对应多个响应，需要根据请求解析
+    #[inline]
+    fn parse_response_of<S: Stream>(
+        &self,
+        req: &HashedCommand,
+        data: &mut S,
+    ) -> Result<Option<Command>> {
+        if !req.txn() && !req.asking() {
+            return match self.parse_response(data) {
+                // 分片迁移期间读请求未命中的响应标记为!ok，由topo到迁移前的分片重试
+                Ok(Some(mut rsp)) if req.miss_notok() && is_miss(req, &rsp) => {
+                    rsp.set_ok(false);
+                    Ok(Some(rsp))
+                }
+                r => r,
+            };
+        }
+        let mut oft = 0;
+        let rsp = match req.asking() {
+            true => self.parse_asking_response(data, &mut oft),
+            false => self.parse_txn_response(req, data, &mut oft),
+        };
+        match rsp {
+            Err(Error::ProtocolIncomplete) => {
+                if oft > data.len() {
+                    data.reserve(oft - data.len());
+                }
+                Ok(None)
+            }
+            r => r,
+        }
+    }
+
     // TODO：当前把padding、nil整合成一个，后续考虑如何把spec-rsp也整合进来
     // 发送响应给client：
     //  1 非multi，有rsponse直接发送，否则构建padding or spec-rsp后发送；
@@ -163,15 +309,31 @@ impl Protocol for Redis {
         I: MetricItem,
     {
         let request = ctx.request();
+        // 本地构建的响应
+        if request.local_rsp() == local::LocalRsp::Hello as u8 {
+            return local::write_hello(request.resp3(), w);
+        }
+        if let Some(rsp) = local::rsp(request) {
+            return w.write(rsp);
+        }
+        // 订阅类指令的响应由后端推送
+        if request.dedicated().push() {
+            return Ok(());
+        }
         let cfg = command::get_cfg(request.op_code())?;
+        if let Some(shards) = request.cross_shards() {
+            let shard = request.hash() as usize;
+            return self.write_cross_shard(ctx, cfg, shard, shards, response, w);
+        }
+        let resp3 = request.resp3();
 
         if !cfg.multi {
             // 非multi请求,有响应直接返回client，否则构建
             if let Some(rsp) = response {
-                w.write_slice(rsp, 0)?;
+                resp3::write_rsp(resp3, cfg, rsp, w)?;
             } else {
                 // 无响应，则根据cmd name构建对应响应
-                w.write(cfg.get_padding_rsp())?;
+                w.write(resp3::padding(resp3, cfg))?;
             }
 
             // quit指令发送完毕后，返回异常断连接
@@ -192,7 +354,7 @@ impl Protocol for Redis {
                 // 如果rsp是ok，或者不需要bulk num，直接发送；否则构建rsp or padding rsp
                 if let Some(rsp) = response {
                     if rsp.ok() || !cfg.need_bulk_num {
-                        w.write_slice(rsp, 0)?;
+                        resp3::write_rsp(resp3, cfg, rsp, w)?;
                         return Ok(());
                     }
                 }
@@ -209,7 +371,8 @@ impl Protocol for Redis {
                         w.write(shard.as_bytes())?;
                         w.write(b"\r\n")?;
                     }
-                    _ => w.write(cfg.get_padding_rsp())?,
+                    _ if cfg.need_bulk_num => w.write(resp3::nil_convert(resp3, cfg))?,
+                    _ => w.write(resp3::padding(resp3, cfg))?,
                 };
 
                 // rsp不为ok，对need_bulk_num为true的cmd进行nil convert 统计
@@ -230,7 +393,28 @@ impl Protocol for Redis {
             log::error!("+++ check failed for req:{:?}, resp:{:?}", _req, _resp);
         }
     }
+
+    #[inline]
+    fn write_push<W: Writer>(
+        &self,
+        subs: &mut Subscriptions,
+        shard: usize,
+        pattern: bool,
+        data: &Command,
+        w: &mut W,
+    ) -> Result<()> {
+        self.write_push_inner(subs, shard, pattern, data, w)
+    }
 }
 
 // tests only
 pub use packet::RequestContext;
+
+// 未命中：空响应$-1、*-1及RESP3的null，或指令定义的key不存在时的响应，如exists的:0、hgetall的*0
+#[inline]
+fn is_miss(req: &HashedCommand, rsp: &ds::RingSlice) -> bool {
+    let miss = command::get_cfg(req.op_code()).map_or(&b""[..], |cfg| cfg.miss);
+    [&b"$-1\r\n"[..], b"*-1\r\n", b"_\r\n", miss]
+        .iter()
+        .any(|m| !m.is_empty() && rsp.len() == m.len() && rsp.start_with(0, m))
+}
@@ -2,11 +2,13 @@ use super::{
     command::{CommandHasher, CommandProperties, CommandType},
     error::RedisError,
 };
-use crate::{error::Error, redis::command, Flag, Result, StreamContext};
+use crate::{error::Error, redis::command, Flag, RequestProcessor, Result, StreamContext};
 use ds::RingSlice;
 use sharding::hash::Hash;
 
 const CRLF_LEN: usize = b"\r\n".len();
+// 未配置big value上限时，事务中缓存的数据上限
+const MAX_TXN_LEN: usize = 64 << 20;
 // 这个context是用于中multi请求中，同一个multi请求中跨request协调
 // 必须是u64长度的。
 #[repr(C)]
@@ -23,6 +25,25 @@ pub struct RequestContext {
     pub reserved_hash: i64,
 }
 
+impl RequestContext {
+    #[inline]
+    fn in_txn(&self) -> bool {
+        self.layer == LayerType::Txn as u8 || self.layer == LayerType::TxnAborted as u8
+    }
+    // 事务中的指令总是完整解析后才处理，指令之间bulk、op_code均为0，
+    // 借用这4个字节记录已经响应过的事务数据在stream中的长度
+    #[inline]
+    fn txn_oft(&self) -> usize {
+        self.bulk as usize | (self.op_code as usize) << 16
+    }
+    #[inline]
+    fn set_txn_oft(&mut self, oft: usize) {
+        assert!(oft <= u32::MAX as usize, "txn too large:{}", oft);
+        self.bulk = oft as u16;
+        self.op_code = (oft >> 16) as u16;
+    }
+}
+
 impl From<&mut StreamContext> for RequestContext {
     fn from(value: &mut StreamContext) -> Self {
         unsafe { std::mem::transmute(*value) }
@@ -36,9 +57,16 @@ impl From<RequestContext> for StreamContext {
 }
 
 // 请求的layer层次，目前只有masterOnly，后续支持业务访问某层时，在此扩展属性
+// 事务中的指令都发送到master，所以事务状态也记录在layer中；
+// 因bulk超过上限被拒绝的指令，剩余的bulk需要丢弃，丢弃状态也记录在layer中
 #[repr(u8)]
 pub enum LayerType {
     MasterOnly = 1,
+    Txn = 2,
+    TxnAborted = 3,
+    Discard = 4,
+    // 事务中的指令被拒绝，丢弃完成后事务为TxnAborted
+    TxnDiscard = 5,
 }
 
 // impl RequestContext {
@@ -70,19 +98,53 @@ pub(crate) struct RequestPacket<'a, S> {
     ctx: RequestContext,
     oft_last: usize,
     oft: usize,
+    // bulk长度的上限，为0时不限制
+    max_bulk: usize,
+    // 超过上限的bulk的长度
+    big: usize,
 }
 
 impl<'a, S: crate::Stream> RequestPacket<'a, S> {
     #[inline]
     pub(crate) fn new(stream: &'a mut S) -> Self {
-        let ctx = stream.context().into();
+        let mut ctx: RequestContext = stream.context().into();
+        // 事务中，已经处理过的指令保留在stream中，从上次处理完的位置开始解析
+        let mut oft = 0;
+        if ctx.in_txn() {
+            oft = ctx.txn_oft();
+            ctx.bulk = 0;
+            ctx.op_code = 0;
+        }
         let data = stream.slice();
         Self {
             oft_last: 0,
-            oft: 0,
+            oft,
             data: Packet { inner: data },
             ctx,
             stream,
+            max_bulk: 0,
+            big: 0,
+        }
+    }
+    #[inline]
+    pub(crate) fn max_bulk(&mut self, max: usize) {
+        self.max_bulk = max;
+    }
+    // 读取bulk的长度并跳过，长度超过上限时直接拒绝，不等待bulk的数据
+    #[inline]
+    fn bulk_and_skip(&mut self) -> Result<usize> {
+        let len = self.data.num(&mut self.oft)?;
+        if self.max_bulk > 0 && len > self.max_bulk {
+            self.big = len;
+            return Err(crate::Error::BigRequest);
+        }
+        if len > 0 {
+            // skip num个字节 + "\r\n" 2个字节
+            self.oft += len + CRLF_LEN;
+        }
+        match self.oft <= self.data.len() {
+            true => Ok(len),
+            false => Err(crate::Error::ProtocolIncomplete),
         }
     }
 
@@ -124,14 +186,18 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
             if let Some(first_r) = self.data.find(self.oft, b'\r') {
                 debug_assert_eq!(self.data[self.oft], b'$', "{:?}", self);
                 // 路过CRLF_LEN个字节，通过命令获取op_code
-                let (op_code, idx) = CommandHasher::hash_slice(&*self.data, first_r + CRLF_LEN)?;
+                let start = first_r + CRLF_LEN;
+                let (hash, idx) = CommandHasher::hash_slice(&*self.data, start)?;
                 assert!(idx + CRLF_LEN <= self.data.len());
-                self.ctx.op_code = op_code;
+                let cfg = command::get_cfg_by_name(hash, &self.data.sub_slice(start, idx - start))?;
+                self.ctx.op_code = cfg.op_code;
                 // 第一次解析cmd需要对协议进行合法性校验
-                let cfg = command::get_cfg(op_code)?;
                 cfg.validate(self.bulk() as usize)?;
 
-                if cfg.need_reserved_hash && !(self.sendto_all() || self.ctx.is_reserved_hash) {
+                // 事务中由事务的分片确定hash，不满足时在事务中响应异常
+                if cfg.need_reserved_hash
+                    && !(self.sendto_all() || self.ctx.is_reserved_hash || self.in_txn())
+                {
                     return Err(RedisError::ReqInvalid.into());
                 }
                 // check 命令长度
@@ -152,7 +218,7 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
     pub(crate) fn parse_key(&mut self) -> Result<RingSlice> {
         debug_assert_ne!(self.ctx.op_code, 0, "packet:{:?}", self);
         debug_assert_ne!(self.ctx.bulk, 0, "packet:{:?}", self);
-        let key_len = self.data.num_and_skip(&mut self.oft)?;
+        let key_len = self.bulk_and_skip()?;
         self.ctx.bulk -= 1;
         let start = self.oft - CRLF_LEN - key_len;
         Ok(self.data.sub_slice(start, key_len))
@@ -160,7 +226,7 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
     #[inline]
     pub(crate) fn ignore_one_bulk(&mut self) -> Result<()> {
         assert_ne!(self.ctx.bulk, 0, "packet:{:?}", self);
-        self.data.num_and_skip(&mut self.oft)?;
+        self.bulk_and_skip()?;
         self.ctx.bulk -= 1;
         Ok(())
     }
@@ -171,6 +237,16 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
         }
         Ok(())
     }
+    // 拒绝big value时，multi请求拆分前先检查所有bulk的长度，避免部分key已经发送后才拒绝
+    #[inline]
+    pub(crate) fn check_bulks(&mut self) -> Result<()> {
+        if self.max_bulk > 0 {
+            let (oft, bulk) = (self.oft, self.ctx.bulk);
+            self.ignore_all_bulks()?;
+            (self.oft, self.ctx.bulk) = (oft, bulk);
+        }
+        Ok(())
+    }
     // 忽略掉之前的数据，通常是multi请求的前面部分。
     #[inline]
     pub(crate) fn multi_ready(&mut self) {
@@ -264,7 +340,11 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
     pub(super) fn hash<H: Hash>(&mut self, cfg: &CommandProperties, alg: &H) -> Result<i64> {
         let mut key: RingSlice = Default::default();
         if cfg.has_key {
-            key = self.parse_key()?;
+            self.skip_before_key(cfg)?;
+            // xinfo help等子命令没有key，按空key计算hash
+            if self.has_bulk() {
+                key = self.parse_key()?;
+            }
         }
         let hash = if self.ctx.is_reserved_hash {
             self.ctx.reserved_hash
@@ -275,6 +355,24 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
         Ok(hash)
     }
 
+    // key之前的子命令，如xgroup create key、xinfo stream key
+    #[inline]
+    fn skip_before_key(&mut self, cfg: &CommandProperties) -> Result<()> {
+        for _ in 1..cfg.first_key_index() {
+            self.ignore_one_bulk()?;
+        }
+        Ok(())
+    }
+    // 解析一个key并计算hash，通过hashkey指定了hash时，使用指定的hash
+    #[inline]
+    pub(super) fn key_hash<H: Hash>(&mut self, alg: &H) -> Result<i64> {
+        let key = self.parse_key()?;
+        Ok(match self.ctx.is_reserved_hash {
+            true => self.ctx.reserved_hash,
+            false => calculate_hash(alg, &key),
+        })
+    }
+
     //处理对下条指令有影响的命令，其造成的影响单独存放，不影响平常流程
     pub(super) fn proc_effect_on_next_req_cmd<H: Hash>(
         &mut self,
@@ -340,6 +438,184 @@ impl<'a, S: crate::Stream> RequestPacket<'a, S> {
         // return Err(crate::Error::ProtocolIncomplete);
     }
 
+    #[inline]
+    pub(super) fn in_txn(&self) -> bool {
+        self.ctx.in_txn()
+    }
+    #[inline]
+    pub(super) fn discarding(&self) -> bool {
+        self.ctx.layer == LayerType::Discard as u8 || self.ctx.layer == LayerType::TxnDiscard as u8
+    }
+    // bulk超过上限时拒绝整个指令：已读到的数据直接丢弃，事务中的指令被拒绝时，缓存的事务也一起丢弃；
+    // 超限的bulk由pipeline读到后跳过，指令剩余的bulk在后续解析时丢弃。
+    // 返回需要跳过的长度，以及超限的bulk长度
+    #[inline]
+    pub(super) fn reject_big(&mut self) -> (usize, usize) {
+        debug_assert!(self.big > 0 && self.bulk() > 0, "packet:{}", self);
+        let layer = match self.in_txn() || self.ctx.layer == LayerType::TxnDiscard as u8 {
+            true => LayerType::TxnDiscard,
+            false => LayerType::Discard,
+        };
+        self.stream.ignore(self.oft - self.oft_last);
+        self.oft_last = self.oft;
+        self.ctx.bulk -= 1;
+        self.ctx.layer = layer as u8;
+        self.discard_save();
+        (self.big.saturating_add(CRLF_LEN), self.big)
+    }
+    // 丢弃被拒绝指令剩余的bulk
+    #[inline]
+    pub(super) fn discard(&mut self) -> Result<()> {
+        while self.has_bulk() {
+            self.ignore_one_bulk()?;
+            self.stream.ignore(self.oft - self.oft_last);
+            self.oft_last = self.oft;
+            self.discard_save();
+        }
+        Ok(())
+    }
+    // 保存丢弃的状态，丢弃完成后，事务中的指令进入TxnAborted，否则清理状态
+    #[inline]
+    fn discard_save(&mut self) {
+        if self.has_bulk() {
+            *self.stream.context() = self.ctx.into();
+            return;
+        }
+        match self.ctx.layer == LayerType::TxnDiscard as u8 {
+            true => {
+                self.ctx.layer = LayerType::TxnAborted as u8;
+                self.ctx.op_code = 0;
+                self.ctx.first = false;
+                *self.stream.context() = self.ctx.into();
+            }
+            false => self.reset_context(),
+        }
+    }
+    #[inline]
+    pub(super) fn txn_aborted(&self) -> bool {
+        self.ctx.layer == LayerType::TxnAborted as u8
+    }
+    #[inline]
+    pub(super) fn txn_abort(&mut self) {
+        self.ctx.layer = LayerType::TxnAborted as u8;
+    }
+    // 事务是否已经确定了分片，事务的第一个key（或multi之前的hashkey）确定分片
+    #[inline]
+    pub(super) fn txn_pinned(&self) -> bool {
+        self.ctx.is_reserved_hash
+    }
+    #[inline]
+    pub(super) fn txn_hash(&self) -> i64 {
+        debug_assert!(self.txn_pinned(), "packet:{}", self);
+        self.ctx.reserved_hash
+    }
+    // multi不take，作为事务的开始保留在stream中，直到exec/discard时整体take；
+    // 保留multi之前hashkey指定的hash，master、sendtoall对事务无意义，直接清理
+    #[inline]
+    pub(super) fn txn_begin(&mut self) {
+        self.ctx = RequestContext {
+            layer: LayerType::Txn as u8,
+            is_reserved_hash: self.ctx.is_reserved_hash,
+            reserved_hash: self.ctx.reserved_hash,
+            ..Default::default()
+        };
+        self.txn_save();
+    }
+    // 一个事务指令处理完毕，保存事务状态，供下一个指令使用
+    #[inline]
+    pub(super) fn txn_save(&mut self) {
+        debug_assert!(self.in_txn() && self.complete(), "packet:{}", self);
+        self.ctx.op_code = 0;
+        self.ctx.first = false;
+        // 事务已被丢弃，exec时只响应EXECABORT，缓存的指令不再需要，直接释放
+        if self.txn_aborted() {
+            let _ = self.take();
+        }
+        let mut ctx = self.ctx;
+        ctx.set_txn_oft(self.oft - self.oft_last);
+        *self.stream.context() = ctx.into();
+    }
+    // 事务缓存的数据是否超过上限：配置了big value上限时复用该上限，否则为MAX_TXN_LEN
+    #[inline]
+    pub(super) fn txn_too_large(&self) -> bool {
+        let max = match self.max_bulk {
+            0 => MAX_TXN_LEN,
+            max => max,
+        };
+        self.oft - self.oft_last > max
+    }
+    // exec/discard：take走整个事务，从multi到当前指令
+    #[inline]
+    pub(super) fn txn_take(&mut self) -> ds::MemGuard {
+        let data = self.take();
+        self.reset_context();
+        data
+    }
+    // 校验事务指令的key，所有key都需要与事务落在同一个分片；
+    // need_reserved_hash的指令不解析全部key，跟随事务的分片。
+    pub(super) fn txn_check_keys<H: Hash, P: RequestProcessor>(
+        &mut self,
+        cfg: &CommandProperties,
+        alg: &H,
+        process: &P,
+    ) -> Result<bool> {
+        let mut same = true;
+        if cfg.has_key && !cfg.need_reserved_hash {
+            self.skip_before_key(cfg)?;
+            while self.has_bulk() {
+                let hash = calculate_hash(alg, &self.parse_key()?);
+                if !self.txn_pinned() {
+                    self.set_reserved_hash(hash);
+                } else if process.shard_idx(hash) != process.shard_idx(self.txn_hash()) {
+                    same = false;
+                }
+                if !cfg.multi {
+                    break;
+                }
+                if cfg.has_val {
+                    self.ignore_one_bulk()?;
+                }
+            }
+        }
+        self.ignore_all_bulks()?;
+        Ok(same)
+    }
+    // 阻塞指令的key需要与第一个key落在同一个分片，最后tail个bulk不是key；
+    // 通过hashkey指定了hash时，不再校验
+    pub(super) fn check_keys_shard<H: Hash, P: RequestProcessor>(
+        &mut self,
+        hash: i64,
+        tail: u16,
+        alg: &H,
+        process: &P,
+    ) -> Result<bool> {
+        let mut same = true;
+        while !self.ctx.is_reserved_hash && self.bulk() > tail {
+            let key_hash = calculate_hash(alg, &self.parse_key()?);
+            same &= process.shard_idx(key_hash) == process.shard_idx(hash);
+        }
+        Ok(same)
+    }
+    // 事务中的指令数，不含multi、exec
+    pub(super) fn txn_cmds(&self) -> Result<usize> {
+        let mut oft = self.oft_last;
+        let mut cmds = 0;
+        while oft < self.oft {
+            for _ in 0..self.data.num_of_bulks(&mut oft)? {
+                self.data.num_and_skip(&mut oft)?;
+            }
+            cmds += 1;
+        }
+        debug_assert!(cmds >= 2, "packet:{}", self);
+        Ok(cmds - 2)
+    }
+
+    // 当前解析位置在指令中的偏移
+    #[inline]
+    pub(super) fn cmd_oft(&self) -> usize {
+        self.oft - self.oft_last
+    }
+
     #[inline]
     pub(crate) fn first(&self) -> bool {
         self.ctx.first
@@ -504,6 +780,16 @@ impl Packet {
         }
         Err(crate::Error::ProtocolIncomplete)
     }
+    // 完整请求中key的长度，key之前有first_key_index个bulk（含cmd）
+    #[inline]
+    pub(super) fn key_len(&self, first_key_index: u8) -> crate::Result<usize> {
+        let mut oft = 0;
+        self.num(&mut oft)?;
+        for _ in 0..first_key_index.max(1) {
+            self.num_and_skip(&mut oft)?;
+        }
+        self.num(&mut oft)
+    }
     #[inline]
     fn num_and_skip(&self, oft: &mut usize) -> crate::Result<usize> {
         let num = self.num(oft)?;
@@ -535,8 +821,25 @@ impl Packet {
             Err(crate::Error::ProtocolIncomplete)
         }
     }
-    // 需要支持4种协议格式：（除了-代表的错误类型）
-    //    1）* 代表array； 2）$代表bulk 字符串；3）+ 代表简单字符串；4）:代表整型；
+    // 跳过一个完整的响应
+    #[inline]
+    pub fn skip_rsp(&self, oft: &mut usize) -> Result<()> {
+        self.check_onetoken(*oft)?;
+        match self.at(*oft) {
+            b'-' | b':' | b'+' => self.line(oft)?,
+            b'$' => {
+                *oft += self.num_of_string(oft)? + 2;
+            }
+            b'*' => self.skip_all_bulk(oft)?,
+            _ => {
+                log::error!("+++ found malformed redis rsp:{:?}", self);
+                return Err(RedisError::RespInvalid.into());
+            } // _ => panic!("not supported:{:?}", data),
+        }
+        Ok(())
+    }
+    // 需要支持5种协议格式：
+    //    1）* 代表array； 2）$代表bulk 字符串；3）+ 代表简单字符串；4）:代表整型；5）- 代表错误；
     #[inline]
     pub fn skip_all_bulk(&self, oft: &mut usize) -> Result<()> {
         let mut bulk_count = self.num_of_bulks(oft)?;
@@ -560,7 +863,8 @@ impl Packet {
                     // 跳过num个字节 + "\r\n" 2个字节
                     *oft += self.num_of_string(oft)? + CRLF_LEN;
                 }
-                b'+' | b':' => self.line(oft)?,
+                // exec的响应中会包含事务中指令的异常
+                b'+' | b':' | b'-' => self.line(oft)?,
                 _ => panic!("unsupport rsp:{:?}, pos: {}/{}", self, oft, bulk_count),
             }
             bulk_count -= 1;
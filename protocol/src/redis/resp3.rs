@@ -0,0 +1,204 @@
+use super::{
+    client_auth,
+    command::{CommandProperties, Reply3},
+    flag::RedisFlager,
+    local::{self, LocalRsp},
+    packet::{Packet, RequestPacket},
+    Redis,
+};
+use crate::{Command, HashedCommand, RequestProcessor, Result, Stream, Writer};
+
+pub(super) const NIL: &[u8] = b"$-1\r\n";
+const NIL_ARRAY: &[u8] = b"*-1\r\n";
+pub(super) const NULL: &[u8] = b"_\r\n";
+// multi-key请求中无响应的key，通过attribute携带原因
+const NIL_CONVERT: &[u8] = b"|1\r\n+error\r\n+redis no available\r\n_\r\n";
+
+// client通过hello 3协商RESP3后，后续请求的响应按RESP3返回：
+//   1. 后端连接仍使用RESP2，响应中的nil（$-1、*-1），包括array中嵌套的nil，都转为null（_）；
+//   2. 顶层array按指令表中的reply3转为map或set，如hgetall、smembers；
+//   3. 无响应时的默认nil转为null；multi-key请求中无响应的key，在null前加attribute说明原因；
+//   4. hello的响应为map；
+//   5. 订阅连接推送的数据转为push类型（>）。
+// 暂不转换double、嵌套的map，如zscore仍返回bulk string，与RESP2相同。
+// 协议版本在解析时记录到请求中，pipeline中hello之前的请求仍按原协议响应。
+impl Redis {
+    // HELLO [protover [AUTH username password] [SETNAME clientname]]，只处理protover及AUTH，其他选项忽略
+    #[inline]
+    pub(super) fn parse_hello<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        process: &mut P,
+    ) -> Result<()> {
+        let mut rsp = LocalRsp::Hello;
+        let mut resp3 = process.resp3();
+        let mut password = None;
+        if packet.has_bulk() {
+            let ver = packet.parse_key()?;
+            match (ver.len(), ver.at(0)) {
+                (1, b'2') => resp3 = false,
+                (1, b'3') => resp3 = true,
+                _ => rsp = LocalRsp::NoProto,
+            };
+            while packet.has_bulk() {
+                let opt = packet.parse_key()?;
+                if client_auth::is_auth_option(&opt) && packet.bulk() >= 2 {
+                    packet.ignore_one_bulk()?;
+                    password = Some(packet.parse_key()?);
+                }
+            }
+        }
+        packet.ignore_all_bulks()?;
+        let _ = packet.take();
+        if let Some(password) = password {
+            if rsp != LocalRsp::NoProto && !process.auth(&password) {
+                rsp = LocalRsp::WrongPass;
+            }
+        }
+        // hello的响应在写入时按请求记录的协议版本构建
+        if rsp == LocalRsp::Hello {
+            process.set_resp3(resp3);
+        }
+        process.process(local::build(cfg, rsp), true);
+        Ok(())
+    }
+}
+
+// 解析请求时，按client当前协商的协议版本标记请求
+pub(super) struct Proto<'a, P>(pub(super) &'a mut P);
+impl<P: RequestProcessor> RequestProcessor for Proto<'_, P> {
+    #[inline]
+    fn process(&mut self, mut req: HashedCommand, last: bool) {
+        if self.0.resp3() {
+            req.set_resp3();
+        }
+        self.0.process(req, last);
+    }
+    #[inline]
+    fn shard_idx(&self, hash: i64) -> usize {
+        self.0.shard_idx(hash)
+    }
+    #[inline]
+    fn subscribing(&self) -> bool {
+        self.0.subscribing()
+    }
+    #[inline]
+    fn shards(&self) -> usize {
+        self.0.shards()
+    }
+    #[inline]
+    fn resp3(&self) -> bool {
+        self.0.resp3()
+    }
+    #[inline]
+    fn set_resp3(&mut self, resp3: bool) {
+        self.0.set_resp3(resp3);
+    }
+    #[inline]
+    fn authed(&self) -> bool {
+        self.0.authed()
+    }
+    #[inline]
+    fn auth(&mut self, password: &ds::RingSlice) -> bool {
+        self.0.auth(password)
+    }
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        self.0.max_value_len()
+    }
+    #[inline]
+    fn reject_big(&mut self, mut req: HashedCommand, len: usize) {
+        if self.0.resp3() {
+            req.set_resp3();
+        }
+        self.0.reject_big(req, len);
+    }
+    #[inline]
+    fn skip(&mut self, n: usize) {
+        self.0.skip(n);
+    }
+}
+
+#[inline]
+pub(super) fn write_rsp<W: Writer>(
+    resp3: bool,
+    cfg: &CommandProperties,
+    rsp: &Command,
+    w: &mut W,
+) -> Result<()> {
+    if !resp3 {
+        return w.write_slice(rsp, 0);
+    }
+    let nil = rsp.len() == NIL.len() && (rsp.start_with(0, NIL) || rsp.start_with(0, NIL_ARRAY));
+    match (nil, rsp.at(0)) {
+        (true, _) => w.write(NULL),
+        (false, b'*') => write_aggregate(&Packet::from(***rsp), cfg.reply3, w),
+        _ => w.write_slice(rsp, 0),
+    }
+}
+
+// 遍历array，顶层按reply3转换类型，所有的nil转为null，其他部分原样写入
+#[inline]
+fn write_aggregate<W: Writer>(data: &Packet, reply3: Reply3, w: &mut W) -> Result<()> {
+    let mut oft = 0;
+    let mut count = data.num_of_bulks(&mut oft)?;
+    // 已写入client的位置
+    let mut start = match reply3 {
+        Reply3::Array => 0,
+        Reply3::Map | Reply3::Set => {
+            let (t, n) = match reply3 {
+                Reply3::Map => (b'%', count / 2),
+                _ => (b'~', count),
+            };
+            w.write_u8(t)?;
+            w.write_str_num(n)?;
+            w.write(b"\r\n")?;
+            oft
+        }
+    };
+    // 使用stack实现递归，存放上层未遍历的元素数
+    let mut levels = Vec::new();
+    while count > 0 || !levels.is_empty() {
+        if count == 0 {
+            count = levels.pop().expect("levels");
+            continue;
+        }
+        count -= 1;
+        data.check_onetoken(oft)?;
+        match (data.at(oft), data.at(oft + 1)) {
+            (b'$' | b'*', b'-') => {
+                w.write_ringslice(&data.sub_slice(start, oft - start), 0)?;
+                w.write(NULL)?;
+                data.line(&mut oft)?;
+                start = oft;
+            }
+            (b'*', _) => {
+                let n = data.num_of_bulks(&mut oft)?;
+                levels.push(count);
+                count = n;
+            }
+            (b'$', _) => oft += data.num_of_string(&mut oft)? + 2,
+            _ => data.line(&mut oft)?,
+        }
+    }
+    w.write_ringslice(&data.sub_slice(start, data.len() - start), 0)
+}
+
+// 无响应时的默认响应
+#[inline]
+pub(super) fn padding(resp3: bool, cfg: &CommandProperties) -> &'static [u8] {
+    match resp3 && cfg.get_padding_rsp() == NIL {
+        true => NULL,
+        false => cfg.get_padding_rsp(),
+    }
+}
+
+// multi-key请求中无响应的key
+#[inline]
+pub(super) fn nil_convert(resp3: bool, cfg: &CommandProperties) -> &'static [u8] {
+    match resp3 && cfg.get_padding_rsp() == NIL {
+        true => NIL_CONVERT,
+        false => cfg.get_padding_rsp(),
+    }
+}
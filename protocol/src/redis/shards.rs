@@ -0,0 +1,275 @@
+use super::{
+    command::{CommandProperties, CommandType},
+    flag::{RedisFlager, MAX_SHARDS},
+    packet::{Packet, RequestPacket},
+    Redis,
+};
+use crate::{
+    Command, Commander, HashedCommand, Metric, MetricItem, RequestProcessor, Result, Stream, Writer,
+};
+use ds::{MemGuard, RingSlice};
+use std::collections::BTreeMap;
+
+// 跨分片指令，请求由协议指定发送的分片：
+//   1. scan：client的cursor = 分片的cursor * 分片数 + 分片索引，分片遍历完毕后从下一个分片的0开始，
+//      最后一个分片遍历完毕时返回0。遍历过程中分片数发生变化时，结果不保证完整；
+//   2. keys、dbsize、info keyspace：拆分成每个分片一个请求，最后一个分片响应时合并所有分片的响应，
+//      任一分片异常时返回该异常。info的其他section无法合并，按普通请求发送到一个分片。
+// 每个分片的请求复用sendto_all，由topo按请求的hash（即分片索引）发送。
+impl Redis {
+    #[inline]
+    pub(super) fn parse_cross_shard<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        process: &mut P,
+    ) -> Result<()> {
+        let shards = process.shards().clamp(1, MAX_SHARDS);
+        if cfg.cmd_type == CommandType::Scan {
+            let head = packet.cmd_oft();
+            let cursor = packet.parse_key()?;
+            let tail = packet.cmd_oft();
+            packet.ignore_all_bulks()?;
+            let cmd = packet.take();
+            let mut flag = packet.flag(cfg);
+            // cursor不合法时直接发送到第0个分片，由后端响应异常
+            let (shard, cmd) = match parse_u64(&cursor) {
+                Some(cursor) => {
+                    let shard_cursor = (cursor / shards as u64).to_string();
+                    let mut data = Vec::with_capacity(cmd.len() + 8);
+                    cmd.copy_to_vec_r(&mut data, 0..head);
+                    data.extend_from_slice(format!("${}\r\n", shard_cursor.len()).as_bytes());
+                    data.extend_from_slice(shard_cursor.as_bytes());
+                    data.extend_from_slice(b"\r\n");
+                    cmd.copy_to_vec_r(&mut data, tail..cmd.len());
+                    ((cursor % shards as u64) as usize, MemGuard::from_vec(data))
+                }
+                None => (0, cmd),
+            };
+            flag.set_cross_shard(shards);
+            process.process(HashedCommand::new(cmd, shard as i64, flag), true);
+            return Ok(());
+        }
+
+        let keyspace = cfg.cmd_type != CommandType::Info
+            || packet.bulk() == 1 && is_keyspace(&packet.parse_key()?);
+        packet.ignore_all_bulks()?;
+        let cmd = packet.take();
+        if !keyspace {
+            process.process(HashedCommand::new(cmd, 0, packet.flag(cfg)), true);
+            return Ok(());
+        }
+        // 最后一个分片使用原始请求，其他分片使用拷贝
+        for shard in 0..shards - 1 {
+            let mut flag = packet.flag(cfg);
+            flag.set_cross_shard(shards);
+            let mut data = Vec::with_capacity(cmd.len());
+            cmd.copy_to_vec(&mut data);
+            let req = HashedCommand::new(MemGuard::from_vec(data), shard as i64, flag);
+            process.process(req, false);
+        }
+        let mut flag = packet.flag(cfg);
+        flag.set_cross_shard(shards);
+        process.process(HashedCommand::new(cmd, (shards - 1) as i64, flag), true);
+        Ok(())
+    }
+
+    #[inline]
+    pub(super) fn write_cross_shard<C, W, M, I>(
+        &self,
+        ctx: &mut C,
+        cfg: &CommandProperties,
+        shard: usize,
+        shards: usize,
+        response: Option<&mut Command>,
+        w: &mut W,
+    ) -> Result<()>
+    where
+        W: Writer,
+        C: Commander<M, I>,
+        M: Metric<I>,
+        I: MetricItem,
+    {
+        if cfg.cmd_type == CommandType::Scan {
+            return match response {
+                Some(rsp) => write_scan(rsp, shard, shards, w),
+                None => w.write(cfg.get_padding_rsp()),
+            };
+        }
+        let merged = ctx.merged();
+        match response {
+            Some(rsp) => rsp.copy_to_vec(merged),
+            None => merged.extend_from_slice(cfg.get_padding_rsp()),
+        }
+        if shard + 1 < shards {
+            return Ok(());
+        }
+        let data = std::mem::take(merged);
+        merge(cfg, &data, w)
+    }
+}
+
+// 响应格式：*2\r\n$len\r\ncursor\r\n*n\r\n...，只修改cursor
+#[inline]
+fn write_scan<W: Writer>(rsp: &Command, shard: usize, shards: usize, w: &mut W) -> Result<()> {
+    let data: Packet = rsp.sub_slice(0, rsp.len()).into();
+    let mut oft = 0;
+    if data.at(0) != b'*' || data.num_of_bulks(&mut oft)? != 2 || data.at(oft) != b'$' {
+        return w.write_slice(rsp, 0);
+    }
+    let len = data.num_of_string(&mut oft)?;
+    let Some(cursor) = parse_u64(&data.sub_slice(oft, len)) else {
+        return w.write_slice(rsp, 0);
+    };
+    let cursor = match cursor {
+        0 if shard + 1 < shards => shard as u64 + 1,
+        0 => 0,
+        c => match c
+            .checked_mul(shards as u64)
+            .and_then(|c| c.checked_add(shard as u64))
+        {
+            Some(c) => c,
+            // 分片的cursor过大，无法编码分片索引
+            None => return w.write(b"-ERR invalid cursor\r\n"),
+        },
+    }
+    .to_string();
+    w.write(b"*2\r\n$")?;
+    w.write_str_num(cursor.len())?;
+    w.write(b"\r\n")?;
+    w.write(cursor.as_bytes())?;
+    w.write(b"\r\n")?;
+    w.write_slice(rsp, oft + len + 2)
+}
+
+// data是所有分片的响应，依次排列
+#[inline]
+fn merge<W: Writer>(cfg: &CommandProperties, data: &Vec<u8>, w: &mut W) -> Result<()> {
+    let packet: Packet = RingSlice::from_vec(data).into();
+    let mut rsps = Vec::new();
+    let mut oft = 0;
+    while oft < data.len() {
+        let start = oft;
+        packet.skip_rsp(&mut oft)?;
+        if data[start] == b'-' {
+            return w.write(&data[start..oft]);
+        }
+        rsps.push(start..oft);
+    }
+    match cfg.cmd_type {
+        // 每个分片的响应都是数组，合并成一个数组
+        CommandType::Keys => {
+            let mut total = 0;
+            let mut items = Vec::with_capacity(rsps.len());
+            for r in rsps {
+                let mut oft = r.start;
+                total += packet.num_of_bulks(&mut oft)?;
+                items.push(oft..r.end);
+            }
+            w.write(b"*")?;
+            w.write_str_num(total)?;
+            w.write(b"\r\n")?;
+            for r in items {
+                w.write(&data[r])?;
+            }
+            Ok(())
+        }
+        CommandType::DbSize => {
+            let total: usize = rsps
+                .into_iter()
+                .map(|r| packet.str_num(r.start + 1..r.end - 2))
+                .sum();
+            w.write(b":")?;
+            w.write_str_num(total)?;
+            w.write(b"\r\n")
+        }
+        _ => {
+            debug_assert_eq!(cfg.cmd_type, CommandType::Info);
+            let mut keyspace = Keyspace::default();
+            for r in rsps {
+                let mut oft = r.start;
+                let len = packet.num_of_string(&mut oft)?;
+                keyspace.add(&String::from_utf8_lossy(&data[oft..oft + len]));
+            }
+            let info = keyspace.to_string();
+            w.write(b"$")?;
+            w.write_str_num(info.len())?;
+            w.write(b"\r\n")?;
+            w.write(info.as_bytes())?;
+            w.write(b"\r\n")
+        }
+    }
+}
+
+// info keyspace的格式：db0:keys=1,expires=0,avg_ttl=0
+// keys、expires累加，avg_ttl按expires加权平均
+#[derive(Default)]
+struct Keyspace(BTreeMap<u16, (u64, u64, u64)>);
+impl Keyspace {
+    fn add(&mut self, info: &str) {
+        for line in info.lines() {
+            let Some((db, fields)) = line.split_once(':') else {
+                continue;
+            };
+            let Some(Ok(db)) = db.strip_prefix("db").map(|d| d.parse::<u16>()) else {
+                continue;
+            };
+            let (mut keys, mut expires, mut ttl) = (0, 0, 0);
+            for field in fields.split(',') {
+                let Some((name, v)) = field.split_once('=') else {
+                    continue;
+                };
+                let v = v.parse::<u64>().unwrap_or(0);
+                match name {
+                    "keys" => keys = v,
+                    "expires" => expires = v,
+                    "avg_ttl" => ttl = v,
+                    _ => {}
+                }
+            }
+            let db = self.0.entry(db).or_default();
+            db.0 += keys;
+            db.1 += expires;
+            db.2 += ttl.saturating_mul(expires);
+        }
+    }
+}
+impl std::fmt::Display for Keyspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "# Keyspace\r\n")?;
+        for (db, (keys, expires, ttl)) in &self.0 {
+            let avg_ttl = ttl.checked_div(*expires).unwrap_or(0);
+            write!(
+                f,
+                "db{}:keys={},expires={},avg_ttl={}\r\n",
+                db, keys, expires, avg_ttl
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// info的section不区分大小写
+#[inline]
+fn is_keyspace(section: &RingSlice) -> bool {
+    const KEYSPACE: &[u8] = b"keyspace";
+    section.len() == KEYSPACE.len()
+        && (0..KEYSPACE.len()).all(|i| section.at(i).to_ascii_lowercase() == KEYSPACE[i])
+}
+
+// 只接受不超过u64的十进制数
+#[inline]
+fn parse_u64(data: &RingSlice) -> Option<u64> {
+    if data.len() == 0 {
+        return None;
+    }
+    let mut n: u64 = 0;
+    for i in 0..data.len() {
+        let d = data.at(i);
+        if !d.is_ascii_digit() {
+            return None;
+        }
+        n = n.checked_mul(10)?.checked_add((d - b'0') as u64)?;
+    }
+    Some(n)
+}
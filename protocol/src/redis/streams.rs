@@ -0,0 +1,72 @@
+use super::{
+    command::CommandProperties,
+    local::{self, LocalRsp},
+    packet::RequestPacket,
+    Redis,
+};
+use crate::{Dedicated, HashedCommand, RequestProcessor, Result, Stream};
+use ds::RingSlice;
+use sharding::hash::Hash;
+
+// xread、xreadgroup的格式：
+//   XREAD [COUNT count] [BLOCK ms] STREAMS key [key ...] id [id ...]
+//   XREADGROUP GROUP group consumer [COUNT count] [BLOCK ms] [NOACK] STREAMS key [key ...] id [id ...]
+// STREAMS之后的参数一半是key，一半是id，key需要落在同一个分片，否则响应CROSSSLOT；
+// 带BLOCK选项时与blpop等阻塞指令一样，发送到client独占的连接。
+// 缺少STREAMS或其后的参数个数不是偶数时，本地响应syntax error；其他格式问题由后端响应异常。
+impl Redis {
+    #[inline]
+    pub(super) fn parse_stream_read<S: Stream, H: Hash, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        alg: &H,
+        process: &mut P,
+    ) -> Result<()> {
+        let mut flag = packet.flag(cfg);
+        while packet.has_bulk() {
+            let opt = packet.parse_key()?;
+            // 选项的参数可能与STREAMS同名，如消费组名，需要整体跳过
+            let args = if is_option(&opt, b"STREAMS") {
+                break;
+            } else if is_option(&opt, b"BLOCK") {
+                flag.set_dedicated(Dedicated::Block);
+                1
+            } else if is_option(&opt, b"COUNT") {
+                1
+            } else if is_option(&opt, b"GROUP") {
+                2
+            } else {
+                0
+            };
+            for _ in 0..args.min(packet.bulk()) {
+                packet.ignore_one_bulk()?;
+            }
+        }
+        let keys = packet.bulk() / 2;
+        let balanced = keys > 0 && packet.bulk() & 1 == 0;
+        let mut rsp = LocalRsp::SyntaxErr;
+        let mut hash = 0;
+        if balanced {
+            hash = packet.key_hash(alg)?;
+            rsp = match packet.check_keys_shard(hash, keys, alg, process)? {
+                true => LocalRsp::Ok,
+                false => LocalRsp::CrossShard,
+            };
+        }
+        packet.ignore_all_bulks()?;
+        let cmd = packet.take();
+        let req = match rsp {
+            LocalRsp::Ok => HashedCommand::new(cmd, hash, flag),
+            rsp => local::build(cfg, rsp),
+        };
+        process.process(req, true);
+        Ok(())
+    }
+}
+
+// 选项不区分大小写
+#[inline]
+fn is_option(data: &RingSlice, opt: &[u8]) -> bool {
+    data.len() == opt.len() && (0..opt.len()).all(|i| data.at(i).to_ascii_uppercase() == opt[i])
+}
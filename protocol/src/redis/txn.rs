@@ -0,0 +1,157 @@
+use super::{
+    command::{CommandProperties, CommandType},
+    flag::RedisFlager,
+    local::{self, LocalRsp},
+    packet::{Packet, RequestPacket},
+    Redis,
+};
+use crate::{Command, Dedicated, HashedCommand, RequestProcessor, Result, Stream};
+use sharding::hash::Hash;
+
+// 事务中指令数的上限，受限于flag中key_count的长度
+const MAX_TXN_CMDS: usize = u16::MAX as usize;
+
+// 后端连接是多个client共享的，事务无法逐条转发：
+//   1. multi之后的指令在本地校验并响应QUEUED，数据保留在client的stream中；
+//   2. 所有key需要落在同一个分片，否则响应CROSSSLOT，并在exec时响应EXECABORT；
+//   3. exec时将multi...exec整体作为一个请求发送到分片的master，只返回exec的响应；
+//   4. discard时直接丢弃整个事务；
+//   5. 缓存的数据超过上限，或exec时指令数超过MAX_TXN_CMDS，事务被丢弃，exec时响应EXECABORT；
+//      被丢弃的事务不再缓存后续指令。
+// watch发送到client独占的master连接，有watch时exec、discard也发送到该连接，由pipeline处理。
+impl Redis {
+    // 事务外的multi、exec、discard
+    #[inline]
+    pub(super) fn parse_txn_start<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        process: &mut P,
+    ) -> Result<()> {
+        packet.ignore_all_bulks()?;
+        let rsp = match cfg.cmd_type {
+            CommandType::Multi => {
+                packet.txn_begin();
+                LocalRsp::Ok
+            }
+            CommandType::Exec => {
+                let _ = packet.txn_take();
+                LocalRsp::ExecWithoutMulti
+            }
+            _ => {
+                debug_assert_eq!(cfg.cmd_type, CommandType::Discard);
+                let _ = packet.txn_take();
+                LocalRsp::DiscardWithoutMulti
+            }
+        };
+        process.process(local::build(cfg, rsp), true);
+        Ok(())
+    }
+
+    // 事务中的指令，每次只处理一个完整的指令
+    #[inline]
+    pub(super) fn parse_txn<S: Stream, H: Hash, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        alg: &H,
+        process: &mut P,
+    ) -> Result<()> {
+        packet.parse_bulk_num()?;
+        let cfg = packet.parse_cmd()?;
+        let rsp = match cfg.cmd_type {
+            CommandType::Exec => {
+                packet.ignore_all_bulks()?;
+                return self.txn_exec(packet, cfg, process);
+            }
+            CommandType::Discard => {
+                packet.ignore_all_bulks()?;
+                let _ = packet.txn_take();
+                process.process(local::discard(cfg), true);
+                return Ok(());
+            }
+            CommandType::Multi => {
+                packet.ignore_all_bulks()?;
+                LocalRsp::Nested
+            }
+            _ if cfg.dedicated == Dedicated::Watch => {
+                packet.ignore_all_bulks()?;
+                LocalRsp::WatchInMulti
+            }
+            // 本地处理的指令无法放到事务中发送
+            _ if cfg.noforward => {
+                packet.ignore_all_bulks()?;
+                LocalRsp::NotAllowed
+            }
+            _ if (!cfg.has_key || cfg.need_reserved_hash) && !packet.txn_pinned() => {
+                packet.ignore_all_bulks()?;
+                LocalRsp::NoShard
+            }
+            _ => match packet.txn_check_keys(cfg, alg, process)? {
+                true if packet.txn_too_large() => LocalRsp::TxnTooLarge,
+                true => LocalRsp::Queued,
+                false => LocalRsp::CrossShard,
+            },
+        };
+        if rsp != LocalRsp::Queued {
+            packet.txn_abort();
+        }
+        packet.txn_save();
+        process.process(local::build(cfg, rsp), true);
+        Ok(())
+    }
+
+    #[inline]
+    fn txn_exec<S: Stream, P: RequestProcessor>(
+        &self,
+        packet: &mut RequestPacket<S>,
+        cfg: &CommandProperties,
+        process: &mut P,
+    ) -> Result<()> {
+        let rsp = if packet.txn_aborted() {
+            LocalRsp::ExecAbort
+        } else if !packet.txn_pinned() {
+            // 只有带key的指令才能进入事务，未确定分片说明事务为空
+            LocalRsp::Empty
+        } else {
+            let cmds = packet.txn_cmds()?;
+            if cmds > MAX_TXN_CMDS {
+                log::warn!("+++ too many cmds in redis txn:{}", cmds);
+                LocalRsp::ExecAbort
+            } else {
+                let mut flag = cfg.flag();
+                flag.set_master_only();
+                flag.set_txn();
+                flag.set_key_count(cmds as u16);
+                flag.set_dedicated(Dedicated::Unwatch);
+                let hash = packet.txn_hash();
+                let req = HashedCommand::new(packet.txn_take(), hash, flag);
+                process.process(req, true);
+                return Ok(());
+            }
+        };
+        let _ = packet.txn_take();
+        process.process(local::build(cfg, rsp), true);
+        Ok(())
+    }
+
+    // 事务的响应：multi的+OK、每个指令的+QUEUED，以及exec的响应，只保留exec的响应
+    #[inline]
+    pub(super) fn parse_txn_response<S: Stream>(
+        &self,
+        req: &HashedCommand,
+        s: &mut S,
+        oft: &mut usize,
+    ) -> Result<Option<Command>> {
+        let data: Packet = s.slice().into();
+        for _ in 0..req.key_count() as usize + 1 {
+            data.skip_rsp(oft)?;
+        }
+        let start = *oft;
+        data.skip_rsp(oft)?;
+        if *oft > data.len() {
+            return Ok(None);
+        }
+        s.ignore(start);
+        Ok(Some(Command::from_ok(s.take(*oft - start))))
+    }
+}
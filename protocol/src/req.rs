@@ -51,6 +51,8 @@ pub trait Request:
     fn on_sent(self) -> Option<Self>;
     fn on_complete(self, resp: Command);
     fn on_err(self, err: crate::Error);
+    // 不再重试，以上一次的响应结束请求，如redis cluster中ASKING失败
+    fn on_finish(self);
     #[inline]
     fn context_mut(&mut self) -> &mut Context {
         self.mut_context()
@@ -63,6 +65,22 @@ pub trait Request:
     fn try_next(&mut self, goon: bool);
     // 请求失败后，协议层面是否允许进行重试
     fn retry_on_rsp_notok(&mut self, retry: bool);
+    // 请求异常（如超时、连接断开）后，是否允许进行重试
+    fn retry_on_err(&mut self, retry: bool);
     // 初始化quota
     fn quota(&mut self, quota: BackendQuota);
+    // 设置重试策略：重试次数、可重试的错误及重试预算
+    fn retry(&mut self, retry: &crate::Retry);
+    // 重试时上一次的响应，如redis cluster的重定向
+    fn response(&self) -> Option<&Command>;
+    // 是否是发送到镜像集群的请求
+    #[inline]
+    fn mirror(&self) -> bool {
+        false
+    }
+    // 是否是对冲请求，对冲请求需要选择与原请求不同的副本
+    #[inline]
+    fn hedge(&self) -> bool {
+        false
+    }
 }
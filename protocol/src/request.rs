@@ -1,4 +1,6 @@
-use crate::{callback::CallbackContext, BackendQuota, Command, Context, Error, HashedCommand};
+use crate::{
+    callback::CallbackContext, BackendQuota, Command, Context, Error, HashedCommand, Retry,
+};
 use std::{
     fmt::{self, Debug, Display, Formatter},
     ptr::NonNull,
@@ -34,6 +36,10 @@ impl crate::Request for Request {
         self.ctx().on_err(err);
     }
     #[inline]
+    fn on_finish(self) {
+        self.ctx().on_finish();
+    }
+    #[inline]
     fn mut_context(&mut self) -> &mut Context {
         &mut self.ctx().flag
     }
@@ -50,9 +56,29 @@ impl crate::Request for Request {
         self.ctx().retry_on_rsp_notok = retry;
     }
     #[inline]
+    fn retry_on_err(&mut self, retry: bool) {
+        self.ctx().retry_on_err = retry;
+    }
+    #[inline]
     fn quota(&mut self, quota: BackendQuota) {
         self.ctx().quota(quota);
     }
+    #[inline]
+    fn retry(&mut self, retry: &Retry) {
+        self.ctx().retry(retry);
+    }
+    #[inline]
+    fn response(&self) -> Option<&Command> {
+        self.ctx().response()
+    }
+    #[inline]
+    fn mirror(&self) -> bool {
+        self.ctx().is_mirror()
+    }
+    #[inline]
+    fn hedge(&self) -> bool {
+        self.ctx().is_hedge()
+    }
 }
 impl Request {
     #[inline]
@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::*};
+use std::sync::Arc;
+
+use ds::time::Instant;
+
+use crate::Error;
+
+// 可重试的错误类型。未发送成功的错误（如连接断开、chan不可用）始终可以重试
+pub const RETRY_TIMEOUT: u8 = 1; // 请求已发送，但未收到响应
+pub const RETRY_CHAN_FULL: u8 = 1 << 1; // 后端的发送队列已满
+pub const RETRY_RSP_NOTOK: u8 = 1 << 2; // 有响应但响应不ok
+pub const RETRY_ALL: u8 = RETRY_TIMEOUT | RETRY_CHAN_FULL | RETRY_RSP_NOTOK;
+
+// 预算的统计窗口
+const BUDGET_WINDOW_MS: u64 = 1000;
+// 每个窗口内至少允许的重试次数，避免低qps的业务无法重试
+const BUDGET_MIN_RETRIES: u32 = 10;
+
+// 请求级别的重试策略，由topo根据namespace的配置设置到请求上
+#[derive(Clone)]
+pub struct Retry {
+    tries: u8, // 首次请求之外，最多的重试次数
+    on: u8,    // 可重试的错误类型
+    budget: Option<RetryBudget>,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            tries: 1,
+            on: RETRY_ALL,
+            budget: None,
+        }
+    }
+}
+
+impl Retry {
+    pub fn new(tries: u8, on: u8, budget: Option<RetryBudget>) -> Self {
+        Self { tries, on, budget }
+    }
+    #[inline]
+    pub fn tries(&self) -> u8 {
+        self.tries
+    }
+    #[inline]
+    pub fn on(&self, kind: u8) -> bool {
+        self.on & kind == kind
+    }
+    #[inline]
+    pub fn budget(&self) -> Option<&RetryBudget> {
+        self.budget.as_ref()
+    }
+    // 根据错误判断是否可以重试
+    #[inline]
+    pub fn on_err(&self, err: &Error) -> bool {
+        match err {
+            Error::Waiting => self.on(RETRY_TIMEOUT),
+            Error::ChanFull => self.on(RETRY_CHAN_FULL),
+            _ => true,
+        }
+    }
+}
+
+// 重试预算：每个窗口内的重试次数不超过请求数的percent%，避免后端抖动时重试放大流量
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct RetryBudget {
+    inner: Arc<Budget>,
+}
+
+struct Budget {
+    percent: u32,
+    start: Instant,
+    // 当前窗口开始的时间，距start的毫秒数
+    window: AtomicU64,
+    reqs: AtomicU32,
+    retries: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(percent: u8) -> Self {
+        Self {
+            inner: Arc::new(Budget {
+                percent: percent as u32,
+                start: Instant::now(),
+                window: 0.into(),
+                reqs: 0.into(),
+                retries: 0.into(),
+            }),
+        }
+    }
+    #[inline]
+    pub fn percent(&self) -> u8 {
+        self.inner.percent as u8
+    }
+    #[inline]
+    pub fn on_request(&self) {
+        self.inner.roll();
+        self.inner.reqs.fetch_add(1, Relaxed);
+    }
+    // 申请一次重试，预算不足时返回false
+    #[inline]
+    pub fn acquire(&self) -> bool {
+        let b = &*self.inner;
+        b.roll();
+        let reqs = b.reqs.load(Relaxed);
+        let limit = (reqs * b.percent / 100).max(BUDGET_MIN_RETRIES);
+        if b.retries.load(Relaxed) >= limit {
+            return false;
+        }
+        b.retries.fetch_add(1, Relaxed);
+        true
+    }
+}
+
+impl Budget {
+    // 窗口到期后重新计数，并发场景下个别计数丢失不影响预算的效果
+    #[inline]
+    fn roll(&self) {
+        let now = self.start.elapsed().as_millis() as u64;
+        let window = self.window.load(Relaxed);
+        if now - window >= BUDGET_WINDOW_MS
+            && self
+                .window
+                .compare_exchange(window, now, AcqRel, Relaxed)
+                .is_ok()
+        {
+            self.reqs.store(0, Relaxed);
+            self.retries.store(0, Relaxed);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Poll};
+
+use tokio::io::AsyncWrite;
+
+use protocol::{Error, HandShake, Protocol, ResOption, Result, Stream};
+
+// 后端连接建立后的握手，如redis的AUTH、SELECT。握手完成后才能发送请求。
+pub struct Auth<'a, P, S> {
+    pub option: &'a mut ResOption,
+    pub s: &'a mut S,
+    pub parser: P,
+}
+
+impl<'a, P, S> Future for Auth<'a, P, S>
+where
+    S: Stream + Unpin + AsyncWrite,
+    P: Protocol + Unpin,
+{
+    type Output = Result<()>;
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let me = &mut *self;
+        let recv_result = me.s.poll_recv(cx)?;
+
+        let auth_result = match me.parser.handshake(me.s, me.option) {
+            Err(e) => Poll::Ready(Err(e)),
+            Ok(HandShake::Failed) => Poll::Ready(Err(Error::AuthFailed)),
+            Ok(HandShake::Continue) => Poll::Pending,
+            Ok(HandShake::Success) => Poll::Ready(Ok(())),
+        };
+
+        let flush_result = Pin::new(&mut *me.s).as_mut().poll_flush(cx);
+
+        let _ = ready!(flush_result);
+        if auth_result.is_ready() {
+            me.s.try_gc();
+            auth_result
+        } else {
+            ready!(recv_result);
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
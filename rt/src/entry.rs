@@ -14,6 +14,9 @@ pub trait ReEnter {
     fn last(&self) -> Option<Instant> {
         None
     }
+    // last返回的请求超时，在close之前调用
+    #[inline]
+    fn on_timeout(&mut self) {}
     // 定期会调用，通常用来清理内存，更新数据等信息。
     // 返回true: 表示期待进行下一次调用
     // 返回false: 表示资源已经释放，不再需要调用。
@@ -67,7 +70,10 @@ impl<T: TimeoutCheck + Sized + Unpin, F: Future<Output = Result<()>> + Unpin + R
     fn poll_run(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
         let Self { timeout, inner, .. } = &mut *self;
         let ret = Pin::new(&mut *inner).poll(cx)?;
-        ready!(timeout.poll_check(cx, inner)?);
+        if let Err(elapsed) = ready!(timeout.poll_check(cx, inner)) {
+            inner.on_timeout();
+            return Poll::Ready(Err(elapsed.into()));
+        }
         // 运行到这里说明：没有需要check timeout的请求
 
         // 只要当前poll进入pending，就会触发持续refresh
@@ -24,12 +24,28 @@ impl Consistent {
         return 0;
     }
 
-    pub fn from<T: Deref<Target = str>>(shards: &[T]) -> Self {
+    // 与twemproxy ketama兼容：每个节点默认160个虚拟节点，每次md5生成4个
+    // 节点可配置为host:port:weight，虚拟节点数与权重成正比，参与hash的节点名不包含权重
+    pub fn from<T: Deref<Target = str>>(shards: &[T], points: Option<u64>) -> Self {
+        let points = points
+            .filter(|p| *p >= POINTS_PER_HASH)
+            .unwrap_or(POINTS_PER_SERVER);
+        let nodes: Vec<(&str, u64)> = shards.iter().map(|s| split_weight(s)).collect();
+        let total: u64 = nodes.iter().map(|(_, w)| *w).sum();
+        let n = nodes.len() as u64;
+
         let mut map = BTreeMap::default();
-        for idx in 0..shards.len() {
-            let factor = 40;
+        for (idx, (name, weight)) in nodes.into_iter().enumerate() {
+            // 权重相同时与未配置权重保持一致
+            let factor = if weight * n == total {
+                points / POINTS_PER_HASH
+            } else {
+                let pct = weight as f32 / total as f32;
+                (pct * points as f32 / POINTS_PER_HASH as f32 * n as f32 + 0.0000000001).floor()
+                    as u64
+            };
             for i in 0..factor {
-                let data: String = shards[idx].to_string() + "-" + &i.to_string();
+                let data: String = name.to_string() + "-" + &i.to_string();
                 let out_bytes = md5::compute(data.as_str());
                 for j in 0..4 {
                     let hash = (((out_bytes[3 + j * 4] & 0xFF) as i64) << 24)
@@ -49,3 +65,18 @@ impl Consistent {
         Self { buckets: map }
     }
 }
+
+const POINTS_PER_SERVER: u64 = 160;
+const POINTS_PER_HASH: u64 = 4;
+
+// 解析host:port:weight格式的节点，未配置权重时为1
+pub fn split_weight(node: &str) -> (&str, u64) {
+    if !node.contains(',') && node.split(':').count() == 3 {
+        if let Some((name, weight)) = node.rsplit_once(':') {
+            if let Ok(weight) = weight.parse::<u64>() {
+                return (name, weight);
+            }
+        }
+    }
+    (node, 1)
+}
@@ -0,0 +1,26 @@
+/// jump consistent hash(Lamping & Veach)，不占用额外内存，
+/// 分片数从n增加到n+1时，只有1/(n+1)的key迁移到新分片
+#[derive(Clone, Debug, Default)]
+pub struct Jump {
+    shards: i64,
+}
+
+impl Jump {
+    pub fn from(shards: usize) -> Self {
+        Self {
+            shards: shards as i64,
+        }
+    }
+
+    #[inline]
+    pub fn index(&self, hash: i64) -> usize {
+        let mut key = hash as u64;
+        let (mut b, mut j) = (-1i64, 0i64);
+        while j < self.shards {
+            b = j;
+            key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+            j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+        }
+        b.max(0) as usize
+    }
+}
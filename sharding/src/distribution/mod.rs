@@ -1,21 +1,26 @@
 mod consistent;
 mod dbrange;
+mod jump;
 mod modrange;
 mod modula;
 //mod padding;
 mod range;
+mod rendezvous;
 mod secmod;
 mod slotmod;
 mod splitmod;
 
+pub use consistent::split_weight;
 use consistent::Consistent;
 pub use dbrange::DBRange;
+use jump::Jump;
 use modrange::ModRange;
 use modula::Modula;
 //use padding::Padding;
 use self::secmod::SecMod;
 use self::slotmod::SlotMod;
 pub use range::Range;
+use rendezvous::Rendezvous;
 use splitmod::SplitMod;
 
 #[derive(Clone, Debug)]
@@ -28,12 +33,15 @@ pub enum Distribute {
     SplitMod(SplitMod),
     SlotMod(SlotMod),
     SecMod(SecMod),
+    Jump(Jump),
+    Rendezvous(Rendezvous),
 }
 
 //pub const DIST_PADDING: &str = "padding";
 //pub const DIST_MODULA: &str = "modula";
 //pub const DIST_ABS_MODULA: &str = "absmodula";
 //pub const DIST_KETAMA: &str = "ketama";
+// ketama默认每个节点160个虚拟节点，如需调整，设置为：ketama-xxx；节点可配置为host:port:weight
 
 // 默认的range分布策略是range，对应的slot是256，如果slot数是xxx(非256)，则需要设置为：range-xxx，shard 需要是2的n次方
 pub const DIST_RANGE: &str = "range";
@@ -67,18 +75,25 @@ impl Distribute {
             //DIST_PADDING => Self::Padding(Default::default()),
             "modula" => Self::Modula(Modula::from(names.len(), false)),
             "absmodula" => Self::Modula(Modula::from(names.len(), true)),
-            "ketama" => Self::Consistent(Consistent::from(names)),
+            "ketama" => Self::Consistent(Consistent::from(names, num)),
             "range" => Self::Range(Range::from(num, names.len())),
             "modrange" => Self::ModRange(ModRange::from(num, names.len())),
             "splitmod" => Self::SplitMod(SplitMod::from(num, names.len())),
             "slotmod" => Self::SlotMod(SlotMod::from(num, names.len())),
             "secmod" => Self::SecMod(SecMod::from(names.len())),
+            "jump" => Self::Jump(Jump::from(names.len())),
+            "rendezvous" => Self::Rendezvous(Rendezvous::from(names)),
             _ => {
                 log::warn!("'{}' is not valid , use modula instead", distribution);
                 Self::Modula(Modula::from(names.len(), false))
             }
         }
     }
+    // 只有ketama支持host:port:weight格式的节点权重，其他分布使用不含权重的节点名
+    pub fn weighted(distribution: &str) -> bool {
+        let dist = distribution.to_ascii_lowercase();
+        dist == "ketama" || dist.starts_with("ketama-")
+    }
     // 适配mysql 动态shands
     // pub fn from_num(distribution: &str, num: usize) -> Self {
     //     let dist = distribution.to_ascii_lowercase();
@@ -101,6 +116,8 @@ impl Distribute {
             Self::SplitMod(s) => s.index(hash),
             Self::SlotMod(s) => s.index(hash),
             Self::SecMod(s) => s.index(hash),
+            Self::Jump(j) => j.index(hash),
+            Self::Rendezvous(r) => r.index(hash),
         }
     }
 }
@@ -0,0 +1,44 @@
+use std::ops::Deref;
+
+/// rendezvous(HRW) hash：key与每个分片计算权重，选择权重最大的分片。
+/// 分片的种子只与分片名相关，增删分片时只影响该分片上的key
+#[derive(Clone, Debug, Default)]
+pub struct Rendezvous {
+    seeds: Vec<u64>,
+}
+
+impl Rendezvous {
+    pub fn from<T: Deref<Target = str>>(shards: &[T]) -> Self {
+        let seeds = shards.iter().map(|s| fnv1a(s.as_bytes())).collect();
+        Self { seeds }
+    }
+
+    #[inline]
+    pub fn index(&self, hash: i64) -> usize {
+        let mut idx = 0;
+        let mut max = 0;
+        for (i, seed) in self.seeds.iter().enumerate() {
+            let score = mix(hash as u64 ^ seed);
+            if i == 0 || score > max {
+                idx = i;
+                max = score;
+            }
+        }
+        idx
+    }
+}
+
+// splitmix64的finalizer，使相近的输入得到分散的权重
+#[inline]
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[inline]
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
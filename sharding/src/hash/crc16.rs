@@ -0,0 +1,39 @@
+// redis cluster的slot数量
+pub const CLUSTER_SLOTS: usize = 16384;
+
+#[derive(Debug, Default, Clone)]
+pub struct Crc16;
+
+/// redis cluster的slot计算：crc16(key) % 16384，crc16为XMODEM算法。
+/// key中包含非空的hash tag（第一个'{'与其后第一个'}'之间的部分）时，只对hash tag计算，
+/// 以便业务通过hash tag把多个key放到同一个slot。
+impl super::Hash for Crc16 {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        let (start, end) = super::hashtag::tag_range(key, b'{', b'}').unwrap_or((0, key.len()));
+        let mut crc: u16 = 0;
+        for i in start..end {
+            crc = (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ key.at(i)) as usize];
+        }
+        (crc as usize % CLUSTER_SLOTS) as i64
+    }
+}
+
+/// crc16 XMODEM（多项式0x1021）快查表
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut j = 0;
+        while j < 8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
@@ -0,0 +1,56 @@
+use super::KeyDelimiter;
+
+const FNV_32_INIT: u32 = 0x811c9dc5;
+const FNV_32_PRIME: u32 = 0x01000193;
+const FNV_64_INIT: u64 = 0xcbf29ce484222325;
+const FNV_64_PRIME: u64 = 0x100000001b3;
+
+// twemproxy中key为char*，x86上非ascii字节按有符号数扩展，为保持分布一致，此处同样先转为i8
+
+// fnv1a 32位版本，同twemproxy的fnv1a_32
+#[derive(Default, Clone, Debug)]
+pub struct Fnv1a32 {
+    delimiter: KeyDelimiter,
+}
+
+// fnv1a 64位版本，twemproxy的fnv1a_64只返回低32位，为保持分布一致，此处同样只取低32位
+#[derive(Default, Clone, Debug)]
+pub struct Fnv1a64 {
+    delimiter: KeyDelimiter,
+}
+
+impl Fnv1a32 {
+    pub fn from(alg: &str) -> Self {
+        Self {
+            delimiter: KeyDelimiter::from(alg),
+        }
+    }
+}
+
+impl Fnv1a64 {
+    pub fn from(alg: &str) -> Self {
+        Self {
+            delimiter: KeyDelimiter::from(alg),
+        }
+    }
+}
+
+impl super::Hash for Fnv1a32 {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        let mut h = FNV_32_INIT;
+        for i in self.delimiter.range(key) {
+            h = (h ^ key.at(i) as i8 as u32).wrapping_mul(FNV_32_PRIME);
+        }
+        h as i64
+    }
+}
+
+impl super::Hash for Fnv1a64 {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        let mut h = FNV_64_INIT;
+        for i in self.delimiter.range(key) {
+            h = (h ^ key.at(i) as i8 as u64).wrapping_mul(FNV_64_PRIME);
+        }
+        h as u32 as i64
+    }
+}
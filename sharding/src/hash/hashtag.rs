@@ -0,0 +1,61 @@
+use super::{Hash, HashKey, Hasher};
+
+// hash tag超过该长度时，需要在堆上拷贝后计算
+const STACK_TAG_LEN: usize = 64;
+const DEFAULT_TAG: &str = "{}";
+
+/// 兼容redis cluster的hash tag：key中包含非空的hash tag（第一个open与其后第一个close之间的部分）时，
+/// 只对hash tag做hash，否则对全key做hash。可包装任意hash算法，如：crc32-tag、crc32-point-tag-[]。
+#[derive(Clone, Debug)]
+pub struct HashTag {
+    open: u8,
+    close: u8,
+    inner: Box<Hasher>,
+}
+
+impl HashTag {
+    pub fn new(open: u8, close: u8, inner: Hasher) -> Self {
+        Self {
+            open,
+            close,
+            inner: Box::new(inner),
+        }
+    }
+    // 拆分hash名称中的tag后缀：xxx-tag 或 xxx-tag-{}，返回(xxx, tag)
+    pub(super) fn split(alg: &str) -> Option<(&str, &str)> {
+        let idx = alg.find("-tag")?;
+        let tag = match &alg[idx + "-tag".len()..] {
+            "" => DEFAULT_TAG,
+            s => s.strip_prefix(super::HASHER_NAME_DELIMITER)?,
+        };
+        Some((&alg[..idx], tag))
+    }
+}
+
+// key中非空hash tag的位置[start, end)，不包含open、close
+#[inline]
+pub(super) fn tag_range<S: HashKey>(key: &S, open: u8, close: u8) -> Option<(usize, usize)> {
+    let l = key.find(0, |c| c == open)?;
+    match key.find(l + 1, |c| c == close)? {
+        r if r > l + 1 => Some((l + 1, r)),
+        _ => None,
+    }
+}
+
+impl Hash for HashTag {
+    fn hash<S: HashKey>(&self, key: &S) -> i64 {
+        let Some((start, end)) = tag_range(key, self.open, self.close) else {
+            return self.inner.hash(key);
+        };
+        // 拷贝到连续内存后再计算，避免hash key类型的嵌套
+        let len = end - start;
+        if len <= STACK_TAG_LEN {
+            let mut buf = [0u8; STACK_TAG_LEN];
+            (start..end).for_each(|i| buf[i - start] = key.at(i));
+            self.inner.hash(&&buf[..len])
+        } else {
+            let buf: Vec<u8> = (start..end).map(|i| key.at(i)).collect();
+            self.inner.hash(&&buf[..])
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use super::KeyDelimiter;
+
+// jenkins one_at_a_time，同twemproxy的one_at_a_time；非ascii字节同twemproxy按有符号char扩展
+#[derive(Default, Clone, Debug)]
+pub struct Jenkins {
+    delimiter: KeyDelimiter,
+}
+
+impl Jenkins {
+    pub fn from(alg: &str) -> Self {
+        Self {
+            delimiter: KeyDelimiter::from(alg),
+        }
+    }
+}
+
+impl super::Hash for Jenkins {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        let mut h = 0u32;
+        for i in self.delimiter.range(key) {
+            h = h.wrapping_add(key.at(i) as i8 as u32);
+            h = h.wrapping_add(h << 10);
+            h ^= h >> 6;
+        }
+        h = h.wrapping_add(h << 3);
+        h ^= h >> 11;
+        h = h.wrapping_add(h << 15);
+        h as i64
+    }
+}
@@ -1,26 +1,38 @@
 pub mod bkdr;
 pub mod bkdrabscrc32;
 pub mod bkdrsub;
+pub mod crc16;
 pub mod crc32;
 pub mod crc32local;
 pub mod crc64;
+pub mod fnv;
+pub mod hashtag;
+pub mod jenkins;
 pub mod lbcrc32local;
+pub mod murmur3;
 pub mod padding;
 pub mod random;
 pub mod raw;
 pub mod rawcrc32local;
 pub mod rawsuffix;
+pub mod xxh3;
 
 pub use bkdr::Bkdr;
 pub use bkdrabscrc32::BkdrAbsCrc32;
+pub use crc16::Crc16;
 pub use crc32::*;
 pub use crc32local::*;
+pub use fnv::{Fnv1a32, Fnv1a64};
+pub use hashtag::HashTag;
+pub use jenkins::Jenkins;
 pub use lbcrc32local::LBCrc32localDelimiter;
+pub use murmur3::Murmur3;
 pub use padding::Padding;
 pub use random::RandomHash;
 pub use raw::Raw;
 pub use rawcrc32local::Rawcrc32local;
 pub use rawsuffix::RawSuffix;
+pub use xxh3::Xxh3;
 
 pub mod crc;
 
@@ -84,8 +96,16 @@ pub enum Hasher {
     Rawcrc32local(Rawcrc32local),                 // raw or crc32local
     Crc32Abs(Crc32Abs), // crc32abs: 基于i32转换，然后直接取abs；其他走i64提升为正数
     Crc64(Crc64),       // Crc64 算法，对整个key做crc64计算
+    Crc16(Crc16),       // redis cluster的slot，支持hash tag
     Random(RandomHash), // random hash
     RawSuffix(RawSuffix),
+    // 兼容twemproxy等代理的hash，均支持与crc32-delimiter相同的分隔符及前缀扩展，如：fnv1a_64-point-3
+    Fnv1a32(Fnv1a32),
+    Fnv1a64(Fnv1a64),
+    Murmur3(Murmur3),
+    Xxh3(Xxh3),
+    Jenkins(Jenkins), // one_at_a_time
+    HashTag(HashTag), // 包装其他hash，只对{}中的hash tag做hash
 }
 
 impl Hasher {
@@ -110,6 +130,10 @@ impl Hasher {
         alg_lower
     }
     pub fn from(alg: &str) -> Self {
+        // 带tag后缀的hash，如crc32-tag、crc32-point-tag-[]，先构建内层hash再包装
+        if let Some((inner, tag)) = HashTag::split(alg) {
+            return Hasher::from(inner).with_tag(tag);
+        }
         let alg_lower = Hasher::reconcreate_hash_name(alg);
         let alg_parts: Vec<&str> = alg_lower.split(HASHER_NAME_DELIMITER).collect();
 
@@ -129,7 +153,11 @@ impl Hasher {
                 }
                 "crc32abs" => Self::Crc32Abs(Default::default()),
                 "crc64" => Self::Crc64(Default::default()),
+                "crc16" => Self::Crc16(Default::default()),
                 "random" => Self::Random(Default::default()),
+                "fnv1a_32" | "fnv1a_64" | "murmur3" | "xxh3" | "jenkins" => {
+                    Self::with_delimiter(alg_parts[0], alg_lower.as_str())
+                }
                 _ => {
                     // 默认采用mc的crc32-s hash
                     log::error!("found unknown hash:{}, use crc32-short instead", alg);
@@ -157,12 +185,34 @@ impl Hasher {
                 _ => Self::Crc32localDelimiter(Crc32localDelimiter::from(alg_lower.as_str())),
             },
             "rawsuffix" => Self::RawSuffix(RawSuffix::from(alg_lower.as_str())),
+            "fnv1a_32" | "fnv1a_64" | "murmur3" | "xxh3" | "jenkins" => {
+                Self::with_delimiter(alg_parts[0], alg_lower.as_str())
+            }
             _ => {
                 log::error!("found unknow hash: {} use crc32 instead", alg);
                 Self::Crc32(Default::default())
             }
         }
     }
+    // 使用hash tag包装当前hash，tag为open、close两个字符，如"{}"，tag非法时不包装
+    pub fn with_tag(self, tag: &str) -> Self {
+        match tag.as_bytes() {
+            [open, close] => Self::HashTag(HashTag::new(*open, *close, self)),
+            _ => {
+                log::warn!("malformed hash tag:{}, ignored", tag);
+                self
+            }
+        }
+    }
+    fn with_delimiter(name: &str, alg: &str) -> Self {
+        match name {
+            "fnv1a_32" => Self::Fnv1a32(Fnv1a32::from(alg)),
+            "fnv1a_64" => Self::Fnv1a64(Fnv1a64::from(alg)),
+            "murmur3" => Self::Murmur3(Murmur3::from(alg)),
+            "xxh3" => Self::Xxh3(Xxh3::from(alg)),
+            _ => Self::Jenkins(Jenkins::from(alg)),
+        }
+    }
     #[inline]
     pub fn crc32_short() -> Self {
         Self::Crc32Short(Default::default())
@@ -183,6 +233,45 @@ fn key_delimiter_name_2u8(_alg: &str, delimiter_name: &str) -> u8 {
     c as u8
 }
 
+// hash key是开始位置之后、分隔符之前的部分，格式同crc32-delimiter：xxx-point-3
+#[derive(Default, Clone, Debug)]
+pub struct KeyDelimiter {
+    start_pos: usize,
+    delimiter: u8,
+}
+
+impl KeyDelimiter {
+    pub fn from(alg: &str) -> Self {
+        let alg_parts: Vec<&str> = alg.split(HASHER_NAME_DELIMITER).collect();
+        let delimiter = match alg_parts.get(1) {
+            Some(name) => key_delimiter_name_2u8(alg, name),
+            None => KEY_DELIMITER_NONE,
+        };
+        let start_pos = match alg_parts.get(2).map(|p| p.parse::<usize>()) {
+            Some(Ok(prefix_len)) => prefix_len,
+            Some(Err(_)) => {
+                log::debug!("found unknown hash/{}, ignore prefix instead", alg);
+                0
+            }
+            None => 0,
+        };
+        Self {
+            start_pos,
+            delimiter,
+        }
+    }
+    // 参与hash计算的key范围
+    #[inline]
+    pub fn range<S: HashKey>(&self, key: &S) -> std::ops::Range<usize> {
+        let start = self.start_pos.min(key.len());
+        let end = match self.delimiter {
+            KEY_DELIMITER_NONE => key.len(),
+            d => key.find(start, |c| c == d).unwrap_or(key.len()),
+        };
+        start..end
+    }
+}
+
 impl Default for Hasher {
     #[inline]
     fn default() -> Self {
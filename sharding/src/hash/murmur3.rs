@@ -0,0 +1,55 @@
+use super::KeyDelimiter;
+
+const C1: u32 = 0xcc9e2d51;
+const C2: u32 = 0x1b873593;
+
+// MurmurHash3_x86_32，seed为0
+#[derive(Default, Clone, Debug)]
+pub struct Murmur3 {
+    delimiter: KeyDelimiter,
+}
+
+impl Murmur3 {
+    pub fn from(alg: &str) -> Self {
+        Self {
+            delimiter: KeyDelimiter::from(alg),
+        }
+    }
+}
+
+impl super::Hash for Murmur3 {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        let range = self.delimiter.range(key);
+        let len = range.len();
+        let mut h = 0u32;
+        // 按小端每次处理4个字节
+        let mut i = range.start;
+        while i + 4 <= range.end {
+            let k = u32::from_le_bytes([key.at(i), key.at(i + 1), key.at(i + 2), key.at(i + 3)]);
+            h ^= mix(k);
+            h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+            i += 4;
+        }
+        // 剩余不足4个字节
+        let mut k = 0u32;
+        for (shift, j) in (i..range.end).enumerate() {
+            k |= (key.at(j) as u32) << (shift * 8);
+        }
+        if i < range.end {
+            h ^= mix(k);
+        }
+
+        h ^= len as u32;
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85ebca6b);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xc2b2ae35);
+        h ^= h >> 16;
+        h as i64
+    }
+}
+
+#[inline]
+fn mix(k: u32) -> u32 {
+    k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
@@ -0,0 +1,35 @@
+use super::KeyDelimiter;
+
+// 超过该长度的key，需要在堆上拷贝后计算
+const STACK_KEY_LEN: usize = 256;
+
+/// xxh3 64位版本，seed为0。与其他hash保持一致，结果去掉最高位，保证非负
+#[derive(Default, Clone, Debug)]
+pub struct Xxh3 {
+    delimiter: KeyDelimiter,
+}
+
+impl Xxh3 {
+    pub fn from(alg: &str) -> Self {
+        Self {
+            delimiter: KeyDelimiter::from(alg),
+        }
+    }
+}
+
+impl super::Hash for Xxh3 {
+    fn hash<S: super::HashKey>(&self, key: &S) -> i64 {
+        // xxh3需要连续内存，key可能跨越ring buffer的边界，先拷贝出来
+        let range = self.delimiter.range(key);
+        let len = range.len();
+        let h = if len <= STACK_KEY_LEN {
+            let mut buf = [0u8; STACK_KEY_LEN];
+            range.clone().for_each(|i| buf[i - range.start] = key.at(i));
+            twox_hash::XxHash3_64::oneshot(&buf[..len])
+        } else {
+            let buf: Vec<u8> = range.map(|i| key.at(i)).collect();
+            twox_hash::XxHash3_64::oneshot(&buf)
+        };
+        (h & i64::MAX as u64) as i64
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::*};
+
+use ds::time::{Duration, Instant};
+use protocol::BreakerOption;
+
+// 半开探测失败后，摘除时长翻倍，最长不超过该值
+const MAX_COOLDOWN_MS: u64 = 60_000;
+// 请求发送时间与探测开始时间分别计算，允许的计时误差
+const PROBE_SKEW_US: u64 = 10;
+
+// 熔断器使用的时钟，测试时可以注入
+pub trait Clock {
+    // 距时钟起点的微秒数
+    fn elapsed_us(&self) -> u64;
+}
+
+impl Clock for Instant {
+    #[inline]
+    fn elapsed_us(&self) -> u64 {
+        self.elapsed().as_micros() as u64
+    }
+}
+
+// 后端实例的熔断器：按窗口统计错误率，超过阈值时从副本选择中摘除cooldown时长，
+// 之后进入半开状态，只放过一个探测请求，成功则恢复，失败则继续摘除。
+// 统计由后端的handler单线程更新，available由各client线程并发读取，try_probe只在选中实例发送请求时调用。
+pub struct Breaker<C = Instant> {
+    clock: C,
+    window_ms: u64,
+    min_reqs: u32,
+    err_percent: u32,
+    // 超过该耗时的请求计为慢请求
+    slow_ms: u64,
+    cooldown_ms: u64,
+    // 当前窗口开始的时间，距clock起点的毫秒数
+    window: AtomicU64,
+    reqs: AtomicU32,
+    errs: AtomicU32,
+    // 摘除截止时间，距clock起点的毫秒数，0表示未摘除
+    ejected_until: AtomicU64,
+    // 本次摘除的时长
+    ejected_ms: AtomicU64,
+    // 半开状态下，探测开始的时间，距clock起点的微秒数，0表示未开始探测
+    probe_us: AtomicU64,
+}
+
+impl Breaker {
+    // timeout：后端的请求超时时间，用于计算慢请求的阈值
+    pub fn new(o: &BreakerOption, timeout: Duration) -> Self {
+        Self::with_clock(o, timeout, Instant::now())
+    }
+}
+
+impl<C: Clock> Breaker<C> {
+    pub fn with_clock(o: &BreakerOption, timeout: Duration, clock: C) -> Self {
+        Self {
+            clock,
+            window_ms: o.window_ms as u64,
+            min_reqs: o.min_reqs,
+            err_percent: o.err_percent,
+            slow_ms: timeout.as_millis() as u64 * o.slow_percent as u64 / 100,
+            cooldown_ms: o.cooldown_ms as u64,
+            window: 0.into(),
+            reqs: 0.into(),
+            errs: 0.into(),
+            ejected_until: 0.into(),
+            ejected_ms: 0.into(),
+            probe_us: 0.into(),
+        }
+    }
+    #[inline]
+    fn now(&self) -> u64 {
+        // 加1避免与未摘除的0冲突
+        self.clock.elapsed_us() / 1000 + 1
+    }
+    #[inline]
+    fn now_us(&self) -> u64 {
+        self.clock.elapsed_us() + 1
+    }
+    // 摘除期间不可用，摘除到期后可用于探测。只读，不改变状态
+    #[inline]
+    pub fn available(&self) -> bool {
+        let until = self.ejected_until.load(Acquire);
+        until == 0 || self.now() >= until
+    }
+    // 选中实例发送请求前调用：摘除到期后只放过一个探测请求，返回true表示本次请求作为探测请求。
+    // 探测请求没有结果时，下一个周期再次探测
+    #[inline]
+    pub fn try_probe(&self) -> bool {
+        let until = self.ejected_until.load(Acquire);
+        if until == 0 {
+            return false;
+        }
+        let now = self.now();
+        if now < until {
+            return false;
+        }
+        let next = now + self.ejected_ms.load(Acquire);
+        let probe = self
+            .ejected_until
+            .compare_exchange(until, next, AcqRel, Acquire);
+        if probe.is_ok() {
+            self.probe_us.store(self.now_us(), Release);
+        }
+        probe.is_ok()
+    }
+    // 请求是否在探测开始之后发送，elapsed为请求发送至今的耗时。
+    // 探测开始前已发出的请求，其结果不能代表探测结果
+    #[inline]
+    fn probed(&self, elapsed: Duration) -> bool {
+        let probe = self.probe_us.load(Acquire);
+        let sent = self.now_us().saturating_sub(elapsed.as_micros() as u64);
+        probe > 0 && sent + PROBE_SKEW_US >= probe
+    }
+    #[inline]
+    pub fn ejected(&self) -> bool {
+        self.ejected_until.load(Acquire) > 0
+    }
+    // 收到响应，rt为请求发送至今的耗时。返回true表示本次触发了摘除
+    #[inline]
+    pub fn on_response(&self, rt: Duration) -> bool {
+        if self.ejected() {
+            // 探测请求成功，恢复。探测开始前发出的请求的响应忽略
+            if self.probed(rt) {
+                self.recover();
+            }
+            return false;
+        }
+        let slow = self.slow_ms > 0 && rt.as_millis() as u64 >= self.slow_ms;
+        self.record(slow)
+    }
+    // 请求超时或连接异常，elapsed为请求发送至今的耗时。返回true表示本次触发了摘除
+    #[inline]
+    pub fn on_err(&self, elapsed: Duration) -> bool {
+        if self.ejected() {
+            if !self.probed(elapsed) {
+                return false;
+            }
+            // 探测请求失败，继续摘除
+            let ms = (self.ejected_ms.load(Acquire) * 2).min(MAX_COOLDOWN_MS);
+            self.eject(ms);
+            return true;
+        }
+        self.record(true)
+    }
+    fn record(&self, err: bool) -> bool {
+        let now = self.now();
+        if now - self.window.load(Acquire) >= self.window_ms {
+            self.reset(now);
+        }
+        let reqs = self.reqs.fetch_add(1, AcqRel) + 1;
+        let errs = self.errs.fetch_add(err as u32, AcqRel) + err as u32;
+        if reqs >= self.min_reqs && errs * 100 >= reqs * self.err_percent {
+            self.eject(self.cooldown_ms);
+            return true;
+        }
+        false
+    }
+    fn eject(&self, ms: u64) {
+        self.reset(self.now());
+        self.ejected_ms.store(ms, Release);
+        self.ejected_until.store(self.now() + ms, Release);
+        self.probe_us.store(0, Release);
+    }
+    fn recover(&self) {
+        self.reset(self.now());
+        self.ejected_until.store(0, Release);
+        self.probe_us.store(0, Release);
+    }
+    fn reset(&self, now: u64) {
+        self.window.store(now, Release);
+        self.reqs.store(0, Release);
+        self.errs.store(0, Release);
+    }
+}
@@ -5,6 +5,8 @@ use ds::chan::mpsc::{channel, Sender, TrySendError};
 use ds::Switcher;
 
 use crate::checker::BackendChecker;
+use crate::{Breaker, Load};
+use ds::time::Duration;
 use endpoint::{Endpoint, Timeout};
 use metrics::Path;
 use protocol::{Error, Protocol, Request, ResOption, Resource};
@@ -25,8 +27,24 @@ impl<R: Request, P: Protocol> From<(&str, P, Resource, &str, Timeout, ResOption)
         let init: Switcher = false.into();
         let f = finish.clone();
         let path = Path::new(vec![rsrc.name(), service]);
-        let checker =
-            BackendChecker::from(addr, rx, f, init.clone(), parser, path, timeout, option);
+        // 熔断阈值由namespace配置，慢请求的阈值按超时时间的百分比计算
+        let to = Duration::from_millis(timeout.ms() as u64);
+        let breaker = Arc::new(Breaker::new(&option.breaker, to));
+        let b = breaker.clone();
+        let load = Arc::new(Load::new());
+        let l = load.clone();
+        let checker = BackendChecker::from(
+            addr,
+            rx,
+            f,
+            init.clone(),
+            parser,
+            path,
+            timeout,
+            option,
+            b,
+            l,
+        );
         rt::spawn(checker.start_check());
 
         let addr = addr.to_string();
@@ -36,6 +54,8 @@ impl<R: Request, P: Protocol> From<(&str, P, Resource, &str, Timeout, ResOption)
                 finish,
                 init,
                 tx,
+                breaker,
+                load,
             }
             .into(),
         }
@@ -54,6 +74,10 @@ pub struct BackendInner<R> {
     finish: Switcher,
     // 由checker设置，标识是否初始化完成。
     init: Switcher,
+    // 错误率过高时，从副本选择中摘除
+    breaker: Arc<Breaker>,
+    // 响应耗时及并发请求数，用于按负载选择副本
+    load: Arc<Load>,
 }
 
 impl<R> discovery::Inited for Backend<R> {
@@ -74,6 +98,8 @@ impl<R: Request> Endpoint for Backend<R> {
     type Item = R;
     #[inline]
     fn send(&self, req: R) {
+        // 实例被摘除且到期后，本次请求作为探测请求
+        self.inner.breaker.try_probe();
         if let Err(e) = self.inner.tx.try_send(req) {
             match e {
                 TrySendError::Closed(r) => r.on_err(Error::ChanWriteClosed),
@@ -85,7 +111,15 @@ impl<R: Request> Endpoint for Backend<R> {
 
     #[inline]
     fn available(&self) -> bool {
-        self.inner.tx.get_enable()
+        self.inner.tx.get_enable() && self.inner.breaker.available()
+    }
+    #[inline]
+    fn load(&self) -> u64 {
+        self.inner.load.cost()
+    }
+    #[inline]
+    fn rtt_percentile(&self, p: u8) -> u64 {
+        self.inner.load.percentile(p)
     }
     #[inline]
     fn addr(&self) -> &str {
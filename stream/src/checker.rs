@@ -1,20 +1,16 @@
 use ds::time::{timeout, Duration};
 use rt::Cancel;
-use std::future::Future;
-use std::pin::Pin;
-use std::task::{ready, Poll};
+use std::sync::Arc;
 
-use tokio::io::AsyncWrite;
-use tokio::net::TcpStream;
-
-use protocol::{Error, HandShake, Protocol, Request, ResOption, Result, Stream};
+use protocol::{Error, Protocol, Request, ResOption};
 
 use crate::handler::Handler;
+use crate::{Breaker, Load};
 use ds::chan::mpsc::Receiver;
 use ds::Switcher;
 use metrics::Path;
 
-use rt::{Entry, Timeout};
+use rt::{Auth, Entry, Timeout};
 
 pub struct BackendChecker<P, Req> {
     rx: Receiver<Req>,
@@ -25,6 +21,8 @@ pub struct BackendChecker<P, Req> {
     timeout: endpoint::Timeout,
     path: Path,
     option: ResOption,
+    breaker: Arc<Breaker>,
+    load: Arc<Load>,
 }
 
 impl<P, Req> BackendChecker<P, Req> {
@@ -37,6 +35,8 @@ impl<P, Req> BackendChecker<P, Req> {
         path: Path,
         timeout: endpoint::Timeout,
         option: ResOption,
+        breaker: Arc<Breaker>,
+        load: Arc<Load>,
     ) -> Self {
         Self {
             addr: addr.to_string(),
@@ -47,6 +47,8 @@ impl<P, Req> BackendChecker<P, Req> {
             timeout,
             path,
             option,
+            breaker,
+            load,
         }
     }
     pub(crate) async fn start_check(mut self)
@@ -61,7 +63,7 @@ impl<P, Req> BackendChecker<P, Req> {
         metrics::incr_task();
         while !self.finish.get() {
             be_conns += 1;
-            let stream = self.reconnect().await;
+            let stream = connect(&self.addr, self.option.tls).await;
             if stream.is_none() {
                 // 连接失败，按策略sleep
                 log::debug!("+++ connected failed to:{}", self.addr);
@@ -99,7 +101,10 @@ impl<P, Req> BackendChecker<P, Req> {
             self.init.on();
             log::debug!("handler started:{:?} with: {}", self.path, self.addr);
             let p = self.parser.clone();
-            let handler = Handler::from(rx, stream, p, rtt);
+            let rsp_bytes = path_addr.qps("rsp_bytes");
+            let breaker = Some((self.breaker.clone(), path_addr.qps("ejected")));
+            let load = Some(self.load.clone());
+            let handler = Handler::from(rx, stream, p, rtt, rsp_bytes, breaker, load);
             let handler = Entry::timeout(handler, Timeout::from(self.timeout.ms()));
             let ret = handler.await;
             log::error!("backend error {:?} => {:?}", path_addr, ret);
@@ -120,56 +125,14 @@ impl<P, Req> BackendChecker<P, Req> {
         metrics::decr_task();
         log::info!("{:?} finished {}", path_addr, self.addr);
     }
-    async fn reconnect(&self) -> Option<TcpStream> {
-        timeout(Duration::from_secs(2), TcpStream::connect(&self.addr))
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::TimedOut, e))
-            .and_then(|x| x)
-            .map_err(|_e| log::debug!("conn to {} err:{}", self.addr, _e))
-            .ok()
-            .map(|s| {
-                let _ = s.set_nodelay(true);
-                s
-            })
-    }
 }
 
-struct Auth<'a, P, S> {
-    pub option: &'a mut ResOption,
-    pub s: &'a mut S,
-    pub parser: P,
-}
-
-impl<'a, P, S> Future for Auth<'a, P, S>
-where
-    S: Stream + Unpin + AsyncWrite,
-    P: Protocol + Unpin,
-{
-    type Output = Result<()>;
-    fn poll(
-        mut self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Self::Output> {
-        let me = &mut *self;
-        let recv_result = me.s.poll_recv(cx)?;
-
-        let auth_result = match me.parser.handshake(me.s, me.option) {
-            Err(e) => Poll::Ready(Err(e)),
-            Ok(HandShake::Failed) => Poll::Ready(Err(Error::AuthFailed)),
-            Ok(HandShake::Continue) => Poll::Pending,
-            Ok(HandShake::Success) => Poll::Ready(Ok(())),
-        };
-
-        let flush_result = Pin::new(&mut *me.s).as_mut().poll_flush(cx);
-
-        let _ = ready!(flush_result);
-        if auth_result.is_ready() {
-            me.s.try_gc();
-            auth_result
-        } else {
-            ready!(recv_result);
-            cx.waker().wake_by_ref();
-            Poll::Pending
-        }
-    }
+// 后端配置了tls时，tls握手完成才算连接成功
+pub(crate) async fn connect(addr: &str, tls: bool) -> Option<net::Stream> {
+    timeout(Duration::from_secs(2), net::connect(addr, tls))
+        .await
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::TimedOut, e))
+        .and_then(|x| x)
+        .map_err(|_e| log::debug!("conn to {} err:{}", addr, _e))
+        .ok()
 }
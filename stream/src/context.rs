@@ -10,6 +10,16 @@ use crate::arena::CallbackContextArena;
 pub(crate) struct CallbackContextPtr {
     ptr: NonNull<CallbackContext>,
     arena: NonNull<CallbackContextArena>,
+    hedge: Hedge,
+}
+
+// 请求的对冲状态
+pub(crate) enum Hedge {
+    None,
+    // 可以对冲，超过对冲延迟后发送
+    Wait,
+    // 对冲请求与原请求从同一个arena分配，释放时一起释放
+    Sent(NonNull<CallbackContext>),
 }
 
 impl CallbackContextPtr {
@@ -31,7 +41,8 @@ impl CallbackContextPtr {
         metric: &mut Arc<M>,
     ) {
         self.async_mode();
-        let mut rsp_ctx = ResponseContext::new(self, metric, |_h| {
+        let mut merged = Vec::new();
+        let mut rsp_ctx = ResponseContext::new(self, metric, &mut merged, |_h| {
             assert!(false, "write back"); // 此处的dist_fn逻辑上暂时不会用
             0
         });
@@ -48,7 +59,58 @@ impl CallbackContextPtr {
     #[inline]
     pub(crate) fn from(ptr: NonNull<CallbackContext>, arena: &mut CallbackContextArena) -> Self {
         let arena = unsafe { NonNull::new_unchecked(arena) };
-        Self { ptr, arena }
+        Self {
+            ptr,
+            arena,
+            hedge: Hedge::None,
+        }
+    }
+    #[inline]
+    pub(crate) fn wait_hedge(&mut self) {
+        self.hedge = Hedge::Wait;
+    }
+    #[inline]
+    pub(crate) fn hedge_waiting(&self) -> bool {
+        matches!(self.hedge, Hedge::Wait)
+    }
+    #[inline]
+    pub(crate) fn hedge_sent(&mut self, hedge: CallbackContextPtr) {
+        debug_assert_eq!(self.arena, hedge.arena);
+        let hedge = std::mem::ManuallyDrop::new(hedge);
+        self.hedge = Hedge::Sent(hedge.ptr);
+    }
+    // 原请求或对冲请求有一个成功，或者两个都已结束
+    #[inline]
+    pub(crate) fn settled(&self) -> bool {
+        match &self.hedge {
+            Hedge::Sent(h) => {
+                let h = unsafe { h.as_ref() };
+                self.succeeded() || h.succeeded() || (self.complete() && h.complete())
+            }
+            _ => self.complete(),
+        }
+    }
+    // 原请求与对冲请求中先成功的胜出，都失败时使用原请求；另一个标记为lost，需要等其结束后再释放。
+    // 返回（胜出的请求，未胜出的请求，对冲请求是否胜出）
+    #[inline]
+    pub(crate) fn settle(mut self) -> (Self, Option<Self>, bool) {
+        match std::mem::replace(&mut self.hedge, Hedge::None) {
+            Hedge::Sent(ptr) => {
+                let hedge = Self {
+                    ptr,
+                    arena: self.arena,
+                    hedge: Hedge::None,
+                };
+                if self.succeeded() || !hedge.succeeded() {
+                    hedge.lose();
+                    (self, Some(hedge), false)
+                } else {
+                    self.lose();
+                    (hedge, Some(self), true)
+                }
+            }
+            _ => (self, None, false),
+        }
     }
 }
 impl Drop for CallbackContextPtr {
@@ -57,6 +119,9 @@ impl Drop for CallbackContextPtr {
         // CallbackContextPtr 在释放时，对arena持有一个mut 引用，因此是safe的。
         // 另外，在CopyBidirectional中使用时，会确保所有的CallbackContextPtr对象释放后，才会销毁arena
         let arena = unsafe { &mut *self.arena.as_ptr() };
+        if let Hedge::Sent(hedge) = self.hedge {
+            arena.dealloc(hedge);
+        }
         arena.dealloc(self.ptr);
     }
 }
@@ -81,15 +146,22 @@ pub struct ResponseContext<'a, M: Metric<T>, T: MetricItem, F: Fn(i64) -> usize>
     // ctx 中的response不可直接用，先封住，按需暴露
     ctx: &'a mut CallbackContextPtr,
     metrics: &'a Arc<M>,
+    merged: &'a mut Vec<u8>,
     dist_fn: F,
     _mark: PhantomData<T>,
 }
 
 impl<'a, M: Metric<T>, T: MetricItem, F: Fn(i64) -> usize> ResponseContext<'a, M, T, F> {
-    pub(super) fn new(ctx: &'a mut CallbackContextPtr, metrics: &'a Arc<M>, dist_fn: F) -> Self {
+    pub(super) fn new(
+        ctx: &'a mut CallbackContextPtr,
+        metrics: &'a Arc<M>,
+        merged: &'a mut Vec<u8>,
+        dist_fn: F,
+    ) -> Self {
         Self {
             ctx,
             metrics,
+            merged,
             dist_fn,
             _mark: Default::default(),
         }
@@ -118,4 +190,8 @@ impl<'a, M: Metric<T>, T: MetricItem, F: Fn(i64) -> usize> Commander<M, T>
     fn ctx(&self) -> u64 {
         self.ctx.flag()
     }
+    #[inline]
+    fn merged(&mut self) -> &mut Vec<u8> {
+        self.merged
+    }
 }
@@ -0,0 +1,485 @@
+// client独占的后端连接。
+// 阻塞指令（blpop等）与订阅指令（subscribe等）不满足一个请求对应一个响应的pipeline模型，
+// 因此为每个client按分片单独建立后端连接：
+// 1. 阻塞指令：每个分片一个连接，复用Handler，请求与响应仍然一一对应；
+// 2. 订阅指令：每个分片一个channel订阅连接、一个pattern订阅连接，连接上收到的所有数据都推送给client；
+// 3. watch：复用阻塞指令的连接，之后清理watch的请求（exec、discard、unwatch）也发送到该连接。
+// 注意：独占连接在建立时确定后端地址，不跟随topology变更。
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering::*};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::{channel as sub_channel, Receiver as SubReceiver, Sender as SubSender};
+
+use ds::chan::mpsc::{channel, Receiver, Sender, TrySendError};
+use ds::{AtomicWaker, MemGuard};
+use endpoint::Endpoint;
+use metrics::Path;
+use protocol::{
+    Command, Dedicated, Error, Protocol, Request as RequestTrait, ResOption, Result, Stream,
+    Subscriptions, Writer,
+};
+use rt::{Auth, Cancel, Entry};
+
+use crate::checker::connect;
+use crate::handler::Handler;
+use crate::Request;
+
+// 每个订阅连接上待发送的订阅请求数
+const SUB_REQS: usize = 256;
+// 待写入client的推送数据的最大字节数，与redis默认的pubsub client-output-buffer-limit一致。
+// 超过时断开client，避免慢订阅者导致内存无限增长
+const MAX_PUSH_BYTES: usize = 32 * 1024 * 1024;
+// 每个client独占连接数的上限，超过时拒绝新的阻塞指令，新的订阅则断开client
+const MAX_CONNS: usize = 256;
+
+pub(crate) struct DedicatedConns<P> {
+    parser: P,
+    // 阻塞指令的连接，按分片索引
+    blocks: Vec<Option<Sender<Request>>>,
+    // 订阅连接，按分片索引
+    channels: Vec<Option<SubSender<Vec<u8>>>>,
+    patterns: Vec<Option<SubSender<Vec<u8>>>>,
+    subs: Subscriptions,
+    pushes: Arc<Pushes>,
+    // watch所在的分片
+    watching: Option<usize>,
+}
+
+impl<P: Protocol + Unpin> DedicatedConns<P> {
+    pub(crate) fn new(parser: P, waker: &Arc<AtomicWaker>) -> Self {
+        Self {
+            parser,
+            blocks: Vec::new(),
+            channels: Vec::new(),
+            patterns: Vec::new(),
+            subs: Subscriptions::default(),
+            pushes: Arc::new(Pushes::new(waker.clone())),
+            watching: None,
+        }
+    }
+    // 发送阻塞指令。连接断开后在下一个请求时重建。
+    pub(crate) fn send<T: Endpoint>(&mut self, top: &T, req: Request) {
+        let shard = top.shard_idx(req.hash());
+        self.send_to(top, shard, req);
+    }
+    fn send_to<T: Endpoint>(&mut self, top: &T, shard: usize, req: Request) {
+        let alive = self.blocks.get(shard).and_then(|tx| tx.as_ref());
+        if !alive.is_some_and(|tx| tx.get_enable()) {
+            if self.opened() >= MAX_CONNS {
+                log::warn!("too many dedicated conns:{}", MAX_CONNS);
+                return reject(req, self.parser.conn_limited(), Error::ChanFull);
+            }
+            let Some((addr, option)) = top.master_of(shard) else {
+                req.on_err(Error::TopInvalid);
+                return;
+            };
+            let (new_tx, mut rx) = channel(32);
+            rx.enable();
+            rt::spawn(block(addr, self.parser.clone(), option, rx));
+            *slot(&mut self.blocks, shard) = Some(new_tx);
+        }
+        let tx = self.blocks[shard].as_ref().expect("block conn");
+        if let Err(e) = tx.try_send(req) {
+            match e {
+                TrySendError::Closed(r) => r.on_err(Error::ChanWriteClosed),
+                TrySendError::Full(r) => r.on_err(Error::ChanFull),
+                TrySendError::Disabled(r) => r.on_err(Error::ChanDisabled),
+            }
+        }
+    }
+    // watch只在一个分片上生效，多次watch需要在同一分片
+    pub(crate) fn watch<T: Endpoint>(&mut self, top: &T, req: Request) {
+        let shard = top.shard_idx(req.hash());
+        if self.watching.is_some_and(|s| s != shard) {
+            return self.cross_shard(req);
+        }
+        self.watching = Some(shard);
+        self.send_to(top, shard, req);
+    }
+    #[inline]
+    pub(crate) fn watching(&self) -> bool {
+        self.watching.is_some()
+    }
+    // 清理watch的请求发送到watch所在的连接，事务需要与watch在同一分片。
+    // 连接断开后watch已经失效，exec直接失败，避免在没有watch的连接上执行事务
+    pub(crate) fn unwatch<T: Endpoint>(&mut self, top: &T, req: Request) {
+        let shard = self.watching.expect("watching");
+        if !req.noforward() && top.shard_idx(req.hash()) != shard {
+            return self.cross_shard(req);
+        }
+        self.watching = None;
+        let alive = self.blocks.get(shard).and_then(|tx| tx.as_ref());
+        match alive.is_some_and(|tx| tx.get_enable()) {
+            true => self.send_to(top, shard, req),
+            false if req.noforward() => {
+                let mut req = req;
+                req.on_noforward();
+            }
+            false => req.on_err(Error::ChanWriteClosed),
+        }
+    }
+    #[inline]
+    fn cross_shard(&self, req: Request) {
+        reject(req, self.parser.cross_shard(), Error::TopInvalid);
+    }
+    // 已建立的独占连接数，断开的阻塞连接不计入
+    fn opened(&self) -> usize {
+        let blocks = self.blocks.iter().flatten().filter(|tx| tx.get_enable());
+        let subs = self.channels.iter().chain(&self.patterns).flatten();
+        blocks.count() + subs.count()
+    }
+    // 发送订阅指令，订阅确认由订阅连接推送给client
+    pub(crate) fn subscribe<T: Endpoint>(&mut self, top: &T, req: &Request) {
+        let mut data = Vec::with_capacity(req.len());
+        req.copy_to_vec(&mut data);
+        let pushes = self.pushes.clone();
+        match req.dedicated() {
+            Dedicated::Subscribe | Dedicated::Unsubscribe => {
+                if req.dedicated() == Dedicated::Subscribe {
+                    self.subs.set_subscribing();
+                }
+                let shard = top.shard_idx(req.hash());
+                if let Some(tx) = self.conn(top, shard, false) {
+                    sub_send(tx, data, &pushes);
+                }
+            }
+            Dedicated::UnsubscribeAll => {
+                // 没有打开的订阅连接时，也需要由后端返回确认
+                let opened: Vec<usize> = (0..self.channels.len())
+                    .filter(|&i| self.channels[i].is_some())
+                    .collect();
+                let shards = if !opened.is_empty() { opened } else { vec![0] };
+                for shard in shards {
+                    if let Some(tx) = self.conn(top, shard, false) {
+                        sub_send(tx, data.clone(), &pushes);
+                    }
+                }
+            }
+            Dedicated::Pattern => {
+                self.subs.set_subscribing();
+                // 模式订阅需要发送到所有分片
+                let mut shard = 0;
+                while let Some(tx) = self.conn(top, shard, true) {
+                    sub_send(tx, data.clone(), &pushes);
+                    shard += 1;
+                }
+            }
+            Dedicated::None | Dedicated::Block | Dedicated::Watch | Dedicated::Unwatch => {}
+        }
+    }
+    // 获取分片的订阅连接，不存在则新建。分片不存在时返回None
+    fn conn<T: Endpoint>(
+        &mut self,
+        top: &T,
+        shard: usize,
+        pattern: bool,
+    ) -> Option<&SubSender<Vec<u8>>> {
+        let (addr, option) = top.master_of(shard)?;
+        let opened = self.opened();
+        let conns = if pattern {
+            &mut self.patterns
+        } else {
+            &mut self.channels
+        };
+        let tx = slot(conns, shard);
+        if tx.is_none() {
+            // 订阅确认由后端推送，无法单独拒绝，只能断开client
+            if opened >= MAX_CONNS {
+                log::warn!("too many dedicated conns:{}, close client", MAX_CONNS);
+                self.pushes.close();
+                return None;
+            }
+            let (new_tx, rx) = sub_channel(SUB_REQS);
+            let pushes = self.pushes.clone();
+            let parser = self.parser.clone();
+            rt::spawn(subscribe(addr, parser, option, rx, shard, pattern, pushes));
+            *tx = Some(new_tx);
+        }
+        tx.as_ref()
+    }
+    #[inline]
+    pub(crate) fn subscribing(&self) -> bool {
+        self.subs.subscribing()
+    }
+    // 把订阅连接推送的数据写入client，resp3为client当前协商的协议版本。返回是否有数据写入
+    pub(crate) fn write_pushes<W: Writer>(&mut self, w: &mut W, resp3: bool) -> Result<bool> {
+        self.subs.set_resp3(resp3);
+        let mut written = false;
+        while let Some((shard, pattern, data)) = self.pushes.pop() {
+            self.parser
+                .write_push(&mut self.subs, shard, pattern, &data, w)?;
+            written = true;
+        }
+        // 订阅连接异常断开，client已丢失订阅，只能断开client
+        if self.pushes.closed() {
+            return Err(Error::Closed);
+        }
+        Ok(written)
+    }
+}
+
+impl<P> Drop for DedicatedConns<P> {
+    #[inline]
+    fn drop(&mut self) {
+        // 订阅连接的task在sender drop后退出，不再需要唤醒client
+        self.pushes.close();
+    }
+}
+
+impl<P> Debug for DedicatedConns<P> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dedicated blocks:{} channels:{} patterns:{} subs:{:?}",
+            self.blocks.len(),
+            self.channels.len(),
+            self.patterns.len(),
+            self.subs
+        )
+    }
+}
+
+// 订阅请求堆积时，说明订阅连接异常，断开client
+#[inline]
+fn sub_send(tx: &SubSender<Vec<u8>>, data: Vec<u8>, pushes: &Pushes) {
+    if let Err(tokio::sync::mpsc::error::TrySendError::Full(_)) = tx.try_send(data) {
+        log::warn!("too many pending subscribe requests, close client");
+        pushes.close();
+    }
+}
+
+// 本地拒绝请求，rsp为空时按无响应处理
+#[inline]
+fn reject(req: Request, rsp: &'static [u8], err: Error) {
+    match rsp {
+        b"" => req.on_err(err),
+        rsp => req.on_complete(Command::from(false, MemGuard::from_vec(rsp.to_vec()))),
+    }
+}
+
+#[inline]
+fn slot<C>(conns: &mut Vec<Option<C>>, shard: usize) -> &mut Option<C> {
+    if conns.len() <= shard {
+        conns.resize_with(shard + 1, || None);
+    }
+    &mut conns[shard]
+}
+
+// (shard, 是否为pattern订阅, 推送数据)
+type Push = (usize, bool, Command);
+
+// 订阅连接收到的数据，由client的pipeline消费
+struct Pushes {
+    // 推送数据及其总字节数
+    queue: Mutex<(VecDeque<Push>, usize)>,
+    closed: AtomicBool,
+    waker: Arc<AtomicWaker>,
+}
+
+impl Pushes {
+    fn new(waker: Arc<AtomicWaker>) -> Self {
+        Self {
+            queue: Mutex::new((VecDeque::new(), 0)),
+            closed: AtomicBool::new(false),
+            waker,
+        }
+    }
+    // client消费过慢，堆积的数据超过限制时关闭，返回false
+    #[inline]
+    fn push(&self, shard: usize, pattern: bool, data: Command) -> bool {
+        let mut queue = self.queue.lock().expect("pushes");
+        if queue.1 + data.len() > MAX_PUSH_BYTES {
+            drop(queue);
+            log::warn!("too many pushes pending:{}, close client", MAX_PUSH_BYTES);
+            self.close();
+            return false;
+        }
+        queue.1 += data.len();
+        queue.0.push_back((shard, pattern, data));
+        drop(queue);
+        self.waker.wake();
+        true
+    }
+    #[inline]
+    fn pop(&self) -> Option<Push> {
+        let mut queue = self.queue.lock().expect("pushes");
+        let push = queue.0.pop_front()?;
+        queue.1 -= push.2.len();
+        Some(push)
+    }
+    #[inline]
+    fn close(&self) {
+        if !self.closed.swap(true, AcqRel) {
+            *self.queue.lock().expect("pushes") = (VecDeque::new(), 0);
+            self.waker.wake();
+        }
+    }
+    #[inline]
+    fn closed(&self) -> bool {
+        self.closed.load(Acquire)
+    }
+}
+
+// 建立连接并鉴权：连接失败返回Pending，鉴权失败返回AuthFailed
+async fn auth<P: Protocol + Unpin>(
+    addr: &str,
+    parser: &P,
+    mut option: ResOption,
+) -> Result<rt::Stream<net::Stream>> {
+    let conn = connect(addr, option.tls).await.ok_or(Error::Pending)?;
+    let mut stream = rt::Stream::from(conn);
+    if parser.config().need_auth {
+        let auth = Auth {
+            option: &mut option,
+            s: &mut stream,
+            parser: parser.clone(),
+        };
+        if let Err(_e) = auth.await {
+            log::warn!("+++ dedicated auth err {} to: {}", _e, addr);
+            stream.cancel();
+            return Err(Error::AuthFailed);
+        }
+    }
+    Ok(stream)
+}
+
+async fn block<P: Protocol + Unpin>(
+    addr: String,
+    parser: P,
+    option: ResOption,
+    mut rx: Receiver<Request>,
+) {
+    match auth(&addr, &parser, option).await {
+        Ok(stream) => {
+            let rtt = Path::base().rtt("block_req");
+            let rsp_bytes = Path::base().qps("block_rsp_bytes");
+            let handler = Handler::from(&mut rx, stream, parser, rtt, rsp_bytes, None, None);
+            // 阻塞指令的耗时由client指定，不做超时检查
+            let _ret = Entry::timeout(handler, rt::DisableTimeout).await;
+            log::info!("block conn to {} finished: {:?}", addr, _ret);
+        }
+        Err(e) => {
+            log::warn!("block conn to {} failed: {:?}", addr, e);
+            rx.disable();
+            let noop = noop_waker::noop_waker();
+            let mut ctx = Context::from_waker(&noop);
+            // 鉴权失败时返回鉴权错误，连接失败时请求未发送
+            let auth_failed = matches!(e, Error::AuthFailed);
+            while let Poll::Ready(Some(req)) = rx.poll_recv(&mut ctx) {
+                match auth_failed {
+                    true => reject(req, parser.auth_failed(), Error::AuthFailed),
+                    false => req.on_err(Error::Pending),
+                }
+            }
+        }
+    }
+}
+
+async fn subscribe<P: Protocol + Unpin>(
+    addr: String,
+    parser: P,
+    option: ResOption,
+    rx: SubReceiver<Vec<u8>>,
+    shard: usize,
+    pattern: bool,
+    pushes: Arc<Pushes>,
+) {
+    if let Ok(s) = auth(&addr, &parser, option).await {
+        let pusher = Pusher {
+            rx,
+            s,
+            parser,
+            shard,
+            pattern,
+            pushes: pushes.clone(),
+        };
+        let _ret = Entry::timeout(pusher, rt::DisableTimeout).await;
+        log::info!("subscribe conn to {} finished: {:?}", addr, _ret);
+    }
+    pushes.close();
+}
+
+// 订阅连接：发送client的订阅请求，并把收到的所有数据推送给client
+struct Pusher<P, S> {
+    rx: SubReceiver<Vec<u8>>,
+    s: S,
+    parser: P,
+    shard: usize,
+    pattern: bool,
+    pushes: Arc<Pushes>,
+}
+
+impl<P, S> Future for Pusher<P, S>
+where
+    P: Protocol + Unpin,
+    S: AsyncRead + AsyncWrite + Stream + Unpin,
+{
+    type Output = Result<()>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = &mut *self;
+        while let Poll::Ready(data) = me.rx.poll_recv(cx) {
+            match data {
+                Some(data) => me.s.write(&data)?,
+                None => return Poll::Ready(Err(Error::ChanReadClosed)),
+            }
+        }
+        let flush = Pin::new(&mut me.s).poll_flush(cx)?;
+        loop {
+            let poll_read = me.s.poll_recv(cx)?;
+            while me.s.len() > 0 {
+                match me.parser.parse_response(&mut me.s)? {
+                    None => break,
+                    Some(cmd) => {
+                        // 复制一份，避免推送数据长时间占用连接的buffer
+                        let mut data = Vec::with_capacity(cmd.len());
+                        cmd.copy_to_vec(&mut data);
+                        let cmd = Command::from(cmd.ok(), MemGuard::from_vec(data));
+                        if !me.pushes.push(me.shard, me.pattern, cmd) {
+                            return Poll::Ready(Err(Error::Closed));
+                        }
+                    }
+                }
+            }
+            if poll_read.is_pending() {
+                break;
+            }
+        }
+        ready!(flush);
+        Poll::Pending
+    }
+}
+
+impl<P, S: AsyncRead + AsyncWrite + Stream + Unpin> rt::ReEnter for Pusher<P, S> {
+    #[inline]
+    fn close(&mut self) -> bool {
+        self.pushes.close();
+        self.s.cancel();
+        self.s.try_gc()
+    }
+    #[inline]
+    fn refresh(&mut self) -> Result<bool> {
+        self.s.try_gc();
+        self.s.shrink();
+        Ok(true)
+    }
+}
+
+impl<P, S: Debug> Debug for Pusher<P, S> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pusher shard:{} pattern:{} => {:?}",
+            self.shard, self.pattern, self.s
+        )
+    }
+}
@@ -1,16 +1,19 @@
 use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{ready, Context, Poll};
 
 use ds::chan::mpsc::Receiver;
-use ds::time::Instant;
+use ds::time::{Duration, Instant};
 use protocol::{Error, Protocol, Request, Result, Stream};
 use tokio::io::ReadBuf;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use metrics::Metric;
 
+use crate::{Breaker, Load};
+
 pub struct Handler<'r, Req, P, S> {
     data: &'r mut Receiver<Req>,
     pending: VecDeque<(Req, Instant)>,
@@ -18,6 +21,12 @@ pub struct Handler<'r, Req, P, S> {
     s: S,
     parser: P,
     rtt: Metric,
+    // 后端返回的响应字节数，用于发现返回大响应的后端
+    rsp_bytes: Metric,
+    // 后端的熔断器及摘除次数，独占连接不熔断
+    breaker: Option<(Arc<Breaker>, Metric)>,
+    // 后端的负载，用于按负载选择副本，独占连接不统计
+    load: Option<Arc<Load>>,
 
     // 处理timeout
     num: Number,
@@ -55,7 +64,15 @@ where
     S: AsyncRead + AsyncWrite + Stream + Unpin,
     P: Protocol + Unpin,
 {
-    pub(crate) fn from(data: &'r mut Receiver<Req>, s: S, parser: P, rtt: Metric) -> Self {
+    pub(crate) fn from(
+        data: &'r mut Receiver<Req>,
+        s: S,
+        parser: P,
+        rtt: Metric,
+        rsp_bytes: Metric,
+        breaker: Option<(Arc<Breaker>, Metric)>,
+        load: Option<Arc<Load>>,
+    ) -> Self {
         data.enable();
         Self {
             data,
@@ -63,6 +80,9 @@ where
             s,
             parser,
             rtt,
+            rsp_bytes,
+            breaker,
+            load,
             num: Number::default(),
             ping_cycle: 0,
         }
@@ -122,7 +142,12 @@ where
             self.s.write_slice(&*req, 0)?;
 
             match req.on_sent() {
-                Some(r) => self.pending.push_back((r, Instant::now())),
+                Some(r) => {
+                    if let Some(load) = self.load.as_ref() {
+                        load.on_sent();
+                    }
+                    self.pending.push_back((r, Instant::now()))
+                }
                 None => self.num.rx(),
             }
         }
@@ -134,13 +159,24 @@ where
             let poll_read = self.s.poll_recv(cx);
 
             while self.s.len() > 0 {
-                match self.parser.parse_response(&mut self.s)? {
+                // 没有等待响应的请求时收到数据，说明连接异常，关闭连接
+                let Some((req, _)) = self.pending.front() else {
+                    log::error!("unexpected data from server:{:?}", self);
+                    return Poll::Ready(Err(Error::UnexpectedData));
+                };
+                match self.parser.parse_response_of(req, &mut self.s)? {
                     None => break,
                     Some(cmd) => {
                         let (req, start) = self.pending.pop_front().expect("take response");
                         self.num.rx();
                         // 统计请求耗时。
-                        self.rtt += start.elapsed();
+                        let elapsed = start.elapsed();
+                        self.rtt += elapsed;
+                        self.on_breaker(start, Some(elapsed));
+                        if let Some(load) = self.load.as_ref() {
+                            load.on_response(elapsed);
+                        }
+                        self.rsp_bytes += cmd.len() as i64;
                         self.parser.check(&*req, &cmd);
                         req.on_complete(cmd);
                     }
@@ -151,6 +187,20 @@ where
         }
         Poll::Ready(Ok(()))
     }
+    // sent为请求发送的时间，rt为None表示请求失败，如超时、连接异常
+    #[inline]
+    fn on_breaker(&mut self, sent: Instant, rt: Option<Duration>) {
+        if let Some((breaker, ejected)) = self.breaker.as_mut() {
+            let eject = match rt {
+                Some(rt) => breaker.on_response(rt),
+                None => breaker.on_err(sent.elapsed()),
+            };
+            if eject {
+                *ejected += 1;
+                log::warn!("backend ejected:{:?}", self.s);
+            }
+        }
+    }
     #[inline(always)]
     fn poll_flush(&mut self, cx: &mut Context) -> Poll<Result<()>> {
         ready!(Pin::new(&mut self.s).poll_flush(cx))?;
@@ -164,6 +214,17 @@ impl<'r, Req: Request, P: Protocol, S: AsyncRead + AsyncWrite + Unpin + Stream>
     fn last(&self) -> Option<ds::time::Instant> {
         self.pending.front().map(|(_, t)| *t)
     }
+    // 超时的请求立即计入熔断统计，其他已发送的请求在close时统计
+    #[inline]
+    fn on_timeout(&mut self) {
+        if let Some((req, start)) = self.pending.pop_front() {
+            req.on_err(Error::Waiting);
+            self.on_breaker(start, None);
+            if let Some(load) = self.load.as_ref() {
+                load.on_err();
+            }
+        }
+    }
     #[inline]
     fn close(&mut self) -> bool {
         self.data.disable();
@@ -174,8 +235,12 @@ impl<'r, Req: Request, P: Protocol, S: AsyncRead + AsyncWrite + Unpin + Stream>
             req.on_err(Error::Pending);
         }
         // 2. 有请求已经发送，但response未获取到
-        while let Some((req, _)) = self.pending.pop_front() {
+        while let Some((req, start)) = self.pending.pop_front() {
             req.on_err(Error::Waiting);
+            self.on_breaker(start, None);
+            if let Some(load) = self.load.as_ref() {
+                load.on_err();
+            }
         }
         // 3. cancel
         use rt::Cancel;
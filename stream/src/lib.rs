@@ -15,10 +15,20 @@ mod builder;
 pub use builder::*;
 
 pub(crate) mod checker;
+mod dedicated;
 
 mod metric;
 pub use metric::StreamMetrics;
 
+mod limit;
+pub use limit::{ConnGuard, Limiter};
+
+mod breaker;
+pub use breaker::{Breaker, Clock};
+
+mod load;
+pub use load::Load;
+
 mod arena;
 
 mod topology;
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::*};
+use std::sync::Arc;
+
+use ds::time::Instant;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// 按service限制client的连接数及qps，同一个service的所有连接共享。
+// 限制值由topology的配置确定，为0时不限制。
+pub struct Limiter {
+    conns: AtomicUsize,
+    // 令牌桶，使用GCRA实现：tat为桶内令牌耗尽的理论时间(距start的纳秒数)，
+    // 每个请求消耗一个令牌，即tat后移1/qps秒；tat超过当前时间1秒时，说明桶已空。
+    tat: AtomicU64,
+    start: Instant,
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self {
+            conns: 0.into(),
+            tat: 0.into(),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Limiter {
+    // 连接数未超过max时返回guard，guard释放时连接数减1
+    #[inline]
+    pub fn conn(self: &Arc<Self>, max: usize) -> Option<ConnGuard> {
+        let conns = self.conns.fetch_add(1, AcqRel);
+        if max > 0 && conns >= max {
+            self.conns.fetch_sub(1, AcqRel);
+            return None;
+        }
+        Some(ConnGuard(self.clone()))
+    }
+    #[inline]
+    pub fn conns(&self) -> usize {
+        self.conns.load(Acquire)
+    }
+    // 从令牌桶中获取一个令牌，桶的容量为1秒的qps。返回false时需要限流
+    #[inline]
+    pub fn acquire(&self, qps: u32) -> bool {
+        if qps == 0 {
+            return true;
+        }
+        let interval = NANOS_PER_SEC / qps as u64;
+        let now = self.start.elapsed().as_nanos() as u64;
+        let mut tat = self.tat.load(Acquire);
+        loop {
+            let next = tat.max(now) + interval;
+            if next > now + NANOS_PER_SEC {
+                return false;
+            }
+            match self.tat.compare_exchange_weak(tat, next, AcqRel, Acquire) {
+                Ok(_) => return true,
+                Err(cur) => tat = cur,
+            }
+        }
+    }
+}
+
+pub struct ConnGuard(Arc<Limiter>);
+impl Drop for ConnGuard {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.conns.fetch_sub(1, AcqRel);
+    }
+}
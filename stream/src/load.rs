@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::*};
+
+use ds::time::{Duration, Instant};
+
+// 请求失败（超时、连接异常）时，按该耗时计入rtt
+const ERR_PENALTY_US: u64 = 500_000;
+// 超过该时长没有响应时，rtt每秒衰减一半，避免慢实例恢复后一直选不到
+const DECAY_MS: u64 = 1000;
+// rtt分布的桶：每个2的幂次区间分成4个桶，误差不超过25%，最大约16s
+const BUCKETS: usize = 4 * 23;
+// 样本数达到该值后所有桶减半，使分布跟随最近的rtt变化
+const HIST_DECAY: u32 = 1024;
+// 样本数不足时不计算分位数
+const HIST_MIN: u32 = 32;
+
+// 后端实例的负载：响应耗时的滑动平均值(ewma)、分布及已发送未响应的请求数。
+// 由后端的handler单线程更新，副本选择、对冲时由各client线程并发读取。
+pub struct Load {
+    start: Instant,
+    // rtt的滑动平均值，单位us，0表示还没有响应
+    rtt_us: AtomicU64,
+    // 最后一次更新rtt的时间，距start的毫秒数
+    last: AtomicU64,
+    inflight: AtomicU32,
+    // rtt的分布，用于计算分位数
+    hist: [AtomicU32; BUCKETS],
+    samples: AtomicU32,
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Load {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            rtt_us: 0.into(),
+            last: 0.into(),
+            inflight: 0.into(),
+            hist: std::array::from_fn(|_| 0.into()),
+            samples: 0.into(),
+        }
+    }
+    #[inline]
+    pub fn on_sent(&self) {
+        self.inflight.fetch_add(1, Relaxed);
+    }
+    #[inline]
+    pub fn on_response(&self, rt: Duration) {
+        let us = rt.as_micros() as u64;
+        self.done(us);
+        self.record(us);
+    }
+    // 请求超时或连接异常
+    #[inline]
+    pub fn on_err(&self) {
+        self.done(ERR_PENALTY_US);
+    }
+    #[inline]
+    fn done(&self, us: u64) {
+        let _ = self
+            .inflight
+            .fetch_update(Relaxed, Relaxed, |n| n.checked_sub(1));
+        // 权重1/8，与tcp的srtt一致
+        let old = self.rtt_us.load(Relaxed);
+        let rtt = match old {
+            0 => us,
+            _ => (old * 7 + us) / 8,
+        };
+        self.rtt_us.store(rtt.max(1), Relaxed);
+        self.last
+            .store(self.start.elapsed().as_millis() as u64, Relaxed);
+    }
+    #[inline]
+    pub fn rtt_us(&self) -> u64 {
+        self.rtt_us.load(Relaxed)
+    }
+    #[inline]
+    pub fn inflight(&self) -> u32 {
+        self.inflight.load(Relaxed)
+    }
+    // 只记录有响应的请求，失败的请求不计入分布
+    #[inline]
+    fn record(&self, us: u64) {
+        let samples = self.samples.load(Relaxed) + 1;
+        if samples >= HIST_DECAY {
+            self.hist
+                .iter()
+                .for_each(|b| b.store(b.load(Relaxed) / 2, Relaxed));
+            self.samples.store(samples / 2, Relaxed);
+        } else {
+            self.samples.store(samples, Relaxed);
+        }
+        self.hist[bucket(us)].fetch_add(1, Relaxed);
+    }
+    // rtt的分位数，单位us，按所在桶的上界计算；样本数不足时返回0
+    #[inline]
+    pub fn percentile(&self, p: u8) -> u64 {
+        let total: u32 = self.hist.iter().map(|b| b.load(Relaxed)).sum();
+        if total < HIST_MIN {
+            return 0;
+        }
+        let target = (total as u64 * p.min(100) as u64).div_ceil(100).max(1);
+        let mut n = 0;
+        for (i, b) in self.hist.iter().enumerate() {
+            n += b.load(Relaxed) as u64;
+            if n >= target {
+                return upper(i);
+            }
+        }
+        upper(BUCKETS - 1)
+    }
+    // 负载 = ewma rtt(us) × (inflight+1)。没有响应的实例rtt按1计，优先被选择以获取rtt
+    #[inline]
+    pub fn cost(&self) -> u64 {
+        let now = self.start.elapsed().as_millis() as u64;
+        // last由handler线程更新，可能晚于这里读取的now
+        let idle = now.saturating_sub(self.last.load(Relaxed));
+        let rtt = (self.rtt_us() >> (idle / DECAY_MS).min(63)).max(1);
+        rtt * (self.inflight() as u64 + 1)
+    }
+}
+
+// 小于4us的各占一个桶，之后每个2的幂次区间[2^e, 2^(e+1))分成4个桶
+#[inline]
+fn bucket(us: u64) -> usize {
+    if us < 4 {
+        return us as usize;
+    }
+    let e = 63 - us.leading_zeros() as usize;
+    let sub = (us >> (e - 2)) as usize & 3;
+    (4 * (e - 1) + sub).min(BUCKETS - 1)
+}
+// 桶的上界（不含）
+#[inline]
+fn upper(idx: usize) -> u64 {
+    if idx < 4 {
+        return idx as u64 + 1;
+    }
+    let (e, sub) = (idx / 4 + 1, idx % 4);
+    (5 + sub as u64) << (e - 2)
+}
@@ -66,7 +66,7 @@ macro_rules! define_metrics {
 }
 
 define_metrics!(
-    qps:    tx-tx, rx-rx, err-err, cps-cps, kps-kps, conn-conn, key-key, nilconvert-nilconvert, inconsist-inconsist;
+    qps:    tx-tx, rx-rx, err-err, cps-cps, kps-kps, conn-conn, key-key, nilconvert-nilconvert, inconsist-inconsist, auth_failed-auth_failed, limited-limited, big_key-big_key, big_value-big_value, big_rsp-big_rsp, mirror-mirror, mirror_err-mirror_err, retry-retry, retry_limited-retry_limited, hedge-hedge, hedge_win-hedge_win;
     num:    conn_num-conn, read-read, write-write, invalid_cmd-invalid_cmd, unsupport_cmd-unsupport_cmd;
     rtt:    avg-avg;
     ratio:  cache-hit;
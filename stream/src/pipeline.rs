@@ -2,28 +2,37 @@ use std::{
     collections::VecDeque,
     future::Future,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
     task::{ready, Context, Poll},
 };
 
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 
 use crate::topology::TopologyCheck;
-use ds::{time::Instant, AtomicWaker};
+use ds::MemGuard;
+use ds::{
+    time::{interval, Duration, Instant, Interval},
+    AtomicWaker,
+};
 use endpoint::Topology;
 use protocol::Error::FlushOnClose;
-use protocol::{HashedCommand, Protocol, Result, Stream};
+use protocol::{Command, Dedicated, HashedCommand, Protocol, Result, Stream};
 
 use crate::{
     arena::CallbackContextArena,
     context::{CallbackContextPtr, ResponseContext},
-    CallbackContext, Request, StreamMetrics,
+    dedicated::DedicatedConns,
+    CallbackContext, Limiter, Request, StreamMetrics,
 };
 
 pub async fn copy_bidirectional<C, P, T>(
     top: T,
     metrics: Arc<StreamMetrics>,
-    client: C,
+    limiter: Arc<Limiter>,
+    mut client: C,
     parser: P,
 ) -> Result<()>
 where
@@ -32,6 +41,14 @@ where
     T: Topology<Item = Request> + Unpin + TopologyCheck,
 {
     *metrics.conn() += 1; // cps
+
+    // 连接数超过限制时，返回错误后关闭连接
+    let Some(_conn) = limiter.conn(top.max_conns()) else {
+        *metrics.limited() += 1;
+        let _ = protocol::Writer::write(&mut client, parser.conn_limited());
+        let _ = AsyncWriteExt::flush(&mut client).await;
+        return Ok(());
+    };
     *metrics.conn_num() += 1;
     let pipeline = CopyBidirectional {
         top,
@@ -45,6 +62,14 @@ where
         start_init: false,
         first: true, // 默认当前请求是第一个
         async_pending: VecDeque::new(),
+        dedicated: None,
+        merged: Vec::new(),
+        resp3: false,
+        authed: false,
+        limiter,
+        limited: false,
+        skip: 0,
+        hedge_tick: None,
 
         arena: CallbackContextArena::with_cache(32),
     };
@@ -67,6 +92,21 @@ pub struct CopyBidirectional<C, P, T> {
     first: bool, // 当前解析的请求是否是第一个。
 
     async_pending: VecDeque<CallbackContextPtr>, // 异步请求中的数量。
+    // 阻塞指令、订阅指令使用的独占连接，按需创建
+    dedicated: Option<Box<DedicatedConns<P>>>,
+    // 跨分片请求（如keys、dbsize）已返回的各分片响应
+    merged: Vec<u8>,
+    // client是否通过hello协商使用RESP3
+    resp3: bool,
+    // client是否已通过鉴权
+    authed: bool,
+    limiter: Arc<Limiter>,
+    // 当前请求是否被限流，multi请求的所有子请求使用第一个子请求的结果
+    limited: bool,
+    // 解析时被拒绝的big value请求，尚未读到的数据长度，读到后直接丢弃
+    skip: usize,
+    // 检查是否有请求需要对冲的定时器，配置了对冲延迟时才创建
+    hedge_tick: Option<Interval>,
 
     arena: CallbackContextArena,
 }
@@ -90,6 +130,10 @@ where
 
             // 把已经返回的response，写入到buffer中。
             self.process_pending()?;
+            // 超过对冲延迟仍未返回的读请求，发送对冲请求
+            self.poll_hedge(cx);
+            // 把订阅连接推送的数据写入到buffer中。
+            self.process_pushes()?;
             let flush = self.poll_flush(cx)?;
 
             if self.pending.len() > 0 && !self.parser.config().pipeline {
@@ -109,29 +153,63 @@ where
     P: Protocol + Unpin,
     T: Topology<Item = Request> + Unpin + TopologyCheck,
 {
-    // 解析buffer，并且发送请求.
+    // 解析buffer，并且发送请求。被拒绝的big value请求的剩余数据直接丢弃，之后的数据继续解析
     #[inline]
     fn parse_request(&mut self) -> Result<()> {
-        if self.client.len() == 0 {
-            return Ok(());
+        loop {
+            if self.skip > 0 {
+                let n = self.skip.min(self.client.len());
+                self.client.ignore(n);
+                self.skip -= n;
+                if self.skip > 0 {
+                    return Ok(());
+                }
+            }
+            if self.client.len() == 0 {
+                return Ok(());
+            }
+            self.parse_buffered()?;
+            if self.skip == 0 {
+                return Ok(());
+            }
         }
+    }
+    #[inline]
+    fn parse_buffered(&mut self) -> Result<()> {
         let Self {
             client,
             top,
             parser,
             pending,
+            async_pending,
             waker,
             first,
             arena,
+            dedicated,
+            resp3,
+            authed,
+            metrics,
+            limiter,
+            limited,
+            skip,
             ..
         } = self;
         // 解析请求，发送请求，并且注册回调
         let mut processor = Visitor {
             pending,
+            async_pending,
             waker,
             top,
+            parser,
             first,
             arena,
+            dedicated,
+            resp3,
+            authed,
+            metrics,
+            limiter,
+            limited,
+            skip,
             retry_on_rsp_notok: parser.config().retry_on_rsp_notok,
         };
 
@@ -163,6 +241,8 @@ where
             start_init,
             metrics,
             flush,
+            merged,
+            async_pending,
             ..
         } = self;
         // 处理回调
@@ -173,10 +253,18 @@ where
                 *start = ctx.start_at();
                 *start_init = true;
             }
-            if !ctx.complete() {
+            if !ctx.settled() {
                 break;
             }
-            let mut ctx = pending.pop_front().expect("front");
+            let ctx = pending.pop_front().expect("front");
+            // 对冲的请求，使用先返回的响应，另一个请求结束后再释放
+            let (mut ctx, lost, hedge_win) = ctx.settle();
+            if let Some(lost) = lost {
+                async_pending.push_back(lost);
+            }
+            if hedge_win {
+                *metrics.hedge_win() += 1;
+            }
             let last = ctx.last();
             // 当前不是最后一个值。也优先写入cache
             if !last {
@@ -184,19 +272,40 @@ where
             }
 
             *metrics.key() += 1;
+            // 重试的次数，及因重试预算不足而放弃重试的请求数
+            if ctx.tries() > 0 {
+                *metrics.retry() += ctx.tries() as i64;
+            }
+            if ctx.retry_limited() {
+                *metrics.retry_limited() += 1;
+            }
             let mut response = ctx.take_response();
+            if let Some(rsp) = response.as_ref() {
+                let max_value = self.top.max_value_len();
+                if max_value > 0 && rsp.len() > max_value {
+                    *metrics.big_rsp() += 1;
+                }
+                self.top.on_response(ctx.request(), rsp);
+            }
 
-            parser.write_response(
-                &mut ResponseContext::new(&mut ctx, metrics, |hash| self.top.shard_idx(hash)),
+            let written = parser.write_response(
+                &mut ResponseContext::new(&mut ctx, metrics, merged, |hash| {
+                    self.top.shard_idx(hash)
+                }),
                 response.as_mut(),
                 client,
-            )?;
+            );
+            // 写入失败时，跨分片请求已合并的部分响应不再有效
+            if written.is_err() {
+                merged.clear();
+            }
+            written?;
 
             let op = ctx.request().operation();
             if let Some(rsp) = response {
                 if ctx.is_write_back() && rsp.ok() {
                     ctx.async_write_back(parser, rsp, self.top.exp_sec(), metrics);
-                    self.async_pending.push_back(ctx);
+                    async_pending.push_back(ctx);
                 }
             }
 
@@ -213,6 +322,16 @@ where
         }
         Ok(())
     }
+    // 订阅模式下，推送数据需要等pending中的响应都写完后再写入，保证顺序
+    #[inline]
+    fn process_pushes(&mut self) -> Result<()> {
+        if let Some(dedicated) = self.dedicated.as_mut() {
+            if self.pending.is_empty() && dedicated.write_pushes(&mut self.client, self.resp3)? {
+                self.flush = true;
+            }
+        }
+        Ok(())
+    }
     // 把response数据flush到client
     #[inline]
     fn poll_flush(&mut self, cx: &mut Context) -> Poll<Result<()>> {
@@ -229,24 +348,153 @@ where
                 if !ctx.async_done() {
                     break;
                 }
+                // 镜像请求的错误单独统计，与主集群的错误区分
+                if ctx.is_mirror() && ctx.failed() {
+                    *self.metrics.mirror_err() += 1;
+                }
+                // 对冲中未胜出的请求可能已有响应，需要take走
+                let _dropped = ctx.take_response();
                 let _ctx = self.async_pending.pop_front();
             }
         }
     }
+    // 定时检查pending中超过对冲延迟仍未返回的请求，复制一份发送给topo，由topo选择另一个副本
+    #[inline]
+    fn poll_hedge(&mut self, cx: &mut Context) {
+        let delay = self.top.hedge_ms();
+        if delay == 0 || self.pending.is_empty() {
+            return;
+        }
+        let delay = Duration::from_millis(delay as u64);
+        let period = (delay / 2).max(Duration::from_millis(1));
+        let Self {
+            top,
+            parser,
+            pending,
+            waker,
+            metrics,
+            arena,
+            hedge_tick,
+            ..
+        } = self;
+        if hedge_tick.as_ref().map(|t| t.period()) != Some(period) {
+            *hedge_tick = Some(interval(period));
+        }
+        let tick = hedge_tick.as_mut().expect("hedge tick");
+        while tick.poll_tick(cx).is_ready() {}
+
+        for ctx in pending.iter_mut() {
+            // pending按请求的发送顺序排列，之后的请求都未超过对冲延迟
+            if ctx.start_at().elapsed() < delay {
+                break;
+            }
+            if !ctx.hedge_waiting() || ctx.complete() {
+                continue;
+            }
+            let cmd = ctx.request().copied();
+            let cb = top.callback();
+            let retry_on_rsp_notok = parser.config().retry_on_rsp_notok;
+            let (first, last) = (ctx.first(), ctx.last());
+            let hedge = CallbackContext::new(cmd, waker, cb, first, last, retry_on_rsp_notok);
+            let mut hedge = CallbackContextPtr::from(arena.alloc(hedge), arena);
+            hedge.hedge_mode(ctx.flag());
+            let req = hedge.build_request();
+            ctx.hedge_sent(hedge);
+            *metrics.hedge() += 1;
+            top.send(req);
+        }
+    }
 }
 
-// struct Visitor<'a, P, T> {
-struct Visitor<'a, T> {
+struct Visitor<'a, P, T> {
     pending: &'a mut VecDeque<CallbackContextPtr>,
+    async_pending: &'a mut VecDeque<CallbackContextPtr>,
     waker: &'a Arc<AtomicWaker>,
     top: &'a T,
+    parser: &'a P,
     first: &'a mut bool,
     arena: &'a mut CallbackContextArena,
+    dedicated: &'a mut Option<Box<DedicatedConns<P>>>,
+    resp3: &'a mut bool,
+    authed: &'a mut bool,
+    metrics: &'a Arc<StreamMetrics>,
+    limiter: &'a Limiter,
+    limited: &'a mut bool,
+    skip: &'a mut usize,
     retry_on_rsp_notok: bool,
 }
 
-impl<'a, T: Topology<Item = Request> + TopologyCheck> protocol::RequestProcessor
-    for Visitor<'a, T>
+impl<'a, P: Protocol, T: Topology<Item = Request> + TopologyCheck> Visitor<'a, P, T> {
+    // 镜像请求以异步方式发送，结束后由async_pending回收
+    #[inline]
+    fn mirror(&mut self, cmd: HashedCommand) {
+        *self.metrics.mirror() += 1;
+        let cb = self.top.callback();
+        let ctx = self
+            .arena
+            .alloc(CallbackContext::new(cmd, self.waker, cb, true, true, false));
+        let mut ctx = CallbackContextPtr::from(ctx, self.arena);
+        ctx.mirror_mode();
+        let req = ctx.build_request();
+        self.async_pending.push_back(ctx);
+        self.top.send(req);
+    }
+
+    // 检测big key/value，统计并记录日志。value的大小以整个请求的长度计算
+    #[inline]
+    fn big(&self, cmd: &HashedCommand) -> bool {
+        let (max_key, max_value) = (self.top.max_key_len(), self.top.max_value_len());
+        let key_len = match max_key {
+            0 => 0,
+            _ => self.parser.key_len(cmd),
+        };
+        let big_key = max_key > 0 && key_len > max_key;
+        let big_value = max_value > 0 && cmd.len() > max_value;
+        if big_key {
+            *self.metrics.big_key() += 1;
+        }
+        if big_value {
+            *self.metrics.big_value() += 1;
+        }
+        if (big_key || big_value) && big_log_allowed() {
+            log::warn!(
+                "big request: {:?} key len:{} len:{}",
+                self.metrics.biz(),
+                key_len,
+                cmd.len()
+            );
+        }
+        big_key || big_value
+    }
+}
+
+// big请求的日志每秒最多记录一次，避免大量big请求时日志刷屏
+static BIG_LOGGED: AtomicU64 = AtomicU64::new(0);
+#[inline]
+fn big_log_allowed() -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let last = BIG_LOGGED.load(Relaxed);
+    now != last
+        && BIG_LOGGED
+            .compare_exchange(last, now, Relaxed, Relaxed)
+            .is_ok()
+}
+
+// 被拒绝的请求不发送，由协议构建的错误响应返回，为空时按无响应处理
+#[inline]
+fn reject(req: Request, rsp: Vec<u8>, err: protocol::Error) {
+    use protocol::req::Request as RequestTrait;
+    match rsp.is_empty() {
+        true => req.on_err(err),
+        false => req.on_complete(Command::from(false, MemGuard::from_vec(rsp))),
+    }
+}
+
+impl<'a, P: Protocol + Unpin, T: Topology<Item = Request> + TopologyCheck>
+    protocol::RequestProcessor for Visitor<'a, P, T>
 {
     #[inline]
     fn process(&mut self, cmd: HashedCommand, last: bool) {
@@ -254,6 +502,13 @@ impl<'a, T: Topology<Item = Request> + TopologyCheck> protocol::RequestProcessor
         // 如果当前是最后一个子请求，那下一个请求就是一个全新的请求。
         // 否则下一个请求是子请求。
         *self.first = last;
+        // 本地响应的请求不限流
+        if first {
+            *self.limited = !cmd.noforward() && !self.limiter.acquire(self.top.max_qps());
+            if *self.limited {
+                *self.metrics.limited() += 1;
+            }
+        }
         let cb = self.top.callback();
         let ctx = self.arena.alloc(CallbackContext::new(
             cmd,
@@ -264,17 +519,150 @@ impl<'a, T: Topology<Item = Request> + TopologyCheck> protocol::RequestProcessor
             self.retry_on_rsp_notok,
         ));
         let mut ctx = CallbackContextPtr::from(ctx, self.arena);
+        let cmd = ctx.request();
+        let forward = !cmd.noforward() && cmd.dedicated() == Dedicated::None;
+        if forward && self.top.hedge_ms() > 0 && self.top.hedge(cmd) {
+            ctx.wait_hedge();
+        }
 
         // pendding 会move走ctx，所以提前把req给封装好
         let mut req: Request = ctx.build_request();
         self.pending.push_back(ctx);
 
+        use protocol::req::Request as RequestTrait;
+        // 被限流的请求不发送，由协议构建错误响应
+        if *self.limited && !req.noforward() {
+            let rsp = self.parser.rate_limited(&req);
+            return reject(req, rsp, protocol::Error::RateLimited);
+        }
+        if !req.noforward() && self.big(&req) && self.top.reject_big() {
+            let rsp = self.parser.big_rejected(&req);
+            return reject(req, rsp, protocol::Error::BigRequest);
+        }
+        let mut dedicated = req.dedicated();
+        // 没有watch时，清理watch的请求按普通请求处理
+        if dedicated == Dedicated::Unwatch && !self.watching() {
+            dedicated = Dedicated::None;
+        }
+        match dedicated {
+            Dedicated::None => {
+                if req.noforward() {
+                    req.on_noforward();
+                } else {
+                    // 镜像请求需要在原请求发送前复制
+                    let mirror = self.top.mirror(&req).then(|| req.copied());
+                    self.top.send(req);
+                    if let Some(cmd) = mirror {
+                        self.mirror(cmd);
+                    }
+                }
+            }
+            // 阻塞指令通过独占连接发送，响应仍按请求顺序返回
+            Dedicated::Block => {
+                let top = self.top;
+                self.dedicated().send(top, req);
+            }
+            // watch及之后的exec、discard、unwatch发送到watch所在的独占连接
+            Dedicated::Watch => {
+                let top = self.top;
+                self.dedicated().watch(top, req);
+            }
+            Dedicated::Unwatch => {
+                let top = self.top;
+                self.dedicated().unwatch(top, req);
+            }
+            // 订阅指令的响应由订阅连接推送
+            _ => {
+                let top = self.top;
+                self.dedicated().subscribe(top, &req);
+                req.on_noforward();
+            }
+        }
+    }
+    #[inline]
+    fn shard_idx(&self, hash: i64) -> usize {
+        self.top.shard_idx(hash)
+    }
+    #[inline]
+    fn shards(&self) -> usize {
+        self.top.shards()
+    }
+    #[inline]
+    fn subscribing(&self) -> bool {
+        self.dedicated
+            .as_ref()
+            .map(|d| d.subscribing())
+            .unwrap_or(false)
+    }
+    #[inline]
+    fn resp3(&self) -> bool {
+        *self.resp3
+    }
+    #[inline]
+    fn set_resp3(&mut self, resp3: bool) {
+        *self.resp3 = resp3;
+    }
+    // 未配置client密码时不需要鉴权
+    #[inline]
+    fn authed(&self) -> bool {
+        *self.authed || self.top.client_password().is_empty()
+    }
+    #[inline]
+    fn auth(&mut self, password: &ds::RingSlice) -> bool {
+        let expected = self.top.client_password().as_bytes();
+        let ok = expected.is_empty() || password.ct_eq(expected);
+        // 鉴权失败时保持之前的鉴权状态
+        match ok {
+            true => *self.authed = true,
+            false => *self.metrics.auth_failed() += 1,
+        }
+        ok
+    }
+    // 开启拒绝时，解析阶段即拒绝big value
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        match self.top.reject_big() {
+            true => self.top.max_value_len(),
+            false => 0,
+        }
+    }
+    #[inline]
+    fn reject_big(&mut self, cmd: HashedCommand, len: usize) {
+        *self.metrics.big_value() += 1;
+        if big_log_allowed() {
+            log::warn!("big request rejected: {:?} len:{}", self.metrics.biz(), len);
+        }
+        let first = *self.first;
+        *self.first = true;
+        let cb = self.top.callback();
+        let ctx = self.arena.alloc(CallbackContext::new(
+            cmd, self.waker, cb, first, true, false,
+        ));
+        let mut ctx = CallbackContextPtr::from(ctx, self.arena);
+        let mut req = ctx.build_request();
+        self.pending.push_back(ctx);
         use protocol::req::Request as RequestTrait;
         if req.noforward() {
-            req.on_noforward();
-        } else {
-            self.top.send(req);
+            return req.on_noforward();
         }
+        let rsp = self.parser.big_rejected(&req);
+        reject(req, rsp, protocol::Error::BigRequest);
+    }
+    #[inline]
+    fn skip(&mut self, n: usize) {
+        *self.skip += n;
+    }
+}
+impl<'a, P: Protocol + Unpin, T> Visitor<'a, P, T> {
+    #[inline]
+    fn watching(&self) -> bool {
+        self.dedicated.as_ref().is_some_and(|d| d.watching())
+    }
+    #[inline]
+    fn dedicated(&mut self) -> &mut DedicatedConns<P> {
+        let (parser, waker) = (self.parser, self.waker);
+        self.dedicated
+            .get_or_insert_with(|| Box::new(DedicatedConns::new(parser.clone(), waker)))
     }
 }
 impl<C, P, T> Drop for CopyBidirectional<C, P, T> {
@@ -295,14 +683,20 @@ where
     fn close(&mut self) -> bool {
         // take走，close后不需要再wake。避免Future drop后再次被wake，导致UB
         self.waker.take();
+        // 独占连接的sender drop后，对应的task会退出
+        self.dedicated.take();
         use rt::Cancel;
         self.client.cancel();
         // 剔除已完成的请求
         while let Some(ctx) = self.pending.front_mut() {
-            if !ctx.complete() {
+            if !ctx.settled() {
                 break;
             }
-            let mut ctx = self.pending.pop_front().expect("empty");
+            let ctx = self.pending.pop_front().expect("empty");
+            let (mut ctx, lost, _) = ctx.settle();
+            if let Some(lost) = lost {
+                self.async_pending.push_back(lost);
+            }
 
             // 如果已经有response记入到ctx，需要take走，保证rsp drop时状态的一致性
             let _dropped = ctx.take_response();
@@ -4,6 +4,7 @@ use endpoint::{Endpoint, Topology};
 use protocol::{
     callback::{Callback, CallbackPtr},
     request::Request,
+    Command, HashedCommand, ResOption,
 };
 use sharding::hash::{Hash, HashKey};
 
@@ -69,6 +70,15 @@ impl<T: Endpoint> Endpoint for CheckedTopology<T> {
     fn shard_idx(&self, hash: i64) -> usize {
         self.top.shard_idx(hash)
     }
+
+    #[inline]
+    fn master_of(&self, shard: usize) -> Option<(String, ResOption)> {
+        self.top.master_of(shard)
+    }
+    #[inline]
+    fn shards(&self) -> usize {
+        self.top.shards()
+    }
 }
 
 impl<T: Topology> Hash for CheckedTopology<T> {
@@ -83,4 +93,44 @@ impl<T: Topology> Topology for CheckedTopology<T> {
     fn exp_sec(&self) -> u32 {
         self.top.exp_sec()
     }
+    #[inline]
+    fn client_password(&self) -> &str {
+        self.top.client_password()
+    }
+    #[inline]
+    fn max_conns(&self) -> usize {
+        self.top.max_conns()
+    }
+    #[inline]
+    fn max_qps(&self) -> u32 {
+        self.top.max_qps()
+    }
+    #[inline]
+    fn max_key_len(&self) -> usize {
+        self.top.max_key_len()
+    }
+    #[inline]
+    fn max_value_len(&self) -> usize {
+        self.top.max_value_len()
+    }
+    #[inline]
+    fn reject_big(&self) -> bool {
+        self.top.reject_big()
+    }
+    #[inline]
+    fn mirror(&self, req: &HashedCommand) -> bool {
+        self.top.mirror(req)
+    }
+    #[inline]
+    fn hedge_ms(&self) -> u32 {
+        self.top.hedge_ms()
+    }
+    #[inline]
+    fn hedge(&self, req: &HashedCommand) -> bool {
+        self.top.hedge(req)
+    }
+    #[inline]
+    fn on_response(&self, req: &HashedCommand, rsp: &Command) {
+        self.top.on_response(req, rsp)
+    }
 }
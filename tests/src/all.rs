@@ -8,23 +8,34 @@ mod protocols;
 //mod queue;
 // mod redis;
 mod hash_test;
+mod mc_binary;
+mod mc_text;
+mod proto_mock;
 mod redis;
 mod ring_slice;
 mod size;
 //mod slice;
 mod arena;
 mod asserts;
+mod breaker;
 mod layout;
+mod limit;
+mod load;
+mod retry;
 // mod mysql;
 mod bkdrsub;
 mod cow;
 mod decrypt;
 mod discovery;
 mod dns;
+mod hedge;
 mod kv;
+mod mirror;
 mod mysql_strategy;
+mod near_cache;
 mod number;
 mod ring_buffer;
 mod select;
 mod time;
+mod tls;
 mod tx_buffer;
@@ -1,6 +1,7 @@
 use criterion::{criterion_group, criterion_main};
 
 mod arena;
+mod distribute;
 mod hash;
 mod heap;
 mod kv;
@@ -13,6 +14,7 @@ criterion_group!(std_cmp, std_cmp::bench_num_to_str, std_cmp::bench_mod);
 criterion_group!(time, time::bench_instant, time::bench_duration);
 criterion_group!(heap, heap::bench_get_checked);
 criterion_group!(hash, hash::bench_crc32);
+criterion_group!(distribute, distribute::bench_dist);
 criterion_group!(redis, redis::parse, redis::parse_num);
 criterion_group!(kv, kv::bench_parse_mysql);
 criterion_group!(
@@ -24,4 +26,4 @@ criterion_group!(
 );
 criterion_group!(arena, arena::bench_alloc);
 
-criterion_main!(time, heap, ring_slice, arena, hash, redis, std_cmp);
+criterion_main!(time, heap, ring_slice, arena, hash, distribute, redis, std_cmp);
@@ -0,0 +1,16 @@
+use criterion::Criterion;
+use sharding::distribution::Distribute;
+pub(super) fn bench_dist(c: &mut Criterion) {
+    let shards: Vec<String> = (0..64).map(|i| format!("10.0.0.{}:11211", i)).collect();
+    let hashes: Vec<i64> = (0..1024u64)
+        .map(|i| i.wrapping_mul(0x9E3779B97F4A7C15) as i64)
+        .collect();
+    let mut group = c.benchmark_group("distribute");
+    for name in ["modula", "ketama", "jump", "rendezvous"] {
+        let dist = Distribute::from(name, &shards);
+        group.bench_function(name, |b| {
+            b.iter(|| hashes.iter().fold(0, |s, h| s + dist.index(*h)));
+        });
+    }
+    group.finish();
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod breaker_test {
+    use std::sync::atomic::{AtomicU64, Ordering::*};
+
+    use ds::time::Duration;
+    use protocol::BreakerOption;
+    use stream::{Breaker, Clock};
+
+    // 手动推进的时钟，单位微秒
+    #[derive(Default)]
+    struct MockClock(AtomicU64);
+    impl Clock for &MockClock {
+        fn elapsed_us(&self) -> u64 {
+            self.0.load(Acquire)
+        }
+    }
+    impl MockClock {
+        fn sleep(&self, d: Duration) {
+            self.0.fetch_add(d.as_micros() as u64, AcqRel);
+        }
+    }
+
+    /// 测试场景：错误率超过阈值后摘除，冷却后半开探测
+    /// 测试步骤：
+    ///     1.请求数不足时，即使全部失败也不摘除；
+    ///     2.错误（含慢请求）超过一半后摘除，摘除期间不可用；
+    ///     3.冷却后available只读，try_probe只放过一个探测请求；
+    ///     4.探测开始前发出的请求，结果忽略；探测失败继续摘除，探测成功后恢复。
+    #[test]
+    fn breaker_eject() {
+        let clock = MockClock::default();
+        let cooldown = Duration::from_millis(50);
+        let o = BreakerOption {
+            cooldown_ms: 50,
+            ..Default::default()
+        };
+        let breaker = Breaker::with_clock(&o, Duration::from_millis(200), &clock);
+        let (fast, now) = (Duration::from_millis(1), Duration::ZERO);
+        for _ in 0..16 {
+            assert!(!breaker.on_err(now));
+        }
+        assert!(breaker.available());
+        assert!(!breaker.try_probe());
+        for _ in 0..14 {
+            assert!(!breaker.on_response(fast));
+        }
+        assert!(!breaker.on_response(Duration::from_millis(200)));
+        // 第32个请求，错误数17
+        assert!(breaker.on_err(now));
+        assert!(!breaker.available());
+        assert!(!breaker.try_probe());
+        // 摘除前发出的请求，响应不再统计
+        clock.sleep(fast);
+        assert!(!breaker.on_err(fast));
+        assert!(!breaker.on_response(fast));
+
+        clock.sleep(cooldown);
+        // available不改变状态，只有选中实例发送时才开始探测
+        assert!(breaker.available());
+        assert!(breaker.available());
+        assert!(breaker.try_probe());
+        assert!(!breaker.try_probe());
+        assert!(!breaker.available());
+        // 探测开始前发出的请求，结果不作为探测结果
+        clock.sleep(fast);
+        let inflight = cooldown + fast * 2;
+        assert!(!breaker.on_err(inflight));
+        assert!(!breaker.on_response(inflight));
+        assert!(breaker.ejected());
+        // 探测失败，摘除时长翻倍
+        assert!(breaker.on_err(now));
+        clock.sleep(cooldown);
+        assert!(!breaker.available());
+        clock.sleep(cooldown);
+        assert!(breaker.available());
+        assert!(breaker.try_probe());
+        assert!(!breaker.on_response(now));
+        assert!(!breaker.ejected());
+        assert!(breaker.available());
+        assert!(!breaker.try_probe());
+    }
+
+    /// 测试场景：熔断阈值由namespace配置
+    /// 测试步骤：
+    ///     1.未配置的项使用默认值；
+    ///     2.窗口过期后重新统计，请求数、错误占比及慢请求阈值按配置计算。
+    #[test]
+    fn breaker_option() {
+        let cfg = endpoint::BreakerConfig::default();
+        assert_eq!(cfg.option(), BreakerOption::default());
+        let cfg = endpoint::BreakerConfig {
+            window_ms: 1000,
+            min_reqs: 4,
+            err_percent: 75,
+            slow_percent: 10,
+            ..Default::default()
+        };
+        let o = cfg.option();
+        assert_eq!(o.cooldown_ms, 5_000);
+
+        let clock = MockClock::default();
+        let breaker = Breaker::with_clock(&o, Duration::from_millis(100), &clock);
+        let slow = Duration::from_millis(10);
+        for _ in 0..3 {
+            assert!(!breaker.on_response(slow));
+        }
+        // 窗口过期，之前的统计不再计入
+        clock.sleep(Duration::from_millis(1000));
+        assert!(!breaker.on_err(Duration::ZERO));
+        assert!(!breaker.on_response(Duration::from_millis(9)));
+        assert!(!breaker.on_response(slow));
+        assert!(breaker.on_response(slow));
+        assert!(!breaker.available());
+    }
+}
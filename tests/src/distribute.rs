@@ -89,4 +89,116 @@ mod distribute_test {
         println!("idx:{}", idx);
         assert_eq!(idx, 2);
     }
+
+    // 权重相同时与未配置权重的分布一致，虚拟节点数与权重成正比
+    #[test]
+    fn ketama_weight() {
+        use sharding::distribution::split_weight;
+        assert_eq!(split_weight("10.0.0.1:11211:3"), ("10.0.0.1:11211", 3));
+        assert_eq!(split_weight("10.0.0.1:11211"), ("10.0.0.1:11211", 1));
+        assert_eq!(
+            split_weight("10.0.0.1:6379,10.0.0.2:6379"),
+            ("10.0.0.1:6379,10.0.0.2:6379", 1)
+        );
+
+        let shards: Vec<String> = (0..5).map(|i| format!("10.0.0.{}:11211", i)).collect();
+        let weighted: Vec<String> = shards.iter().map(|s| format!("{}:2", s)).collect();
+        let dist = Distribute::from("ketama", &shards);
+        let same = [
+            Distribute::from("ketama-160", &shards),
+            Distribute::from("ketama", &weighted),
+        ];
+        let hasher = Hasher::from("crc32");
+        for i in 0..1000 {
+            let hash = hasher.hash(&format!("{}.key", i).as_bytes());
+            let idx = dist.index(hash);
+            same.iter().for_each(|d| assert_eq!(d.index(hash), idx));
+        }
+
+        assert!(Distribute::weighted("ketama"));
+        assert!(Distribute::weighted("Ketama-80"));
+        assert!(!Distribute::weighted("rendezvous"));
+        assert!(!Distribute::weighted("modula"));
+
+        let mut weighted = shards.clone();
+        weighted[0] = format!("{}:4", shards[0]);
+        let dist = Distribute::from("ketama-80", &weighted);
+        let mut counts = vec![0; shards.len()];
+        for i in 0..10000 {
+            let hash = hasher.hash(&format!("{}.key", i).as_bytes());
+            counts[dist.index(hash)] += 1;
+        }
+        assert!(
+            counts[1..].iter().all(|c| counts[0] > c * 2),
+            "{:?}",
+            counts
+        );
+    }
+
+    // 增加一个分片时，只有迁移到新分片的key发生变化
+    fn check_grow(dist: &str, shards: &mut Vec<String>) {
+        let hasher = Hasher::from("crc32");
+        let hashes: Vec<i64> = (0..10000)
+            .map(|i| hasher.hash(&format!("{}.key", i).as_bytes()))
+            .collect();
+        let old = Distribute::from(dist, shards);
+        shards.push(format!("shard_{}", shards.len()));
+        let new = Distribute::from(dist, shards);
+        let mut counts = vec![0; shards.len()];
+        let mut moved = 0;
+        for hash in hashes.iter() {
+            let (o, n) = (old.index(*hash), new.index(*hash));
+            counts[n] += 1;
+            if o != n {
+                assert_eq!(n, shards.len() - 1, "{} {}", dist, hash);
+                moved += 1;
+            }
+        }
+        // 均匀分布，每个分片的key数量偏差不超过30%
+        let avg = hashes.len() / shards.len();
+        assert!(
+            counts.iter().all(|c| c * 10 > avg * 7 && c * 10 < avg * 13),
+            "{:?}",
+            counts
+        );
+        assert!(
+            moved * 10 > avg * 7 && moved * 10 < avg * 13,
+            "{} {}",
+            dist,
+            moved
+        );
+    }
+
+    #[test]
+    fn jump() {
+        let mut shards: Vec<String> = (0..7).map(|i| format!("shard_{}", i)).collect();
+        check_grow("jump", &mut shards);
+        check_grow("jump", &mut shards);
+
+        let dist = Distribute::from("jump", &shards[..1]);
+        assert_eq!(dist.index(-1), 0);
+        assert_eq!(dist.index(i64::MAX), 0);
+    }
+
+    #[test]
+    fn rendezvous() {
+        let mut shards: Vec<String> = (0..7).map(|i| format!("shard_{}", i)).collect();
+        check_grow("rendezvous", &mut shards);
+        check_grow("rendezvous", &mut shards);
+
+        // 删除一个分片，只有该分片上的key发生迁移
+        let hasher = Hasher::from("crc32");
+        let dist = Distribute::from("rendezvous", &shards);
+        let removed = shards.remove(3);
+        let new = Distribute::from("rendezvous", &shards);
+        for i in 0..10000 {
+            let hash = hasher.hash(&format!("{}.key", i).as_bytes());
+            let idx = dist.index(hash);
+            if idx != 3 {
+                assert_eq!(&shards[new.index(hash)], &format!("shard_{}", idx));
+            } else {
+                assert_ne!(&shards[new.index(hash)], &removed);
+            }
+        }
+    }
 }
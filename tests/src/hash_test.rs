@@ -232,6 +232,24 @@ mod hash_test {
         assert_eq!(-7536761181773004100_i64, crc);
     }
 
+    #[test]
+    fn crc16() {
+        let hasher = Hasher::from("crc16");
+        // crc16 xmodem("123456789") = 0x31C3
+        assert_eq!(hasher.hash(&"123456789".as_bytes()), 12739);
+        assert_eq!(hasher.hash(&"foo".as_bytes()), 12182);
+        assert_eq!(hasher.hash(&"bar".as_bytes()), 5061);
+
+        // hash tag
+        let tag = hasher.hash(&"user1000".as_bytes());
+        assert_eq!(hasher.hash(&"{user1000}.following".as_bytes()), tag);
+        assert_eq!(hasher.hash(&"{user1000}.followers".as_bytes()), tag);
+        assert_eq!(hasher.hash(&"foo{bar}{zap}".as_bytes()), 5061);
+        // 空tag、未闭合的tag，对整个key计算
+        assert_eq!(hasher.hash(&"foo{}{bar}".as_bytes()), 8363);
+        assert_eq!(hasher.hash(&"{bar".as_bytes()), 4015);
+    }
+
     #[test]
     fn bkdrabscrc32() {
         let hasher = Hasher::from("bkdrabscrc32");
@@ -309,4 +327,100 @@ mod hash_test {
         let crc32_lblocal_hasher = crc32_lblocal_hasher.hash(key);
         assert_eq!(crc32_lblocal, crc32_lblocal_hasher, "key:{key:?}");
     }
+    // 参考向量分别来自各算法的标准实现
+    #[test]
+    fn twemproxy_hashers() {
+        let vectors: [(&str, &str, u64); 21] = [
+            ("fnv1a_32", "", 0x811c9dc5),
+            ("fnv1a_32", "a", 0xe40c292c),
+            ("fnv1a_32", "foobar", 0xbf9cf968),
+            ("fnv1a_64", "a", 0x8601ec8c),
+            ("fnv1a_64", "foobar", 0xf73967e8),
+            // 非ascii字节同twemproxy按有符号char扩展
+            ("fnv1a_32", "é", 0x3cfa68c1),
+            ("fnv1a_32", "你好", 0x2ada87a3),
+            ("fnv1a_64", "é", 0xb4cc3001),
+            ("fnv1a_64", "你好", 0x378702a3),
+            ("murmur3", "", 0),
+            ("murmur3", "hello", 0x248bfa47),
+            (
+                "murmur3",
+                "The quick brown fox jumps over the lazy dog",
+                0x2e4ff723,
+            ),
+            ("jenkins", "a", 0xca2e9442),
+            (
+                "jenkins",
+                "The quick brown fox jumps over the lazy dog",
+                0x519e91f5,
+            ),
+            ("jenkins", "é", 0x019148ae),
+            ("jenkins", "你好", 0x4c089dab),
+            // xxh3为XXH3_64bits的结果去掉最高位
+            ("xxh3", "", 0x2d06800538d394c2),
+            ("xxh3", "a", 0x66c632b61e964e1f),
+            ("xxh3", "abc", 0x78af5f94892f3950),
+            ("xxh3", "hello world!", 0x6155d613728f4b18),
+            (
+                "xxh3",
+                "The quick brown fox jumps over the lazy dog",
+                0x4e7d19a5418fb365,
+            ),
+        ];
+        for (alg, key, h) in vectors {
+            assert_eq!(
+                Hasher::from(alg).hash(&key.as_bytes()),
+                h as i64,
+                "{alg} {key}"
+            );
+        }
+        // 覆盖xxh3 129~240及240以上长度的计算分支
+        let xxh3 = Hasher::from("xxh3");
+        assert_eq!(xxh3.hash(&"x".repeat(200).as_bytes()), 0x50ef124fb1e4de53);
+        assert_eq!(xxh3.hash(&"a".repeat(300).as_bytes()), 0x41d17581d8eb7e21);
+    }
+    #[test]
+    fn twemproxy_hashers_delimiter() {
+        let long = "a".repeat(300);
+        for alg in ["fnv1a_32", "fnv1a_64", "murmur3", "xxh3", "jenkins"] {
+            let hasher = Hasher::from(alg);
+            let h = hasher.hash(&"abcdef".as_bytes());
+            let point = Hasher::from(&format!("{}-point", alg));
+            assert_eq!(point.hash(&"abcdef.xyz".as_bytes()), h, "{alg}");
+            let prefix = Hasher::from(&format!("{}-underscore-3", alg));
+            assert_eq!(prefix.hash(&"xx.abcdef_1".as_bytes()), h, "{alg}");
+
+            let h = hasher.hash(&long.as_bytes());
+            let long_key = format!("{}#1", long);
+            assert_eq!(
+                Hasher::from(&format!("{}-pound", alg)).hash(&long_key.as_bytes()),
+                h
+            );
+        }
+    }
+    #[test]
+    fn hash_tag() {
+        let crc32 = Hasher::from("crc32");
+        let h = crc32.hash(&"user1".as_bytes());
+        let tag = Hasher::from("crc32-tag");
+        assert_eq!(tag.hash(&"{user1}.name".as_bytes()), h);
+        assert_eq!(tag.hash(&"a.{user1}.b}".as_bytes()), h);
+        // 没有tag或tag为空时，对全key做hash
+        for key in ["user1.name", "{}user1", "user1{.name"] {
+            assert_eq!(tag.hash(&key.as_bytes()), crc32.hash(&key.as_bytes()));
+        }
+
+        let point = Hasher::from("crc32-point-tag-[]");
+        assert_eq!(point.hash(&"x[user1.a]{b}".as_bytes()), h);
+        assert_eq!(point.hash(&"user1.a".as_bytes()), h);
+        let long = "u".repeat(100);
+        let key = format!("{{{}}}.a", long);
+        assert_eq!(tag.hash(&key.as_bytes()), crc32.hash(&long.as_bytes()));
+
+        let bkdr = Hasher::from("bkdr").with_tag("<>");
+        let h = Hasher::from("bkdr").hash(&"user1".as_bytes());
+        assert_eq!(bkdr.hash(&"abc<user1>".as_bytes()), h);
+        let malformed = Hasher::from("bkdr").with_tag("{");
+        assert_eq!(malformed.hash(&"user1".as_bytes()), h);
+    }
 }
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod hedge_test {
+    use crate::proto_mock::*;
+    use ds::MemGuard;
+    use protocol::{Command, Request};
+
+    /// 测试场景：对冲请求先返回时，原请求的响应被安全丢弃
+    /// 测试步骤：
+    ///     1.对冲请求复制原请求的topo上下文，并通过Request::hedge区分；
+    ///     2.对冲请求先返回并胜出，原请求标记为lost；
+    ///     3.原请求之后返回，响应被丢弃、不再重试，结束后可以释放。
+    #[test]
+    fn hedge_lost() {
+        let mock = MockCallback::default();
+        let cmd = mc_cmd(b"get k\r\n");
+        let mut origin = mock.ctx(cmd.copied());
+        origin.send();
+        let mut o = mock.pop().expect("origin");
+        *o.mut_context() = 7;
+        o.try_next(true);
+        let mut hedge = mock.ctx(cmd);
+        hedge.hedge_mode(origin.flag());
+        hedge.send();
+        let mut h = mock.pop().expect("hedge");
+        assert!(h.hedge() && !o.hedge());
+        assert_eq!(*h.mut_context(), 7);
+
+        let rsp = |data: &[u8]| Command::from_ok(MemGuard::from_vec(data.to_vec()));
+        h.on_complete(rsp(b"VALUE k 0 1\r\nv\r\nEND\r\n"));
+        assert!(hedge.complete());
+        origin.lose();
+        o.on_complete(Command::from(
+            false,
+            MemGuard::from_vec(b"END\r\n".to_vec()),
+        ));
+        // 未胜出的请求不重试，响应被丢弃
+        assert!(mock.pop().is_none());
+        assert!(origin.async_done());
+        assert!(origin.take_response().is_none());
+        let rsp = hedge.take_response().expect("hedge rsp");
+        assert_eq!(string(&rsp), "VALUE k 0 1\r\nv\r\nEND\r\n");
+    }
+
+    /// 测试场景：先返回错误的一方不能胜出，需等另一方返回ok响应或也结束
+    /// 测试步骤：
+    ///     1.原请求先失败，已结束但不是成功状态；
+    ///     2.对冲请求之后返回ok响应，成功状态，由其胜出。
+    #[test]
+    fn hedge_settle_ok() {
+        let mock = MockCallback::default();
+        let cmd = mc_cmd(b"get k\r\n");
+        let mut origin = mock.ctx(cmd.copied());
+        origin.send();
+        let o = mock.pop().expect("origin");
+        let mut hedge = mock.ctx(cmd);
+        hedge.hedge_mode(origin.flag());
+        hedge.send();
+        let h = mock.pop().expect("hedge");
+
+        o.on_err(protocol::Error::Waiting);
+        assert!(origin.complete() && !origin.succeeded());
+        assert!(!hedge.complete() && !hedge.succeeded());
+        h.on_complete(Command::from_ok(MemGuard::from_vec(
+            b"VALUE k 0 1\r\nv\r\nEND\r\n".to_vec(),
+        )));
+        assert!(hedge.succeeded());
+        assert!(origin.take_response().is_none());
+        assert!(hedge.take_response().is_some());
+    }
+}
@@ -41,9 +41,9 @@ fn checkout_basic() {
     assert_eq!(8, size_of::<metrics::Metric>());
     assert_eq!(64, size_of::<metrics::Item>());
     assert_eq!(1, size_of::<Parser>());
-    assert_eq!(64, size_of::<BackendInner<Request>>());
+    assert_eq!(80, size_of::<BackendInner<Request>>());
     assert_eq!(40, size_of::<CheckedTopology>());
-    assert_eq!(192, size_of::<stream::StreamMetrics>());
+    assert_eq!(280, size_of::<stream::StreamMetrics>());
     assert_eq!(24, size_of::<sharding::hash::Hasher>());
 }
 
@@ -56,7 +56,7 @@ fn check_layout_rx_buffer() {
 #[ignore]
 #[test]
 fn check_callback_ctx() {
-    assert_eq!(192, size_of::<CallbackContext>());
+    assert_eq!(208, size_of::<CallbackContext>());
     //assert_eq!(16, size_of::<protocol::callback::Context>());
 }
 //#[ignore]
@@ -72,8 +72,8 @@ fn check_stream() {
 #[ignore]
 #[test]
 fn check_handler() {
-    assert_eq!(216, size_of::<Handler<'static>>());
-    assert_eq!(296, size_of::<Entry<Handler<'static>, rt::Timeout>>());
+    assert_eq!(248, size_of::<Handler<'static>>());
+    assert_eq!(328, size_of::<Entry<Handler<'static>, rt::Timeout>>());
 }
 
 #[ignore]
@@ -91,7 +91,7 @@ fn check_topology() {
 #[ignore]
 #[test]
 fn check_pipeline() {
-    assert_eq!(320, size_of::<CopyBidirectional>());
+    assert_eq!(328, size_of::<CopyBidirectional>());
     // 512字节对齐
     assert_eq!(360, size_of::<Entry<CopyBidirectional, DisableTimeout>>());
 }
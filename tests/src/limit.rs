@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod limit_test {
+    use std::sync::Arc;
+    use stream::Limiter;
+
+    /// 测试场景：连接数限制
+    /// 测试步骤：
+    ///     1.max为2时，第3个连接被拒绝；
+    ///     2.释放一个连接后，可以建立新的连接；
+    ///     3.max为0时不限制。
+    #[test]
+    fn limit_conns() {
+        let limiter = Arc::new(Limiter::default());
+        let c1 = limiter.conn(2).expect("c1");
+        let _c2 = limiter.conn(2).expect("c2");
+        assert!(limiter.conn(2).is_none());
+        assert_eq!(limiter.conns(), 2);
+
+        drop(c1);
+        assert_eq!(limiter.conns(), 1);
+        let _c3 = limiter.conn(2).expect("c3");
+        assert!(limiter.conn(2).is_none());
+
+        let unlimited: Vec<_> = (0..8)
+            .map(|_| limiter.conn(0).expect("unlimited"))
+            .collect();
+        assert_eq!(limiter.conns(), 10);
+        drop(unlimited);
+        assert_eq!(limiter.conns(), 2);
+    }
+
+    /// 测试场景：qps限制
+    /// 测试步骤：
+    ///     1.桶容量为1秒的qps，连续获取qps个令牌后被限流；
+    ///     2.等待1/qps秒以上，可以再获取令牌；
+    ///     3.qps为0时不限制。
+    #[test]
+    fn limit_qps() {
+        let limiter = Limiter::default();
+        let qps = 100;
+        let acquired = (0..qps * 2).filter(|_| limiter.acquire(qps)).count();
+        assert!(
+            acquired >= qps as usize && acquired <= qps as usize + 2,
+            "{}",
+            acquired
+        );
+        assert!(!limiter.acquire(qps));
+
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        assert!(limiter.acquire(qps));
+
+        assert!((0..10000).all(|_| limiter.acquire(0)));
+    }
+
+    // 被限流的请求由协议返回错误响应
+    #[test]
+    fn limit_rsp() {
+        use ds::MemGuard;
+        use protocol::{Flag, HashedCommand, Parser, Proto};
+        let req = HashedCommand::new(MemGuard::from_vec(b"get k\r\n".to_vec()), 0, Flag::new());
+        let redis = Parser::try_from("redis").expect("redis");
+        assert_eq!(redis.rate_limited(&req), b"-ERR rate limited\r\n");
+        let mc = Parser::try_from("mctext").expect("mctext");
+        assert_eq!(mc.rate_limited(&req), b"SERVER_ERROR rate limited\r\n");
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod load_test {
+    use ds::time::Duration;
+    use stream::Load;
+
+    /// 测试场景：后端负载按ewma rtt与在途请求数计算
+    /// 测试步骤：
+    ///     1.没有响应时rtt按1计，负载随在途请求数增加；
+    ///     2.首个响应直接作为rtt，之后按1/8的权重平滑；
+    ///     3.请求失败按惩罚耗时计入rtt。
+    #[test]
+    fn load_ewma() {
+        let load = Load::new();
+        assert_eq!(load.cost(), 1);
+        load.on_sent();
+        load.on_sent();
+        assert_eq!(load.inflight(), 2);
+        assert_eq!(load.cost(), 3);
+
+        load.on_response(Duration::from_micros(800));
+        assert_eq!(load.rtt_us(), 800);
+        assert_eq!(load.cost(), 1600);
+        load.on_response(Duration::from_micros(1600));
+        assert_eq!(load.rtt_us(), 900);
+        assert_eq!(load.inflight(), 0);
+        assert_eq!(load.cost(), 900);
+
+        // 请求失败，按500ms计入
+        load.on_sent();
+        load.on_err();
+        assert_eq!(load.rtt_us(), (900 * 7 + 500_000) / 8);
+        // 在途请求数不会小于0
+        load.on_response(Duration::from_micros(900));
+        assert_eq!(load.inflight(), 0);
+    }
+
+    /// 测试场景：按rtt的分布计算分位数，用于按分位数对冲
+    /// 测试步骤：
+    ///     1.样本数不足时返回0；
+    ///     2.按样本所在桶的上界返回分位数，误差不超过25%；
+    ///     3.请求失败不计入分布。
+    #[test]
+    fn load_percentile() {
+        let load = Load::new();
+        (0..31).for_each(|_| load.on_response(Duration::from_micros(1000)));
+        assert_eq!(load.percentile(99), 0);
+        load.on_response(Duration::from_micros(1000));
+        // 1000us在[896, 1024)的桶中
+        assert_eq!(load.percentile(50), 1024);
+
+        // 90个1ms、10个10ms
+        (0..58).for_each(|_| load.on_response(Duration::from_micros(1000)));
+        (0..10).for_each(|_| load.on_response(Duration::from_micros(10_000)));
+        assert_eq!(load.percentile(90), 1024);
+        let p99 = load.percentile(99);
+        assert!(p99 > 10_000 && p99 <= 12_500, "p99:{p99}");
+
+        (0..100).for_each(|_| load.on_err());
+        assert_eq!(load.percentile(99), p99);
+    }
+}
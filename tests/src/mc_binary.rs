@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod mc_binary_test {
+    use crate::proto_mock::*;
+    use protocol::memcache::Binary;
+    use protocol::{BufRead, Commander, Operation, Parser, Proto};
+    use sharding::hash::Hasher;
+
+    const OP_INCR: u8 = 0x05;
+    const OP_INCRQ: u8 = 0x15;
+    const OP_APPENDQ: u8 = 0x19;
+    const OP_SETQ: u8 = 0x11;
+    const OP_APPEND: u8 = 0x0e;
+    const OP_TOUCH: u8 = 0x1c;
+    const OP_GAT: u8 = 0x1d;
+    const OP_GATQ: u8 = 0x1e;
+    const OP_SET: u8 = 0x01;
+    const OP_DELQ: u8 = 0x14;
+    const OP_GET: u8 = 0x00;
+
+    fn parser() -> Parser {
+        Parser::try_from("mc").expect("mc")
+    }
+    // 构建mc二进制请求/响应包
+    fn packet(magic: u8, op: u8, status: u16, extra: &[u8], key: &str, val: &str) -> Vec<u8> {
+        let mut p = vec![magic, op];
+        p.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        p.push(extra.len() as u8);
+        p.push(0);
+        p.extend_from_slice(&status.to_be_bytes());
+        let body_len = extra.len() + key.len() + val.len();
+        p.extend_from_slice(&(body_len as u32).to_be_bytes());
+        p.extend_from_slice(&[0; 12]); // opaque + cas
+        p.extend_from_slice(extra);
+        p.extend_from_slice(key.as_bytes());
+        p.extend_from_slice(val.as_bytes());
+        p
+    }
+    fn req(op: u8, extra: &[u8], key: &str, val: &str) -> Vec<u8> {
+        packet(0x80, op, 0, extra, key, val)
+    }
+    fn rsp(op: u8, status: u16, extra: &[u8], val: &str) -> Vec<u8> {
+        packet(0x81, op, status, extra, "", val)
+    }
+
+    #[test]
+    fn parse_store_and_gat() {
+        let mut data = req(OP_INCR, &[0; 20], "counter", "");
+        data.extend(req(OP_APPEND, &[], "k", "tail"));
+        data.extend(req(OP_TOUCH, &[0, 0, 0, 60], "k", ""));
+        data.extend(req(OP_GATQ, &[0, 0, 0, 60], "k1", ""));
+        data.extend(req(OP_GAT, &[0, 0, 0, 60], "k2", ""));
+        let (mut s, _) = stream(&data);
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+
+        let reqs = reqs.0;
+        assert_eq!(reqs.len(), 5);
+        for (req, _) in &reqs[..3] {
+            assert_eq!(req.operation(), Operation::Store);
+            assert!(!req.sentonly());
+            assert!(!parser().can_retry_on_rsp_notok(req));
+        }
+        assert_eq!(reqs[0].0.key().as_string_lossy(), "counter");
+        // gat需要touch master，按gets处理；gatq转换为gat，以实现pipeline
+        for (req, _) in &reqs[3..] {
+            assert_eq!(req.operation(), Operation::Gets);
+            assert_eq!(req.op(), OP_GAT);
+            assert!(!req.sentonly());
+        }
+        assert!(!reqs[3].1);
+        assert!(reqs[4].1);
+    }
+
+    // 开启big value拒绝时，声明的value长度超过上限即拒绝，响应的status为value too large；
+    // 被拒绝请求的body读到后直接丢弃，之后的请求正常解析
+    #[test]
+    fn parse_reject_big() {
+        let too_large = [0x81, OP_SET, 0, 0, 0, 0, 0, 0x03];
+        let hasher = Hasher::from("crc32");
+        let mut data = req(OP_SET, &[0; 8], "k", "hello");
+        let mut big = req(OP_SET, &[0; 8], "k", "hello world");
+        big[12..16].copy_from_slice(&7u32.to_be_bytes()); // opaque
+        data.extend_from_slice(&big[..big.len() - 6]);
+        let (mut s, rx, _) = duplex(&data);
+        let mut reqs = MaxValue::new(10);
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("reject");
+        assert_eq!(reqs.0 .0.len(), 1);
+        assert_eq!(reqs.2.len(), 1);
+        let (rejected, len) = &reqs.2[0];
+        assert_eq!(*len, 11);
+        let rsp = parser().big_rejected(rejected);
+        assert_eq!(rsp.len(), 24);
+        assert_eq!(&rsp[..8], &too_large);
+        assert_eq!(&rsp[12..16], &7u32.to_be_bytes());
+
+        // body为extra + key + value，部分已经读到
+        assert_eq!(reqs.3, 8 + 1 + 11);
+        reqs.skip_from(&mut *s);
+        assert_eq!((reqs.3, s.len()), (6, 0));
+        let mut rest = big[big.len() - 6..].to_vec();
+        rest.extend(req(OP_SET, &[0; 8], "k2", "hi"));
+        recv(&mut s, &rx, &rest);
+        reqs.skip_from(&mut *s);
+        assert_eq!(reqs.3, 0);
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+        assert_eq!(reqs.0 .0.len(), 2);
+        assert_eq!(reqs.0 .0[1].0.key().as_string_lossy(), "k2");
+    }
+
+    // 连接数、qps超过限制时返回status为busy的响应，qps限制的响应需要复制请求的opaque
+    #[test]
+    fn limited() {
+        const BUSY: u8 = 0x85;
+        let conn = parser().conn_limited();
+        assert_eq!(conn.len(), 24);
+        assert_eq!(&conn[..8], &[0x81, 0x0a, 0, 0, 0, 0, 0, BUSY]);
+        assert!(conn[8..].iter().all(|b| *b == 0));
+
+        let mut data = req(OP_GET, &[], "k", "");
+        data[12..16].copy_from_slice(&9u32.to_be_bytes()); // opaque
+        let (mut s, _) = stream(&data);
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let limited = parser().rate_limited(&reqs.0[0].0);
+        assert_eq!(limited.len(), 24);
+        assert_eq!(&limited[..8], &[0x81, OP_GET, 0, 0, 0, 0, 0, BUSY]);
+        assert_eq!(&limited[12..16], &9u32.to_be_bytes());
+    }
+
+    #[test]
+    fn parse_store_response() {
+        let not_found = 0x0001;
+        let mut data = rsp(OP_INCR, not_found, &[], "");
+        data.extend(rsp(OP_INCR, 0, &[], "\0\0\0\0\0\0\0\x01"));
+        // cas的key不存在，不能更新其他layers
+        data.extend(rsp(OP_SET, not_found, &[], ""));
+        let (mut s, _) = stream(&data);
+        for ok in [true, true, false] {
+            let rsp = parser()
+                .parse_response(&mut *s)
+                .expect("rsp")
+                .expect("some");
+            assert_eq!(rsp.ok(), ok);
+        }
+    }
+
+    // incr等无法重放的写请求，回写时转换为deleteq清理其他layers
+    #[test]
+    fn write_back_invalidate() {
+        let (mut s, _) = stream(&req(OP_APPEND, &[], "key", "tail"));
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let (req, _) = reqs.0.pop().expect("req");
+        let hash = req.hash();
+        let mut ctx = Ctx::from(req);
+        let rsp = protocol::Command::from_ok(ds::MemGuard::from_vec(rsp(OP_APPEND, 0, &[], "")));
+        let wb = parser()
+            .build_writeback_request(&mut ctx, &rsp, 600)
+            .expect("write back");
+        assert_eq!(wb.op(), OP_DELQ);
+        assert_eq!(wb.key().as_string_lossy(), "key");
+        assert_eq!(wb.len(), 24 + "key".len());
+        assert_eq!(wb.hash(), hash);
+        assert!(wb.sentonly());
+        assert!(wb.operation().is_store());
+    }
+
+    // quiet的incr/append同样无法重放，回写时也转换为deleteq
+    #[test]
+    fn write_back_invalidate_quiet() {
+        let mut data = req(OP_INCRQ, &[0; 20], "counter", "");
+        data.extend(req(OP_APPENDQ, &[], "k", "tail"));
+        let (mut s, _) = stream(&data);
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        assert_eq!(reqs.0.len(), 2);
+        for ((req, _), op) in reqs.0.into_iter().zip([OP_INCRQ, OP_APPENDQ]) {
+            let key = req.key().as_string_lossy();
+            let mut ctx = Ctx::from(req);
+            let rsp = protocol::Command::from_ok(ds::MemGuard::from_vec(rsp(op, 0, &[], "")));
+            let wb = parser()
+                .build_writeback_request(&mut ctx, &rsp, 600)
+                .expect("write back");
+            assert_eq!(wb.op(), OP_DELQ);
+            assert_eq!(wb.key().as_string_lossy(), key);
+        }
+    }
+
+    // 带cas的set只在master上执行：不可try next，成功后以清除了cas的setq回写其他layers
+    #[test]
+    fn write_back_cas() {
+        let mut data = req(OP_SET, &[0; 8], "key", "val");
+        data[16..24].copy_from_slice(&9u64.to_be_bytes()); // cas
+        let (mut s, _) = stream(&data);
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let (req, _) = reqs.0.pop().expect("req");
+        assert!(!parser().can_retry_on_rsp_notok(&req));
+        let mut ctx = Ctx::from(req);
+        let rsp = protocol::Command::from_ok(ds::MemGuard::from_vec(rsp(OP_SET, 0, &[], "")));
+        assert!(parser()
+            .build_writeback_request(&mut ctx, &rsp, 600)
+            .is_none());
+        let wb = ctx.request();
+        assert_eq!(wb.op(), OP_SETQ);
+        assert!((16..24).all(|i| wb.at(i) == 0));
+        assert!(wb.sentonly());
+        // 回写的setq可以重试
+        assert!(parser().can_retry_on_rsp_notok(wb));
+    }
+}
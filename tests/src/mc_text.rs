@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod mc_text_test {
+    use std::pin::Pin;
+    use std::task::{Context, Waker};
+
+    use crate::proto_mock::*;
+    use ds::MemGuard;
+    use protocol::{BufRead, Command, Error, Operation, Parser, Proto};
+    use sharding::hash::{Hash, Hasher};
+    use tokio::io::AsyncWrite;
+
+    fn parser() -> Parser {
+        Parser::try_from("mctext").expect("mctext")
+    }
+
+    #[test]
+    fn parse_multi_get() {
+        let (mut s, _) = stream(b"get k1 k2 k3\r\nget k4\r\n");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+
+        let reqs = reqs.0;
+        assert_eq!(reqs.len(), 4);
+        let keys = ["k1", "k2", "k3", "k4"];
+        let lasts = [false, false, true, true];
+        for (i, (req, last)) in reqs.iter().enumerate() {
+            assert_eq!(string(req), format!("get {}\r\n", keys[i]));
+            assert_eq!(*last, lasts[i]);
+            assert_eq!(req.hash(), Hasher::from("crc32").hash(&keys[i].as_bytes()));
+        }
+        assert_eq!(reqs[0].0.operation(), Operation::MGet);
+        assert_eq!(reqs[3].0.operation(), Operation::Get);
+    }
+
+    #[test]
+    fn parse_store() {
+        let (mut s, _) =
+            stream(b"set k 1 0 5 noreply\r\nhello\r\ncas k 0 0 2 10\r\nhi\r\ndelete k\r\nset k 0 0 5\r\nhel");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        // 不完整的请求保留在buffer中
+        assert_eq!(s.len(), "set k 0 0 5\r\nhel".len());
+
+        let reqs = reqs.0;
+        assert_eq!(reqs.len(), 3);
+        assert_eq!(string(&reqs[0].0), "set k 1 0 5 noreply\r\nhello\r\n");
+        assert!(reqs[0].0.sentonly());
+        assert!(reqs[0].0.operation().is_store());
+        assert_eq!(string(&reqs[1].0), "cas k 0 0 2 10\r\nhi\r\n");
+        assert!(!reqs[1].0.sentonly());
+        assert_eq!(string(&reqs[2].0), "delete k\r\n");
+        assert!(parser().can_retry_on_rsp_notok(&reqs[0].0));
+        assert!(!parser().can_retry_on_rsp_notok(&reqs[1].0));
+    }
+
+    #[test]
+    fn sample_key() {
+        let (mut s, _) = stream(b"get k1 k2\r\nset k3 0 0 2\r\nhi\r\nversion\r\n");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let keys: Vec<_> = (reqs.0.iter())
+            .map(|(req, _)| parser().sample_key(req).map(|k| k.as_string_lossy()))
+            .collect();
+        let expected = [Some("k1"), Some("k2"), Some("k3"), None];
+        assert_eq!(keys, expected.map(|k| k.map(String::from)));
+        let lens: Vec<_> = reqs
+            .0
+            .iter()
+            .map(|(req, _)| parser().key_len(req))
+            .collect();
+        assert_eq!(lens, [2, 2, 2, 0]);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let hasher = Hasher::from("crc32");
+        for req in [
+            "flush_all\r\n",
+            "get\r\n",
+            "set k 0 0 x\r\nhello\r\n",
+            "delete k 0 1\r\n",
+            "set k 0 0 99999999999999999999\r\n",
+        ] {
+            let (mut s, _) = stream(req.as_bytes());
+            let mut reqs = Reqs::default();
+            let e = parser().parse_request(&mut *s, &hasher, &mut reqs);
+            assert!(matches!(e, Err(Error::FlushOnClose(_))), "{}", req);
+        }
+        // 超过item大小或溢出的value长度，不再申请内存，直接返回bad data chunk
+        for req in [
+            "set k 0 0 1048577\r\n",
+            "set k 0 0 18446744073709551615\r\n",
+        ] {
+            let (mut s, _) = stream(req.as_bytes());
+            let mut reqs = Reqs::default();
+            match parser().parse_request(&mut *s, &hasher, &mut reqs) {
+                Err(Error::FlushOnClose(e)) => {
+                    assert_eq!(&e[..], b"CLIENT_ERROR bad data chunk\r\n")
+                }
+                e => panic!("{} => {:?}", req, e),
+            }
+        }
+        // 请求行超长：没有行结束符、或者有行结束符但超过上限
+        let long = format!("get {}", "k".repeat(64 * 1024));
+        for req in [long.clone(), format!("{}\r\n", long)] {
+            let (mut s, _) = stream(req.as_bytes());
+            let mut reqs = Reqs::default();
+            match parser().parse_request(&mut *s, &hasher, &mut reqs) {
+                Err(Error::FlushOnClose(e)) => {
+                    assert_eq!(&e[..], b"CLIENT_ERROR line too long\r\n")
+                }
+                e => panic!("{:?}", e),
+            }
+        }
+        let (mut s, _) = stream(b"get k1 k2");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("incomplete");
+        assert!(reqs.0.is_empty());
+        // 开启big value拒绝时，声明的value长度超过上限即拒绝，不等待数据；
+        // value读到后直接丢弃，之后的请求正常解析，noreply的请求不响应
+        let (mut s, rx, _) = duplex(b"set k 0 0 5\r\nhello\r\nset k 0 0 11\r\nhello");
+        let mut reqs = MaxValue::new(10);
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("reject");
+        assert_eq!(reqs.0 .0.len(), 1);
+        let (rejected, len) = &reqs.2[0];
+        assert_eq!(*len, 11);
+        assert!(!rejected.noforward() && !rejected.sentonly());
+        assert_eq!(
+            parser().big_rejected(rejected),
+            b"SERVER_ERROR object too large for cache\r\n"
+        );
+        assert_eq!(reqs.3, 13);
+        recv(&mut s, &rx, b" world\r\nadd k 0 0 11 noreply\r\n");
+        reqs.skip_from(&mut *s);
+        assert_eq!(reqs.3, 0);
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("reject");
+        assert_eq!(reqs.2.len(), 2);
+        assert!(reqs.2[1].0.noforward() && reqs.2[1].0.sentonly());
+        recv(&mut s, &rx, b"hello world\r\nget k\r\n");
+        reqs.skip_from(&mut *s);
+        parser()
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+        assert_eq!(reqs.0 .0.len(), 2);
+        assert_eq!(reqs.0 .0[1].0.operation(), Operation::Get);
+    }
+
+    #[test]
+    fn parse_response() {
+        let (mut s, _) =
+            stream(b"VALUE k1 0 5\r\nhello\r\nEND\r\nEND\r\nSTORED\r\nNOT_STORED\r\n12\r\nVALUE k2 0 5\r\nwor");
+        let rsps = [
+            ("VALUE k1 0 5\r\nhello\r\nEND\r\n", true),
+            ("END\r\n", false),
+            ("STORED\r\n", true),
+            ("NOT_STORED\r\n", false),
+            ("12\r\n", true),
+        ];
+        for (data, ok) in rsps {
+            let rsp = parser()
+                .parse_response(&mut *s)
+                .expect("rsp")
+                .expect("some");
+            assert_eq!(string(&rsp), data);
+            assert_eq!(rsp.ok(), ok);
+        }
+        // 不完整的响应
+        assert!(parser().parse_response(&mut *s).expect("rsp").is_none());
+        assert_eq!(s.len(), "VALUE k2 0 5\r\nwor".len());
+    }
+
+    // multi-get的响应：非最后一个key去掉END，最后一个key统一返回END
+    #[test]
+    fn write_multi_get_response() {
+        let (mut s, tx) = stream(b"get k1 k2 k3 k4 k5\r\n");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        // 非最后一个key的错误响应需要返回，未命中及没有响应时什么都不返回
+        let rsps = [
+            Some("VALUE k1 0 2\r\nv1\r\nEND\r\n"),
+            None,
+            Some("SERVER_ERROR out of memory\r\n"),
+            Some("END\r\n"),
+            Some("END\r\n"),
+        ];
+        for ((req, _), rsp) in reqs.0.into_iter().zip(rsps) {
+            let mut ctx = Ctx::from(req);
+            let mut rsp = rsp.map(|r| {
+                Command::from(
+                    r.starts_with("VALUE"),
+                    MemGuard::from_vec(r.as_bytes().to_vec()),
+                )
+            });
+            parser()
+                .write_response(&mut ctx, rsp.as_mut(), &mut *s)
+                .expect("write");
+        }
+        let mut cx = Context::from_waker(Waker::noop());
+        let _ = Pin::new(&mut *s).poll_flush(&mut cx);
+        assert_eq!(
+            String::from_utf8(tx.lock().unwrap().clone()).unwrap(),
+            "VALUE k1 0 2\r\nv1\r\nSERVER_ERROR out of memory\r\nEND\r\n"
+        );
+    }
+
+    #[test]
+    fn write_back_request() {
+        let (mut s, _) = stream(b"gets k1\r\ncas k1 0 0 2 10\r\nv2\r\n");
+        let mut reqs = Reqs::default();
+        parser()
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let mut reqs = reqs.0.into_iter();
+
+        let (req, _) = reqs.next().unwrap();
+        let rsp = Command::from_ok(MemGuard::from_vec(
+            b"VALUE k1 3 2 10\r\nv1\r\nEND\r\n".to_vec(),
+        ));
+        let mut ctx = Ctx::from(req);
+        let wb = parser()
+            .build_writeback_request(&mut ctx, &rsp, 600)
+            .expect("write back");
+        assert_eq!(string(&wb), "set k1 3 600 2 noreply\r\nv1\r\n");
+        assert!(wb.sentonly());
+
+        let (req, _) = reqs.next().unwrap();
+        let mut ctx = Ctx::from(req);
+        let rsp = Command::from_ok(MemGuard::from_vec(b"STORED\r\n".to_vec()));
+        let wb = parser()
+            .build_writeback_request(&mut ctx, &rsp, 600)
+            .expect("write back");
+        assert_eq!(string(&wb), "set k1 0 0 2 noreply\r\nv2\r\n");
+        assert!(wb.sentonly());
+    }
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod mirror_test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::proto_mock::*;
+    use ds::{AtomicWaker, MemGuard};
+    use endpoint::mirror::{Mirror, MirrorConfig};
+    use endpoint::{Endpoint, Timeout};
+    use protocol::callback::{Callback, CallbackContext, CallbackPtr};
+    use protocol::{
+        request, Command, Error, HashedCommand, Parser, Proto, Request, ResOption, Resource,
+    };
+    use sharding::hash::Hasher;
+
+    // 记录各后端收到的请求：(后端地址, 是否是镜像请求)
+    static SENT: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+    #[derive(Clone)]
+    struct MBackend {
+        addr: String,
+    }
+
+    impl Endpoint for MBackend {
+        type Item = request::Request;
+        fn send(&self, req: Self::Item) {
+            SENT.lock().unwrap().push((self.addr.clone(), req.mirror()));
+            req.on_err(Error::Timeout(100));
+        }
+        fn available(&self) -> bool {
+            true
+        }
+        fn addr(&self) -> &str {
+            &self.addr
+        }
+        fn build_o<P: protocol::Protocol>(
+            addr: &str,
+            _p: P,
+            _r: Resource,
+            _service: &str,
+            _to: Timeout,
+            _o: ResOption,
+        ) -> Self {
+            Self {
+                addr: addr.to_string(),
+            }
+        }
+    }
+
+    fn cmd(data: &[u8]) -> HashedCommand {
+        let (mut s, _) = stream(data);
+        let mut reqs = Reqs::default();
+        Parser::try_from("mctext")
+            .expect("mctext")
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let req = reqs.0.pop().expect("req").0;
+        req.copied()
+    }
+
+    fn mirror(backends: &[&str], percent: u8, writes_only: bool) -> Mirror<MBackend> {
+        let cfg = MirrorConfig {
+            backends: backends.iter().map(|b| b.to_string()).collect(),
+            percent,
+            writes_only,
+            ..Default::default()
+        };
+        let parser = Parser::try_from("mctext").expect("mctext");
+        let mut mirror = Mirror::from(Resource::Memcache);
+        let to = Timeout::new(100);
+        mirror.update(
+            "mirror_test",
+            &parser,
+            &cfg,
+            "modula",
+            to,
+            Default::default(),
+        );
+        mirror
+    }
+
+    /// 测试场景：镜像请求
+    /// 测试步骤：
+    ///     1.复制的请求不再引用client的buffer，client buffer可以回收；
+    ///     2.镜像请求以异步方式发送，响应被丢弃。
+    #[test]
+    fn mirror_request() {
+        let (mut s, _) = stream(b"set k 0 0 5\r\nhello\r\n");
+        let mut reqs = Reqs::default();
+        Parser::try_from("mctext")
+            .expect("mctext")
+            .parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        let req = reqs.0.pop().expect("req").0;
+        let copied = req.copied();
+        assert_eq!(string(&copied), "set k 0 0 5\r\nhello\r\n");
+        assert_eq!(copied.hash(), req.hash());
+        assert_eq!(copied.op_code(), req.op_code());
+        assert!(copied.operation().is_store());
+        drop(req);
+        drop(s);
+
+        let mirrored = Arc::new(Mutex::new(Vec::new()));
+        let sent = mirrored.clone();
+        let cb: CallbackPtr = Callback::new(Box::new(move |req| {
+            sent.lock().unwrap().push(req.mirror());
+            let rsp = MemGuard::from_vec(b"STORED\r\n".to_vec());
+            req.on_complete(Command::from_ok(rsp));
+        }))
+        .into();
+        let waker = Arc::new(AtomicWaker::default());
+        let mut ctx = CallbackContext::new(copied, &waker, cb, true, true, false);
+        ctx.mirror_mode();
+        assert!(ctx.is_mirror());
+        assert!(!ctx.async_done());
+        ctx.send();
+        assert!(ctx.async_done());
+        assert!(ctx.response().is_none());
+        assert_eq!(*mirrored.lock().unwrap(), [true]);
+    }
+
+    /// 测试场景：按比例及请求类型采样镜像请求
+    /// 测试步骤：
+    ///     1.未配置镜像集群、percent为0时，不镜像；
+    ///     2.percent为30时，连续100个请求中恰好30个被镜像；
+    ///     3.writes_only时，只镜像写请求。
+    #[test]
+    fn mirror_sample() {
+        let get = cmd(b"get k\r\n");
+        let set = cmd(b"set k 0 0 1\r\nv\r\n");
+        let sampled =
+            |m: &Mirror<MBackend>, req: &HashedCommand| (0..100).filter(|_| m.sample(req)).count();
+
+        assert_eq!(sampled(&mirror(&[], 100, false), &set), 0);
+        assert_eq!(sampled(&mirror(&["a:1"], 0, false), &set), 0);
+
+        let m = mirror(&["a:1"], 100, false);
+        assert_eq!(sampled(&m, &get), 100);
+        let m = mirror(&["a:1"], 30, false);
+        assert_eq!(sampled(&m, &get), 30);
+        assert_eq!(sampled(&m, &set), 30);
+
+        let m = mirror(&["a:1"], 100, true);
+        assert_eq!(sampled(&m, &get), 0);
+        assert_eq!(sampled(&m, &set), 100);
+    }
+
+    /// 测试场景：镜像请求按镜像集群的分布路由
+    /// 测试步骤：
+    ///     1.镜像请求只发送到镜像集群，分片配置为"master,slave"时只发送到master；
+    ///     2.同一个key始终路由到同一个分片，不同key分散到各分片；
+    ///     3.镜像请求失败（包括未配置镜像集群）后标记为failed，用于单独统计镜像错误。
+    #[test]
+    fn mirror_route() {
+        let m = Arc::new(mirror(&["a:1,a:2", "b:1,b:2"], 100, false));
+        let sender = m.clone();
+        let cb: CallbackPtr = Callback::new(Box::new(move |req| sender.send(req))).into();
+        let waker = Arc::new(AtomicWaker::default());
+        let send = |key: usize| {
+            let data = format!("set k{} 0 0 1\r\nv\r\n", key);
+            let mut ctx =
+                CallbackContext::new(cmd(data.as_bytes()), &waker, cb.clone(), true, true, false);
+            ctx.mirror_mode();
+            ctx.send();
+            assert!(ctx.async_done() && ctx.failed());
+            SENT.lock().unwrap().pop().expect("sent")
+        };
+
+        let mut shards = Vec::new();
+        for key in 0..16 {
+            let (addr, mirrored) = send(key);
+            assert!(mirrored);
+            assert!(addr == "a:1" || addr == "b:1", "{}", addr);
+            assert_eq!(send(key).0, addr);
+            shards.push(addr);
+        }
+        assert!(shards.contains(&"a:1".to_string()) && shards.contains(&"b:1".to_string()));
+
+        let empty: Arc<Mirror<MBackend>> = Arc::new(mirror(&[], 100, false));
+        let cb: CallbackPtr = Callback::new(Box::new(move |req| empty.send(req))).into();
+        let mut ctx =
+            CallbackContext::new(cmd(b"set k 0 0 1\r\nv\r\n"), &waker, cb, true, true, false);
+        ctx.mirror_mode();
+        ctx.send();
+        assert!(ctx.async_done() && ctx.failed());
+        assert!(SENT.lock().unwrap().is_empty());
+    }
+}
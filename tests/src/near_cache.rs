@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod near_cache_test {
+    use crate::proto_mock::*;
+    use endpoint::cacheservice::{hotkey::HotKeys, near::NearCache};
+    use protocol::{Parser, Proto};
+    use sharding::hash::Hasher;
+    use std::time::Duration;
+
+    /// 测试场景：near cache的读写及删除
+    /// 测试步骤：
+    ///     1.未开启时不缓存；
+    ///     2.相同指令才能读到缓存，删除后读不到；
+    ///     3.已缓存且未过期的key，不会被覆盖。
+    #[test]
+    fn near_cache_get_put() {
+        let near = NearCache::default();
+        assert!(!near.enabled());
+
+        near.config(2, 1000);
+        assert!(near.enabled());
+        near.put(0, b"k1".to_vec(), b"v1".to_vec());
+        assert_eq!(near.get(0, b"k1"), Some(b"v1".to_vec()));
+        assert_eq!(near.get(1, b"k1"), None);
+        assert_eq!(near.get(0, b"k2"), None);
+
+        near.put(0, b"k1".to_vec(), b"v2".to_vec());
+        assert_eq!(near.get(0, b"k1"), Some(b"v1".to_vec()));
+
+        near.remove(b"k1");
+        assert_eq!(near.get(0, b"k1"), None);
+
+        near.put(0, b"k1".to_vec(), b"v1".to_vec());
+        near.config(0, 1000);
+        assert!(!near.enabled());
+        near.config(2, 1000);
+        assert_eq!(near.get(0, b"k1"), None);
+    }
+
+    /// 测试场景：near cache的淘汰
+    /// 测试步骤：
+    ///     1.超过容量时淘汰最久未访问的key；
+    ///     2.过期的key读不到，且可以重新写入。
+    #[test]
+    fn near_cache_evict() {
+        let near = NearCache::default();
+        near.config(2, 50);
+        near.put(0, b"k1".to_vec(), b"v1".to_vec());
+        near.put(0, b"k2".to_vec(), b"v2".to_vec());
+        // 访问k1后，k2是最久未访问的
+        assert!(near.get(0, b"k1").is_some());
+        near.put(0, b"k3".to_vec(), b"v3".to_vec());
+        assert!(near.get(0, b"k1").is_some());
+        assert!(near.get(0, b"k2").is_none());
+        assert!(near.get(0, b"k3").is_some());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(near.get(0, b"k1").is_none());
+        near.put(0, b"k1".to_vec(), b"v4".to_vec());
+        assert_eq!(near.get(0, b"k1"), Some(b"v4".to_vec()));
+    }
+
+    // mc二进制协议的get请求
+    fn binary_get(key: &str, opaque: u32) -> Vec<u8> {
+        let mut p = vec![0x80, 0x00];
+        p.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        p.extend_from_slice(&[0; 4]); // extra len, data type, vbucket
+        p.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        p.extend_from_slice(&opaque.to_be_bytes());
+        p.extend_from_slice(&[0; 8]); // cas
+        p.extend_from_slice(key.as_bytes());
+        p
+    }
+
+    /// 测试场景：mc二进制协议get请求的hot key统计及near cache复用
+    /// 测试步骤：
+    ///     1.按请求中的key采样，窗口结束时采样次数足够多的key为hot key；
+    ///     2.get的响应可以复用，复用时替换为当前请求的opaque。
+    #[test]
+    fn hot_key_binary_get() {
+        let mc = Parser::try_from("mc").expect("mc");
+        let mut data: Vec<u8> = (0..2048).flat_map(|i| binary_get("hot", i)).collect();
+        data.extend((0..64).flat_map(|i| binary_get("cold", i)));
+        let (mut s, _) = stream(&data);
+        let mut reqs = Reqs::default();
+        mc.parse_request(&mut *s, &Hasher::from("crc32"), &mut reqs)
+            .expect("parse");
+        assert_eq!(reqs.0.len(), 2048 + 64);
+
+        metrics::tests::init_metrics_onlyfor_test();
+        let hotkeys = HotKeys::new(Duration::from_millis(50));
+        let (hot, cold) = reqs.0.split_at(2048);
+        for (req, _) in hot {
+            hotkeys.sample("hotkey_test", &mc.sample_key(req).expect("key"));
+        }
+        // 窗口结束后的采样触发统计
+        std::thread::sleep(Duration::from_millis(60));
+        for (req, _) in cold {
+            hotkeys.sample("hotkey_test", &mc.sample_key(req).expect("key"));
+        }
+        let (hot, cold) = (&hot[0].0, &cold[0].0);
+        assert!(hotkeys.is_hot(&mc.sample_key(hot).expect("hot")));
+        assert!(!hotkeys.is_hot(&mc.sample_key(cold).expect("cold")));
+
+        assert!(mc.rsp_reusable(hot));
+        let mut rsp = binary_get("hot", 1);
+        rsp[0] = 0x81;
+        mc.reuse_rsp(cold, &mut rsp);
+        assert_eq!(&rsp[12..16], &0u32.to_be_bytes());
+        mc.reuse_rsp(&reqs.0[7].0, &mut rsp);
+        assert_eq!(&rsp[12..16], &7u32.to_be_bytes());
+    }
+}
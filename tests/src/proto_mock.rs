@@ -0,0 +1,312 @@
+// 协议测试用的mock：模拟client连接、请求处理、metric及Commander
+use std::cell::{RefCell, UnsafeCell};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use ds::MemGuard;
+use protocol::{
+    AsyncBufRead, Commander, HashedCommand, Metric, MetricName, RequestProcessor, Writer,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub(crate) type Buf = Arc<Mutex<Vec<u8>>>;
+
+// 读取rx中的数据，写入的数据保存在tx中
+#[derive(Debug, Default)]
+pub(crate) struct Mock {
+    rx: Buf,
+    tx: Buf,
+}
+impl AsyncRead for Mock {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let mut rx = self.rx.lock().unwrap();
+        if rx.is_empty() {
+            return Poll::Pending;
+        }
+        let n = buf.remaining().min(rx.len());
+        buf.put_slice(&rx[..n]);
+        rx.drain(..n);
+        Poll::Ready(Ok(()))
+    }
+}
+impl AsyncWrite for Mock {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.tx.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// 释放前先gc，确保所有的MemGuard都已经回收
+pub(crate) struct TestStream(rt::Stream<Mock>);
+impl Drop for TestStream {
+    fn drop(&mut self) {
+        assert!(self.0.try_gc());
+    }
+}
+impl std::ops::Deref for TestStream {
+    type Target = rt::Stream<Mock>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl std::ops::DerefMut for TestStream {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+pub(crate) fn stream(data: &[u8]) -> (TestStream, Buf) {
+    let (s, _, tx) = duplex(data);
+    (s, tx)
+}
+
+// 返回rx，用于模拟client分多次发送数据
+pub(crate) fn duplex(data: &[u8]) -> (TestStream, Buf, Buf) {
+    let rx = Arc::new(Mutex::new(Vec::new()));
+    let tx = Arc::new(Mutex::new(Vec::new()));
+    let mock = Mock {
+        rx: rx.clone(),
+        tx: tx.clone(),
+    };
+    let mut s = TestStream(rt::Stream::from(mock));
+    recv(&mut s, &rx, data);
+    (s, rx, tx)
+}
+
+pub(crate) fn recv(s: &mut TestStream, rx: &Buf, data: &[u8]) {
+    rx.lock().unwrap().extend_from_slice(data);
+    let mut cx = Context::from_waker(Waker::noop());
+    // 没有数据时，poll_recv会返回Pending
+    assert_eq!(s.poll_recv(&mut cx).is_ready(), !data.is_empty());
+}
+
+// 第二个字段模拟client是否处于订阅模式，第三个字段模拟client是否协商为RESP3
+#[derive(Default)]
+pub(crate) struct Reqs(
+    pub(crate) Vec<(HashedCommand, bool)>,
+    pub(crate) bool,
+    pub(crate) bool,
+);
+impl RequestProcessor for Reqs {
+    fn process(&mut self, req: HashedCommand, last: bool) {
+        self.0.push((req, last));
+    }
+    // 模拟两个分片，按hash的奇偶分片
+    fn shard_idx(&self, hash: i64) -> usize {
+        (hash & 1) as usize
+    }
+    fn subscribing(&self) -> bool {
+        self.1
+    }
+    fn shards(&self) -> usize {
+        2
+    }
+    fn resp3(&self) -> bool {
+        self.2
+    }
+    fn set_resp3(&mut self, resp3: bool) {
+        self.2 = resp3;
+    }
+}
+
+// 模拟开启big value拒绝的client，第二个字段为value长度的上限，
+// 第三个字段为被拒绝的请求及声明的value长度，第四个字段为需要跳过的数据长度
+#[derive(Default)]
+pub(crate) struct MaxValue(
+    pub(crate) Reqs,
+    pub(crate) usize,
+    pub(crate) Vec<(HashedCommand, usize)>,
+    pub(crate) usize,
+);
+impl MaxValue {
+    pub(crate) fn new(max: usize) -> Self {
+        Self(Reqs::default(), max, Vec::new(), 0)
+    }
+    // 与pipeline一致，先丢弃被拒绝请求的数据，再继续解析
+    pub(crate) fn skip_from<S: protocol::Stream>(&mut self, s: &mut S) {
+        let n = self.3.min(s.len());
+        s.ignore(n);
+        self.3 -= n;
+    }
+}
+impl RequestProcessor for MaxValue {
+    fn process(&mut self, req: HashedCommand, last: bool) {
+        self.0.process(req, last);
+    }
+    fn shard_idx(&self, hash: i64) -> usize {
+        self.0.shard_idx(hash)
+    }
+    fn max_value_len(&self) -> usize {
+        self.1
+    }
+    fn reject_big(&mut self, req: HashedCommand, len: usize) {
+        self.2.push((req, len));
+    }
+    fn skip(&mut self, n: usize) {
+        self.3 += n;
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Item(i64);
+impl std::ops::AddAssign<i64> for Item {
+    fn add_assign(&mut self, v: i64) {
+        self.0 += v;
+    }
+}
+impl std::ops::AddAssign<bool> for Item {
+    fn add_assign(&mut self, v: bool) {
+        self.0 += v as i64;
+    }
+}
+#[derive(Default)]
+pub(crate) struct Metrics(UnsafeCell<Item>);
+impl Metric<Item> for Metrics {
+    fn get(&self, _name: MetricName) -> &mut Item {
+        unsafe { &mut *self.0.get() }
+    }
+}
+pub(crate) struct Ctx {
+    req: HashedCommand,
+    metrics: Metrics,
+    pub(crate) merged: Vec<u8>,
+}
+impl From<HashedCommand> for Ctx {
+    fn from(req: HashedCommand) -> Self {
+        Self {
+            req,
+            metrics: Metrics::default(),
+            merged: Vec::new(),
+        }
+    }
+}
+impl Commander<Metrics, Item> for Ctx {
+    fn request_mut(&mut self) -> &mut HashedCommand {
+        &mut self.req
+    }
+    fn request(&self) -> &HashedCommand {
+        &self.req
+    }
+    fn request_shard(&self) -> usize {
+        0
+    }
+    fn metric(&self) -> &Metrics {
+        &self.metrics
+    }
+    fn ctx(&self) -> u64 {
+        0
+    }
+    fn merged(&mut self) -> &mut Vec<u8> {
+        &mut self.merged
+    }
+}
+
+pub(crate) fn string(data: &MemGuard) -> String {
+    data.as_string_lossy()
+}
+
+// 解析一个mc文本协议的请求，复制后不再引用client的buffer
+pub(crate) fn mc_cmd(data: &[u8]) -> HashedCommand {
+    use protocol::{Parser, Proto};
+    let (mut s, _) = stream(data);
+    let mut reqs = Reqs::default();
+    Parser::try_from("mctext")
+        .expect("mctext")
+        .parse_request(&mut *s, &sharding::hash::Hasher::from("crc32"), &mut reqs)
+        .expect("parse");
+    let req = reqs.0.pop().expect("req").0;
+    req.copied()
+}
+
+thread_local! {
+    static BACKEND_SENT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+// 模拟的后端实例：记录收到请求的后端地址，请求以超时结束，用于测试topo的路由及重试
+#[derive(Clone)]
+pub(crate) struct MockBackend {
+    addr: String,
+}
+impl endpoint::Endpoint for MockBackend {
+    type Item = protocol::request::Request;
+    fn send(&self, req: Self::Item) {
+        use protocol::Request;
+        BACKEND_SENT.with(|sent| sent.borrow_mut().push(self.addr.clone()));
+        req.on_err(protocol::Error::Timeout(100));
+    }
+    fn available(&self) -> bool {
+        true
+    }
+    fn addr(&self) -> &str {
+        &self.addr
+    }
+    fn build_o<P: protocol::Protocol>(
+        addr: &str,
+        _p: P,
+        _r: protocol::Resource,
+        _service: &str,
+        _to: endpoint::Timeout,
+        _o: protocol::ResOption,
+    ) -> Self {
+        Self {
+            addr: addr.to_string(),
+        }
+    }
+}
+// 当前线程的MockBackend收到请求的后端地址
+pub(crate) fn backend_sent() -> Vec<String> {
+    BACKEND_SENT.with(|sent| sent.take())
+}
+
+// 不经过topo直接驱动CallbackContext：请求发送后暂存，由测试取出后模拟响应或失败
+pub(crate) struct MockCallback {
+    cb: protocol::callback::CallbackPtr,
+    waker: Arc<ds::AtomicWaker>,
+    sent: Arc<Mutex<Vec<protocol::request::Request>>>,
+}
+impl Default for MockCallback {
+    fn default() -> Self {
+        let sent: Arc<Mutex<Vec<protocol::request::Request>>> = Default::default();
+        let holder = sent.clone();
+        let cb = protocol::callback::Callback::new(Box::new(move |req| {
+            holder.lock().unwrap().push(req);
+        }));
+        Self {
+            cb: cb.into(),
+            waker: Default::default(),
+            sent,
+        }
+    }
+}
+impl MockCallback {
+    // ctx引用了waker，需要在MockCallback之前释放
+    pub(crate) fn ctx(&self, cmd: HashedCommand) -> protocol::callback::CallbackContext {
+        protocol::callback::CallbackContext::new(
+            cmd,
+            &self.waker,
+            self.cb.clone(),
+            true,
+            true,
+            true,
+        )
+    }
+    // 取出最后发送的请求。先释放锁，重试时callback会再次加锁
+    pub(crate) fn pop(&self) -> Option<protocol::request::Request> {
+        self.sent.lock().unwrap().pop()
+    }
+}
@@ -2,8 +2,19 @@
 mod redis_test {
     use std::collections::{HashMap, HashSet};
 
-    use ds::MemGuard;
-    use protocol::Flag;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Waker};
+
+    use crate::proto_mock::*;
+    use ds::{AtomicWaker, MemGuard};
+    use protocol::callback::{Callback, CallbackContext, CallbackPtr};
+    use protocol::{
+        request, BufRead, Command, Dedicated, Error, Flag, HandShake, HashedCommand, Parser, Proto,
+        RedisFlager, Request, ResOption, Subscriptions,
+    };
+    use sharding::hash::Hasher;
+    use tokio::io::AsyncWrite;
     #[test]
     fn test_hosts_eq() {
         let hosts1 = create_hosts();
@@ -42,7 +53,7 @@ mod redis_test {
 
     #[test]
     fn redis_flag() {
-        use protocol::{Bit, HashedCommand, RedisFlager};
+        use protocol::Bit;
         let mut cmd = HashedCommand::new(MemGuard::from_vec(vec![1u8]), 1, Flag::new());
         assert!(!cmd.master_only());
         cmd.set_master_only();
@@ -57,4 +68,1173 @@ mod redis_test {
         //对前面设置的flag没有影响
         assert!(cmd.master_only());
     }
+
+    fn auth_option(username: &str, token: &str) -> ResOption {
+        ResOption {
+            token: token.to_string(),
+            username: username.to_string(),
+            ..Default::default()
+        }
+    }
+    fn flushed(s: &mut TestStream, tx: &Arc<Mutex<Vec<u8>>>) -> String {
+        let mut cx = Context::from_waker(Waker::noop());
+        let _ = Pin::new(&mut **s).poll_flush(&mut cx);
+        String::from_utf8(tx.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn redis_auth() {
+        let redis = Parser::try_from("redis").expect("redis");
+        assert!(redis.config().need_auth);
+
+        // 未配置密码，不发送AUTH
+        let (mut s, tx) = stream(b"");
+        let mut option = auth_option("", "");
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Success)
+        ));
+        assert_eq!(flushed(&mut s, &tx), "");
+
+        let (mut s, tx) = stream(b"+OK\r\n");
+        let mut option = auth_option("", "pwd");
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Continue)
+        ));
+        assert_eq!(flushed(&mut s, &tx), "*2\r\n$4\r\nAUTH\r\n$3\r\npwd\r\n");
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Success)
+        ));
+        assert_eq!(s.len(), 0);
+    }
+
+    #[test]
+    fn redis_auth_acl_failed() {
+        let redis = Parser::try_from("phantom").expect("phantom");
+        let (mut s, tx) = stream(b"-WRONGPASS invalid username-password pair\r\n");
+        let mut option = auth_option("user", "pwd");
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Continue)
+        ));
+        assert_eq!(
+            flushed(&mut s, &tx),
+            "*3\r\n$4\r\nAUTH\r\n$4\r\nuser\r\n$3\r\npwd\r\n"
+        );
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Failed)
+        ));
+        assert_eq!(s.len(), 0);
+    }
+
+    #[test]
+    fn redis_select_db() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, tx) = stream(b"+OK\r\n+OK\r\n");
+        let mut option = ResOption {
+            token: "pwd".to_string(),
+            db: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Continue)
+        ));
+        assert_eq!(
+            flushed(&mut s, &tx),
+            "*2\r\n$4\r\nAUTH\r\n$3\r\npwd\r\n*2\r\n$6\r\nSELECT\r\n$1\r\n3\r\n"
+        );
+        assert!(matches!(
+            redis.handshake(&mut *s, &mut option),
+            Ok(HandShake::Success)
+        ));
+        assert_eq!(s.len(), 0);
+        assert_eq!(*s.context(), [0u8; 16]);
+    }
+
+    fn cmd(args: &[&str]) -> String {
+        let mut c = format!("*{}\r\n", args.len());
+        for arg in args {
+            c += &format!("${}\r\n{}\r\n", arg.len(), arg);
+        }
+        c
+    }
+    // 解析请求，本地响应的请求直接写入响应，返回需要转发的请求
+    fn parse_txn(redis: &Parser, s: &mut TestStream, rsp: &mut String) -> Vec<HashedCommand> {
+        parse_txn_max(redis, s, rsp, 0)
+    }
+    // max为big value的上限，为0时不限制
+    fn parse_txn_max(
+        redis: &Parser,
+        s: &mut TestStream,
+        rsp: &mut String,
+        max: usize,
+    ) -> Vec<HashedCommand> {
+        let mut reqs = MaxValue::new(max);
+        redis
+            .parse_request(&mut **s, &Hasher::from("raw"), &mut reqs)
+            .expect("parse");
+        let (mut out, tx) = stream(b"");
+        let mut fwd = Vec::new();
+        for (req, last) in reqs.0 .0 {
+            assert!(last);
+            if req.noforward() {
+                redis
+                    .write_response(&mut Ctx::from(req), None, &mut *out)
+                    .expect("write");
+            } else {
+                fwd.push(req);
+            }
+        }
+        *rsp += &flushed(&mut out, &tx);
+        fwd
+    }
+
+    // 事务中的指令本地响应QUEUED，exec时multi...exec整体发送到master
+    #[test]
+    fn redis_txn() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut rsp = String::new();
+        let multi = cmd(&["MULTI"]);
+        let (mut s, rx, _) = duplex(multi.as_bytes());
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        assert_eq!(rsp, "+OK\r\n");
+
+        // client逐条发送，最后一个指令不完整
+        let set = cmd(&["SET", "1", "a"]);
+        let mset = cmd(&["MSET", "3", "b", "5", "c"]);
+        let exec = cmd(&["EXEC"]);
+        recv(&mut s, &rx, set.as_bytes());
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        recv(&mut s, &rx, &mset.as_bytes()[..10]);
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        recv(&mut s, &rx, &mset.as_bytes()[10..]);
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        assert_eq!(rsp, "+OK\r\n+QUEUED\r\n+QUEUED\r\n");
+        // 事务数据保留在stream中
+        assert_eq!(s.len(), multi.len() + set.len() + mset.len());
+
+        recv(&mut s, &rx, exec.as_bytes());
+        let mut fwd = parse_txn(&redis, &mut s, &mut rsp);
+        assert_eq!(fwd.len(), 1);
+        assert_eq!(s.len(), 0);
+        assert_eq!(*s.context(), [0u8; 16]);
+
+        let req = fwd.pop().expect("exec");
+        assert_eq!(string(&req), multi + &set + &mset + &exec);
+        assert!(req.txn() && req.master_only());
+        assert_eq!(req.key_count(), 2);
+        assert_eq!(req.hash(), 1);
+        assert!(req.operation().is_store());
+    }
+
+    // 跨分片、不能放到事务中的指令，都会导致事务被丢弃
+    #[test]
+    fn redis_txn_abort() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut rsp = String::new();
+        let mut data = cmd(&["EXEC"]) + &cmd(&["DISCARD"]) + &cmd(&["MULTI"]);
+        data += &(cmd(&["SET", "1", "a"]) + &cmd(&["DEL", "3", "2"]));
+        data += &(cmd(&["PING"]) + &cmd(&["MULTI"]) + &cmd(&["EXEC"]));
+        data += &(cmd(&["MULTI"]) + &cmd(&["EVAL", "return 1", "0"]) + &cmd(&["EXEC"]));
+        let (mut s, _) = stream(data.as_bytes());
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        assert_eq!(s.len(), 0);
+        assert_eq!(
+            rsp,
+            "-ERR EXEC without MULTI\r\n-ERR DISCARD without MULTI\r\n+OK\r\n+QUEUED\r\n\
+            -CROSSSLOT Keys in request don't hash to the same shard\r\n\
+            -ERR command not allowed in MULTI\r\n-ERR MULTI calls can not be nested\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n+OK\r\n\
+            -ERR no key to locate shard in MULTI(try hashkey before multi)\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n"
+        );
+
+        // discard之后恢复正常请求；hashkey可以指定事务的分片
+        let mut rsp = String::new();
+        let mut data = cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]) + &cmd(&["DISCARD"]);
+        data += &(cmd(&["GET", "2"]) + &cmd(&["MULTI"]) + &cmd(&["EXEC"]));
+        data += &(cmd(&["HASHKEYQ", "4"]) + &cmd(&["MULTI"]));
+        data += &(cmd(&["EVAL", "return 1", "0"]) + &cmd(&["EXEC"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let fwd = parse_txn(&redis, &mut s, &mut rsp);
+        assert_eq!(
+            rsp,
+            "+OK\r\n+QUEUED\r\n+OK\r\n+OK\r\n*0\r\n+OK\r\n+QUEUED\r\n"
+        );
+        assert_eq!(fwd.len(), 2);
+        assert_eq!(string(&fwd[0]), cmd(&["GET", "2"]));
+        assert!(!fwd[0].txn());
+        assert!(fwd[1].txn());
+        assert_eq!(fwd[1].hash(), 4);
+        assert_eq!(fwd[1].key_count(), 1);
+    }
+
+    // 事务缓存的数据超过big value上限时，事务被丢弃，不再缓存后续指令，exec时响应EXECABORT
+    #[test]
+    fn redis_txn_too_large() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut rsp = String::new();
+        let multi = cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]);
+        let (mut s, rx, _) = duplex(multi.as_bytes());
+        assert!(parse_txn_max(&redis, &mut s, &mut rsp, 48).is_empty());
+        assert_eq!(s.len(), multi.len());
+
+        let data = cmd(&["SET", "1", "abcdefghijklmnopqrstuvwxyz"]) + &cmd(&["SET", "1", "b"]);
+        recv(&mut s, &rx, data.as_bytes());
+        assert!(parse_txn_max(&redis, &mut s, &mut rsp, 48).is_empty());
+        assert_eq!(s.len(), 0);
+        recv(&mut s, &rx, cmd(&["EXEC"]).as_bytes());
+        assert!(parse_txn_max(&redis, &mut s, &mut rsp, 48).is_empty());
+        assert_eq!(s.len(), 0);
+        assert_eq!(*s.context(), [0u8; 16]);
+        assert_eq!(
+            rsp,
+            "+OK\r\n+QUEUED\r\n-ERR MULTI transaction too large\r\n+QUEUED\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n"
+        );
+    }
+
+    // 事务的响应只返回exec的响应
+    #[test]
+    fn redis_txn_response() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]) + &cmd(&["INCR", "1"]);
+        let (mut s, _) = stream((data + &cmd(&["EXEC"])).as_bytes());
+        let mut rsp = String::new();
+        let req = parse_txn(&redis, &mut s, &mut rsp).pop().expect("exec");
+
+        let exec = "*2\r\n+OK\r\n-ERR value is not an integer or out of range\r\n";
+        let data = format!("+OK\r\n+QUEUED\r\n+QUEUED\r\n{}:1\r\n", exec);
+        let (mut s, rx, _) = duplex(&data.as_bytes()[..20]);
+        assert!(redis
+            .parse_response_of(&req, &mut *s)
+            .expect("rsp")
+            .is_none());
+        recv(&mut s, &rx, &data.as_bytes()[20..]);
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        assert_eq!(string(&rsp.expect("exec")), exec);
+        let rsp = redis.parse_response(&mut *s).expect("rsp");
+        assert_eq!(string(&rsp.expect("incr")), ":1\r\n");
+        assert_eq!(s.len(), 0);
+    }
+
+    fn parse(redis: &Parser, s: &mut TestStream, subscribing: bool) -> Vec<(HashedCommand, bool)> {
+        let mut reqs = Reqs(Vec::new(), subscribing, false);
+        redis
+            .parse_request(&mut **s, &Hasher::from("raw"), &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+        reqs.0
+    }
+
+    // big key检测使用的key长度，multi-key指令按拆分后的子请求计算
+    #[test]
+    fn redis_key_len() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["SET", "key1", "hello"])
+            + &cmd(&["MSET", "a", "1", "bbb", "2"])
+            + &cmd(&["XINFO", "STREAM", "stream1"])
+            + &cmd(&["PING"]);
+        let (mut s, _) = stream(data.as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        let lens: Vec<usize> = reqs.iter().map(|(req, _)| redis.key_len(req)).collect();
+        assert_eq!(lens, [4, 1, 3, 7, 0]);
+        assert_eq!(
+            redis.big_rejected(&reqs[0].0),
+            b"-ERR key or value too large\r\n"
+        );
+    }
+
+    // 开启big value拒绝时，bulk声明的长度超过上限即拒绝整个指令，不等待数据：
+    // 超限的bulk由pipeline跳过，指令剩余的bulk在后续解析时丢弃，连接保持可用
+    #[test]
+    fn redis_reject_big() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let hasher = Hasher::from("raw");
+        let small = cmd(&["SET", "k", "hello"]);
+        let big = "*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$11\r\nhello";
+        let (mut s, _) = stream((small.clone() + big).as_bytes());
+        let mut reqs = MaxValue::new(0);
+        redis
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("incomplete");
+        assert_eq!((reqs.0 .0.len(), reqs.2.len()), (1, 0));
+
+        // mset的第二个value超限：整个指令被拒绝，没有子请求发送；之后的bulk再次超限时只跳过数据
+        let data = small + "*7\r\n$4\r\nMSET\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$11\r\nhello";
+        let (mut s, rx, _) = duplex(data.as_bytes());
+        let mut reqs = MaxValue::new(10);
+        redis
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("reject");
+        assert_eq!((reqs.0 .0.len(), reqs.2.len()), (1, 1));
+        assert_eq!(reqs.2[0].1, 11);
+        assert_eq!(reqs.3, 13);
+        reqs.skip_from(&mut *s);
+        assert_eq!((reqs.3, s.len()), (8, 0));
+        let rest = " world\r\n$1\r\nc\r\n$12\r\nhello world!\r\n".to_string() + &cmd(&["GET", "k"]);
+        recv(&mut s, &rx, rest.as_bytes());
+        reqs.skip_from(&mut *s);
+        redis
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("discard");
+        assert_eq!((reqs.2.len(), reqs.3), (1, 14));
+        reqs.skip_from(&mut *s);
+        redis
+            .parse_request(&mut *s, &hasher, &mut reqs)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+        assert_eq!(*s.context(), [0u8; 16]);
+        assert_eq!(reqs.0 .0.len(), 2);
+        assert_eq!(string(&reqs.0 .0[1].0), cmd(&["GET", "k"]));
+
+        // 事务中的指令被拒绝时，事务被丢弃，exec时响应EXECABORT
+        let data = cmd(&["MULTI"]) + "*3\r\n$3\r\nSET\r\n$1\r\n1\r\n$11\r\nhello world\r\n";
+        let (mut s, _) = stream((data + &cmd(&["EXEC"])).as_bytes());
+        let mut txn = MaxValue::new(10);
+        redis
+            .parse_request(&mut *s, &hasher, &mut txn)
+            .expect("reject");
+        txn.skip_from(&mut *s);
+        redis
+            .parse_request(&mut *s, &hasher, &mut txn)
+            .expect("exec");
+        assert_eq!(s.len(), 0);
+        assert_eq!(*s.context(), [0u8; 16]);
+
+        // 被拒绝的指令本地响应
+        let (mut out, tx) = stream(b"");
+        let (multi, exec) = (txn.0 .0.remove(0).0, txn.0 .0.remove(0).0);
+        let rejected = [reqs.2.remove(0).0, multi, txn.2.remove(0).0, exec];
+        for req in rejected {
+            assert!(req.noforward());
+            redis
+                .write_response(&mut Ctx::from(req), None, &mut *out)
+                .expect("write");
+        }
+        assert_eq!(
+            flushed(&mut out, &tx),
+            "-ERR key or value too large\r\n+OK\r\n-ERR MULTI transaction too large\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n"
+        );
+    }
+
+    // 分片迁移期间，读请求的空响应标记为!ok，由迁移前的分片重试
+    #[test]
+    fn redis_miss_notok() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, _) = stream(cmd(&["GET", "key1"]).as_bytes());
+        let (mut req, _) = parse(&redis, &mut s, false).pop().expect("get");
+        let (mut s, _) = stream(b"$-1\r\n$5\r\nhello\r\n");
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        assert!(rsp.expect("nil").ok());
+
+        req.set_miss_notok();
+        let (mut s, _) = stream(b"$-1\r\n$5\r\nhello\r\n");
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        assert!(!rsp.expect("nil").ok());
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        assert!(rsp.expect("hello").ok());
+        assert_eq!(s.len(), 0);
+
+        // 按指令判断未命中，如exists的:0、hgetall的*0，空字符串对get是命中
+        let cases = [
+            (&["GET", "key1"][..], "$0\r\n\r\n", true),
+            (&["EXISTS", "key1"][..], ":0\r\n", false),
+            (&["EXISTS", "key1"][..], ":1\r\n", true),
+            (&["HGETALL", "key1"][..], "*0\r\n", false),
+            (
+                &["HGETALL", "key1"][..],
+                "*2\r\n$1\r\nf\r\n$1\r\nv\r\n",
+                true,
+            ),
+            (&["LRANGE", "key1", "0", "-1"][..], "*0\r\n", false),
+            (&["TTL", "key1"][..], ":-2\r\n", false),
+            (&["TTL", "key1"][..], ":-1\r\n", true),
+            (&["GETRANGE", "key1", "0", "1"][..], "$0\r\n\r\n", false),
+            (&["ZCARD", "key1"][..], ":0\r\n", false),
+            (&["HGET", "key1", "f"][..], "$-1\r\n", false),
+        ];
+        for (args, rsp, ok) in cases {
+            let (mut s, _) = stream(cmd(args).as_bytes());
+            let (mut req, _) = parse(&redis, &mut s, false).pop().expect("req");
+            req.set_miss_notok();
+            let (mut s, _) = stream(rsp.as_bytes());
+            let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+            assert_eq!(rsp.expect("rsp").ok(), ok, "{:?}", args);
+        }
+    }
+
+    // 分片迁移期间的读请求只在未命中时回退到迁移前的分片，请求异常（如超时、连接断开）时不回退
+    #[test]
+    fn redis_miss_fallback() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, _) = stream(cmd(&["GET", "key1"]).as_bytes());
+        let (req, _) = parse(&redis, &mut s, false).pop().expect("get");
+        let get = req.copied();
+        drop(req);
+
+        let sent: Arc<Mutex<Vec<request::Request>>> = Default::default();
+        let holder = sent.clone();
+        let cb: CallbackPtr = Callback::new(Box::new(move |req| {
+            holder.lock().unwrap().push(req);
+        }))
+        .into();
+        let waker = Arc::new(AtomicWaker::default());
+        let fallback = |ctx: &mut CallbackContext| {
+            ctx.send();
+            let mut req = sent.lock().unwrap().pop().expect("sent");
+            req.retry_on_rsp_notok(true);
+            req.retry_on_err(false);
+            req.try_next(true);
+            req
+        };
+
+        let mut ctx = CallbackContext::new(get.copied(), &waker, cb.clone(), true, true, false);
+        fallback(&mut ctx).on_err(Error::Closed);
+        assert!(sent.lock().unwrap().is_empty());
+        assert!(ctx.complete() && ctx.failed());
+
+        let mut ctx = CallbackContext::new(get, &waker, cb, true, true, false);
+        let rsp = Command::from(false, MemGuard::from_vec(b"$-1\r\n".to_vec()));
+        fallback(&mut ctx).on_complete(rsp);
+        let mut old = sent.lock().unwrap().pop().expect("fallback");
+        assert!(!ctx.complete());
+        old.try_next(false);
+        old.on_complete(Command::from_ok(MemGuard::from_vec(
+            b"$1\r\nv\r\n".to_vec(),
+        )));
+        assert!(ctx.complete() && !ctx.failed());
+        let rsp = ctx.take_response().expect("rsp");
+        assert_eq!(string(&rsp), "$1\r\nv\r\n");
+    }
+
+    // 订阅指令按channel拆分，不带channel的unsubscribe及模式订阅整体发送
+    #[test]
+    fn redis_subscribe() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, _) = stream(cmd(&["SUBSCRIBE", "1", "2"]).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 2);
+        for (i, (req, last)) in reqs.iter().enumerate() {
+            assert_eq!(*last, i == 1);
+            assert!(req.noforward());
+            assert_eq!(req.dedicated(), Dedicated::Subscribe);
+            assert_eq!(req.hash(), i as i64 + 1);
+            assert_eq!(string(req), cmd(&["subscribe", &(i + 1).to_string()]));
+        }
+        let (mut s, _) = stream(cmd(&["UNSUBSCRIBE", "2"]).as_bytes());
+        let reqs = parse(&redis, &mut s, true);
+        assert_eq!(reqs[0].0.dedicated(), Dedicated::Unsubscribe);
+        assert_eq!(string(&reqs[0].0), cmd(&["unsubscribe", "2"]));
+
+        let data = cmd(&["UNSUBSCRIBE"]) + &cmd(&["PSUBSCRIBE", "a*", "b*"]);
+        let (mut s, _) = stream(data.as_bytes());
+        let reqs = parse(&redis, &mut s, true);
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].0.dedicated(), Dedicated::UnsubscribeAll);
+        assert_eq!(reqs[1].0.dedicated(), Dedicated::Pattern);
+        assert_eq!(string(&reqs[1].0), cmd(&["PSUBSCRIBE", "a*", "b*"]));
+
+        // 订阅指令的确认由后端推送，不写入响应
+        let (mut out, tx) = stream(b"");
+        for (req, _) in reqs {
+            assert!(req.dedicated().push());
+            redis
+                .write_response(&mut Ctx::from(req), None, &mut *out)
+                .expect("write");
+        }
+        assert_eq!(flushed(&mut out, &tx), "");
+    }
+
+    // 阻塞指令整体转发，key需要落在同一分片
+    #[test]
+    fn redis_blocking() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["BLPOP", "1", "3", "0"]) + &cmd(&["BRPOP", "1", "2", "0"]);
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 2);
+        let (req, _) = reqs.remove(0);
+        assert_eq!(req.dedicated(), Dedicated::Block);
+        assert!(!req.noforward());
+        assert_eq!(req.hash(), 1);
+        assert_eq!(string(&req), cmd(&["BLPOP", "1", "3", "0"]));
+
+        let (req, _) = reqs.remove(0);
+        assert!(req.noforward());
+        let (mut out, tx) = stream(b"");
+        redis
+            .write_response(&mut Ctx::from(req), None, &mut *out)
+            .expect("write");
+        assert_eq!(
+            flushed(&mut out, &tx),
+            "-CROSSSLOT Keys in request don't hash to the same shard\r\n"
+        );
+        // 独占连接鉴权失败、连接数超过上限时，本地返回的错误
+        assert_eq!(redis.auth_failed(), b"-ERR backend auth failed\r\n");
+        assert_eq!(
+            redis.conn_limited(),
+            b"-ERR max number of clients reached\r\n"
+        );
+    }
+
+    // watch发送到独占连接，key需要落在同一分片；exec、discard、unwatch标记为清理watch的请求
+    #[test]
+    fn redis_watch() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["WATCH", "1", "3"]) + &cmd(&["WATCH", "1", "2"]) + &cmd(&["UNWATCH"]);
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 3);
+        let (req, _) = reqs.remove(0);
+        assert_eq!(req.dedicated(), Dedicated::Watch);
+        assert!(!req.noforward());
+        assert_eq!(req.hash(), 1);
+        assert_eq!(string(&req), cmd(&["WATCH", "1", "3"]));
+
+        let (mut out, tx) = stream(b"");
+        for (req, _) in reqs {
+            assert!(req.noforward());
+            redis
+                .write_response(&mut Ctx::from(req), None, &mut *out)
+                .expect("write");
+        }
+        assert_eq!(
+            flushed(&mut out, &tx),
+            "-CROSSSLOT Keys in request don't hash to the same shard\r\n+OK\r\n"
+        );
+
+        // 事务中不能watch
+        let mut rsp = String::new();
+        let mut data = cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]) + &cmd(&["WATCH", "1"]);
+        data += &cmd(&["EXEC"]);
+        let (mut s, _) = stream(data.as_bytes());
+        assert!(parse_txn(&redis, &mut s, &mut rsp).is_empty());
+        assert_eq!(
+            rsp,
+            "+OK\r\n+QUEUED\r\n-ERR WATCH inside MULTI is not allowed\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n"
+        );
+
+        // exec、discard会清理watch
+        let mut data = cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]) + &cmd(&["EXEC"]);
+        data += &(cmd(&["MULTI"]) + &cmd(&["SET", "1", "a"]) + &cmd(&["DISCARD"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = Reqs::default();
+        redis
+            .parse_request(&mut *s, &Hasher::from("raw"), &mut reqs)
+            .expect("parse");
+        let mut reqs: Vec<_> = reqs.0.into_iter().map(|(req, _)| req).collect();
+        let (discard, exec) = (reqs.remove(5), &reqs[2]);
+        assert!(exec.txn() && !exec.noforward());
+        assert_eq!(exec.dedicated(), Dedicated::Unwatch);
+        assert!(discard.noforward());
+        assert_eq!(discard.dedicated(), Dedicated::Unwatch);
+        assert_eq!(string(&discard), cmd(&["UNWATCH"]));
+        // discard总是本地响应+OK
+        let (mut out, tx) = stream(b"");
+        let mut rsp = Command::from_ok(MemGuard::from_vec(b"+OK\r\n".to_vec()));
+        redis
+            .write_response(&mut Ctx::from(discard), Some(&mut rsp), &mut *out)
+            .expect("write");
+        assert_eq!(flushed(&mut out, &tx), "+OK\r\n");
+    }
+
+    // 订阅模式下ping的响应为数组
+    #[test]
+    fn redis_subscribe_ping() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, _) = stream(cmd(&["PING"]).as_bytes());
+        let (mut out, tx) = stream(b"");
+        for (req, _) in parse(&redis, &mut s, true) {
+            assert!(req.noforward());
+            redis
+                .write_response(&mut Ctx::from(req), None, &mut *out)
+                .expect("write");
+        }
+        assert_eq!(flushed(&mut out, &tx), "*2\r\n$4\r\npong\r\n$0\r\n\r\n");
+    }
+
+    // 订阅确认中的订阅数修正为client总的订阅数
+    #[test]
+    fn redis_write_push() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let confirm = |kind: &str, channel: Option<&str>, count: usize| {
+            let channel = channel
+                .map(|c| format!("${}\r\n{}\r\n", c.len(), c))
+                .unwrap_or("$-1\r\n".to_string());
+            format!(
+                "*3\r\n${}\r\n{}\r\n{}:{}\r\n",
+                kind.len(),
+                kind,
+                channel,
+                count
+            )
+        };
+        let mut subs = Subscriptions::default();
+        subs.set_subscribing();
+        let (mut out, tx) = stream(b"");
+        let mut push = |subs: &mut Subscriptions, shard, pattern, data: &str| {
+            let data = Command::from_ok(MemGuard::from_vec(data.as_bytes().to_vec()));
+            redis
+                .write_push(subs, shard, pattern, &data, &mut *out)
+                .expect("push");
+            let pushed = flushed(&mut out, &tx);
+            tx.lock().unwrap().clear();
+            pushed
+        };
+        let sub2 = confirm("subscribe", Some("2"), 1);
+        assert_eq!(push(&mut subs, 0, false, &sub2), sub2);
+        let sub1 = confirm("subscribe", Some("1"), 1);
+        assert_eq!(
+            push(&mut subs, 1, false, &sub1),
+            confirm("subscribe", Some("1"), 2)
+        );
+        // 模式订阅只返回第0个分片的确认
+        let psub = confirm("psubscribe", Some("a*"), 1);
+        assert_eq!(
+            push(&mut subs, 0, true, &psub),
+            confirm("psubscribe", Some("a*"), 3)
+        );
+        assert_eq!(push(&mut subs, 1, true, &psub), "");
+        // 消息直接返回
+        let msg = "*3\r\n$7\r\nmessage\r\n$1\r\n1\r\n$5\r\nhello\r\n";
+        assert_eq!(push(&mut subs, 1, false, msg), msg);
+        assert!(subs.subscribing());
+
+        let unsub = confirm("unsubscribe", Some("2"), 0);
+        assert_eq!(
+            push(&mut subs, 0, false, &unsub),
+            confirm("unsubscribe", Some("2"), 2)
+        );
+        // 连接上没有订阅时，client还有其他订阅则丢弃
+        let nil = confirm("unsubscribe", None, 0);
+        assert_eq!(push(&mut subs, 0, false, &nil), "");
+        let unsub = confirm("punsubscribe", Some("a*"), 0);
+        assert_eq!(
+            push(&mut subs, 0, true, &unsub),
+            confirm("punsubscribe", Some("a*"), 1)
+        );
+        let unsub = confirm("unsubscribe", Some("1"), 0);
+        assert_eq!(push(&mut subs, 1, false, &unsub), unsub);
+        assert!(!subs.subscribing());
+        assert_eq!(push(&mut subs, 0, false, &nil), nil);
+        // 订阅数不合法时原样返回
+        let bad = "*3\r\n$9\r\nsubscribe\r\n$1\r\n3\r\n:-1\r\n";
+        assert_eq!(push(&mut subs, 0, false, bad), bad);
+        assert!(!subs.subscribing());
+
+        // RESP3的client，推送数据转为push类型，nil的channel转为null
+        let to_push = |s: String| s.replacen('*', ">", 1);
+        subs.set_resp3(true);
+        let sub2 = confirm("subscribe", Some("2"), 1);
+        assert_eq!(push(&mut subs, 0, false, &sub2), to_push(sub2));
+        assert_eq!(push(&mut subs, 0, false, msg), to_push(msg.to_string()));
+        let unsub = confirm("unsubscribe", Some("2"), 0);
+        assert_eq!(push(&mut subs, 0, false, &unsub), to_push(unsub));
+        assert_eq!(
+            push(&mut subs, 0, false, &nil),
+            ">3\r\n$11\r\nunsubscribe\r\n_\r\n:0\r\n"
+        );
+        // 非array的数据原样返回
+        assert_eq!(push(&mut subs, 0, false, "+OK\r\n"), "+OK\r\n");
+    }
+
+    // stream指令：xgroup、xinfo的key在子命令之后，xread的key在STREAMS之后
+    #[test]
+    fn redis_streams() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut data =
+            cmd(&["XADD", "1", "*", "f", "v"]) + &cmd(&["XGROUP", "CREATE", "3", "g", "$"]);
+        data += &(cmd(&["XINFO", "GROUPS", "2"]) + &cmd(&["XLEN", "4"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 4);
+        let hashes: Vec<i64> = reqs.iter().map(|(req, _)| req.hash()).collect();
+        assert_eq!(hashes, [1, 3, 2, 4]);
+        assert!(reqs[0].0.operation().is_store() && reqs[1].0.operation().is_store());
+        // 消费组的读取只发送到master
+        assert!(!reqs[2].0.operation().is_store() && reqs[2].0.master_only());
+        assert!(!reqs[3].0.master_only());
+        assert_eq!(
+            string(&reqs[1].0),
+            cmd(&["XGROUP", "CREATE", "3", "g", "$"])
+        );
+
+        let read = cmd(&["XREAD", "COUNT", "2", "STREAMS", "1", "3", "0", "0"]);
+        let group = cmd(&[
+            "XREADGROUP",
+            "GROUP",
+            "streams",
+            "c",
+            "BLOCK",
+            "0",
+            "STREAMS",
+            "2",
+            ">",
+        ]);
+        let cross = cmd(&["xread", "streams", "1", "2", "0", "0"]);
+        let (mut s, _) = stream((read.clone() + &group + &cross).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 3);
+        assert_eq!(string(&reqs[0].0), read);
+        assert_eq!(reqs[0].0.hash(), 1);
+        assert_eq!(reqs[0].0.dedicated(), Dedicated::None);
+        assert!(!reqs[0].0.operation().is_store());
+        // 带BLOCK时使用独占连接
+        assert_eq!(string(&reqs[1].0), group);
+        assert_eq!(reqs[1].0.hash(), 2);
+        assert_eq!(reqs[1].0.dedicated(), Dedicated::Block);
+        assert!(reqs[1].0.operation().is_store());
+
+        let (req, _) = reqs.into_iter().nth(2).expect("cross");
+        assert!(req.noforward());
+        let (mut out, tx) = stream(b"");
+        redis
+            .write_response(&mut Ctx::from(req), None, &mut *out)
+            .expect("write");
+        assert_eq!(
+            flushed(&mut out, &tx),
+            "-CROSSSLOT Keys in request don't hash to the same shard\r\n"
+        );
+
+        // STREAMS之后的参数个数为奇数、缺少STREAMS时本地响应syntax error；xinfo help没有key
+        let mut data =
+            cmd(&["XREAD", "STREAMS", "1", "3", "0"]) + &cmd(&["XREAD", "COUNT", "1", "NOACK"]);
+        data += &cmd(&["XINFO", "HELP"]);
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 3);
+        let (help, _) = reqs.pop().expect("help");
+        assert!(!help.noforward());
+        assert_eq!(string(&help), cmd(&["XINFO", "HELP"]));
+        let (mut out, tx) = stream(b"");
+        for (req, _) in reqs {
+            assert!(req.noforward());
+            redis
+                .write_response(&mut Ctx::from(req), None, &mut *out)
+                .expect("write");
+        }
+        assert_eq!(
+            flushed(&mut out, &tx),
+            "-ERR syntax error\r\n-ERR syntax error\r\n"
+        );
+    }
+
+    // 依次写回各分片的响应，跨分片请求的中间结果在Ctx间传递
+    fn write_shards(redis: &Parser, reqs: Vec<(HashedCommand, bool)>, rsps: &[&str]) -> String {
+        let (mut out, tx) = stream(b"");
+        let mut merged = Vec::new();
+        for ((req, _), rsp) in reqs.into_iter().zip(rsps) {
+            let mut ctx = Ctx::from(req);
+            ctx.merged = merged;
+            let mut rsp = Command::from_ok(MemGuard::from_vec(rsp.as_bytes().to_vec()));
+            redis
+                .write_response(&mut ctx, Some(&mut rsp), &mut *out)
+                .expect("write");
+            merged = std::mem::take(&mut ctx.merged);
+        }
+        assert!(merged.is_empty());
+        flushed(&mut out, &tx)
+    }
+
+    // mock的分片数为2，client的cursor = 分片cursor * 2 + 分片索引
+    #[test]
+    fn redis_scan() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["SCAN", "0"]) + &cmd(&["scan", "5", "MATCH", "k*", "COUNT", "10"]);
+        let (mut s, _) = stream((data + &cmd(&["SCAN", "x"])).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 3);
+        let shards: Vec<_> = reqs
+            .iter()
+            .map(|(r, _)| (r.hash(), r.cross_shards()))
+            .collect();
+        assert_eq!(shards, [(0, Some(2)), (1, Some(2)), (0, Some(2))]);
+        assert!(reqs.iter().all(|(r, _)| r.sendto_all()));
+        assert!(reqs.iter().all(|(r, last)| *last && r.master_only()));
+        assert_eq!(string(&reqs[0].0), cmd(&["SCAN", "0"]));
+        assert_eq!(
+            string(&reqs[1].0),
+            cmd(&["scan", "2", "MATCH", "k*", "COUNT", "10"])
+        );
+        // cursor不合法时由后端响应异常
+        assert_eq!(string(&reqs[2].0), cmd(&["SCAN", "x"]));
+
+        let keys = "*2\r\n$2\r\nk1\r\n$2\r\nk2\r\n";
+        let rsp = |cursor: &str| format!("*2\r\n${}\r\n{}\r\n{}", cursor.len(), cursor, keys);
+        let err = "-ERR invalid cursor\r\n";
+        let mut reqs = reqs.into_iter();
+        // 第0个分片遍历完毕，从第1个分片继续
+        let first: Vec<_> = reqs.by_ref().take(1).collect();
+        assert_eq!(write_shards(&redis, first, &[&rsp("0")]), rsp("1"));
+        // 第1个分片未遍历完毕：7 * 2 + 1
+        let second: Vec<_> = reqs.by_ref().take(1).collect();
+        assert_eq!(write_shards(&redis, second, &[&rsp("7")]), rsp("15"));
+        assert_eq!(write_shards(&redis, reqs.collect(), &[err]), err);
+
+        // 最后一个分片遍历完毕，返回0
+        let (mut s, _) = stream(cmd(&["SCAN", "3"]).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        assert_eq!(write_shards(&redis, reqs, &[&rsp("0")]), rsp("0"));
+
+        // 分片的cursor过大，编码分片索引后溢出
+        let (mut s, _) = stream(cmd(&["SCAN", "1"]).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        let max = u64::MAX.to_string();
+        assert_eq!(
+            write_shards(&redis, reqs, &[&rsp(&max)]),
+            "-ERR invalid cursor\r\n"
+        );
+    }
+
+    #[test]
+    fn redis_cross_shard_merge() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = cmd(&["KEYS", "k*"]) + &cmd(&["DBSIZE"]) + &cmd(&["INFO", "KeySpace"]);
+        let (mut s, _) = stream((data + &cmd(&["INFO", "all"])).as_bytes());
+        let mut reqs = parse(&redis, &mut s, false);
+        assert_eq!(reqs.len(), 7);
+        // info只合并keyspace，其他section按普通请求发送
+        let (all, _) = reqs.pop().expect("info all");
+        assert_eq!(all.cross_shards(), None);
+        assert_eq!(string(&all), cmd(&["INFO", "all"]));
+        let shards: Vec<_> = reqs
+            .iter()
+            .map(|(r, _)| (r.hash(), r.cross_shards()))
+            .collect();
+        assert_eq!(shards, [(0, Some(2)), (1, Some(2))].repeat(3));
+        let lasts: Vec<_> = reqs.iter().map(|(_, last)| *last).collect();
+        assert_eq!(lasts, [false, true].repeat(3));
+        assert_eq!(string(&reqs[1].0), cmd(&["KEYS", "k*"]));
+        assert_eq!(string(&reqs[4].0), cmd(&["INFO", "KeySpace"]));
+        assert_eq!(string(&reqs[5].0), cmd(&["INFO", "KeySpace"]));
+
+        let mut reqs = reqs.into_iter();
+        let keys: Vec<_> = reqs.by_ref().take(2).collect();
+        let merged = write_shards(
+            &redis,
+            keys,
+            &["*1\r\n$2\r\nk1\r\n", "*2\r\n$2\r\nk2\r\n$2\r\nk3\r\n"],
+        );
+        assert_eq!(merged, "*3\r\n$2\r\nk1\r\n$2\r\nk2\r\n$2\r\nk3\r\n");
+
+        let dbsize: Vec<_> = reqs.by_ref().take(2).collect();
+        assert_eq!(
+            write_shards(&redis, dbsize, &[":3\r\n", ":4\r\n"]),
+            ":7\r\n"
+        );
+
+        let info = |s: &str| format!("${}\r\n{}\r\n", s.len(), s);
+        let rsp0 = info("# Keyspace\r\ndb0:keys=2,expires=1,avg_ttl=100\r\n");
+        let rsp1 = info(
+            "# Keyspace\r\ndb0:keys=3,expires=3,avg_ttl=300\r\ndb2:keys=1,expires=0,avg_ttl=0\r\n",
+        );
+        assert_eq!(
+            write_shards(&redis, reqs.collect(), &[&rsp0, &rsp1]),
+            info("# Keyspace\r\ndb0:keys=5,expires=4,avg_ttl=250\r\ndb2:keys=1,expires=0,avg_ttl=0\r\n")
+        );
+
+        // 任一分片异常时返回异常
+        let (mut s, _) = stream(cmd(&["DBSIZE"]).as_bytes());
+        let reqs = parse(&redis, &mut s, false);
+        let err = "-ERR loading\r\n";
+        assert_eq!(write_shards(&redis, reqs, &[":3\r\n", err]), err);
+    }
+
+    // hello 3之后的请求按RESP3响应，hello之前的请求不受影响
+    #[test]
+    fn redis_resp3() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut data = cmd(&["GET", "1"]) + &cmd(&["HELLO", "3", "SETNAME", "c"]);
+        data += &(cmd(&["GET", "2"]) + &cmd(&["MGET", "1", "2"]) + &cmd(&["HELLO", "4"]));
+        data += &(cmd(&["HELLO"]) + &cmd(&["HELLO", "2"]) + &cmd(&["GET", "3"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = Reqs(Vec::new(), false, false);
+        redis
+            .parse_request(&mut *s, &Hasher::from("raw"), &mut reqs)
+            .expect("parse");
+        assert!(!reqs.2);
+        let resp3: Vec<bool> = reqs.0.iter().map(|(r, _)| r.resp3()).collect();
+        assert_eq!(
+            resp3,
+            [false, true, true, true, true, true, true, false, false]
+        );
+
+        let (mut out, tx) = stream(b"");
+        let mut write = |req: HashedCommand, rsp: Option<&str>| {
+            let mut rsp = rsp.map(|r| Command::from_ok(MemGuard::from_vec(r.as_bytes().to_vec())));
+            redis
+                .write_response(&mut Ctx::from(req), rsp.as_mut(), &mut *out)
+                .expect("write");
+            let written = flushed(&mut out, &tx);
+            tx.lock().unwrap().clear();
+            written
+        };
+        let hello = |proto: &str| {
+            let fields = format!(
+                "$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n$5\r\n6.0.0\r\n$5\r\nproto\r\n:{}\r\n$2\r\nid\r\n:0\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n",
+                proto
+            );
+            match proto {
+                "3" => format!("%7\r\n{}", fields),
+                _ => format!("*14\r\n{}", fields),
+            }
+        };
+        let mut reqs = reqs.0.into_iter().map(|(r, _)| r);
+        let mut next = || reqs.next().expect("req");
+        assert_eq!(write(next(), Some("$-1\r\n")), "$-1\r\n");
+        assert_eq!(write(next(), None), hello("3"));
+        assert_eq!(write(next(), Some("$-1\r\n")), "_\r\n");
+        // 无响应的key转为null，并通过attribute携带原因
+        assert_eq!(
+            write(next(), None),
+            "*2\r\n|1\r\n+error\r\n+redis no available\r\n_\r\n"
+        );
+        assert_eq!(write(next(), Some("$-1\r\n")), "_\r\n");
+        // 不支持的协议版本，不影响当前协议
+        assert_eq!(
+            write(next(), None),
+            "-NOPROTO unsupported protocol version\r\n"
+        );
+        assert_eq!(write(next(), None), hello("3"));
+        assert_eq!(write(next(), None), hello("2"));
+        assert_eq!(write(next(), None), "-ERR redis no available\r\n");
+    }
+
+    // RESP3下，array中嵌套的nil转为null，hgetall、smembers等转为map、set
+    #[test]
+    fn redis_resp3_aggregate() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut data = cmd(&["HELLO", "3"]) + &cmd(&["HGETALL", "h"]);
+        data += &(cmd(&["SMEMBERS", "s"]) + &cmd(&["LRANGE", "l", "0", "-1"]));
+        data += &(cmd(&["XREAD", "STREAMS", "x", "0"]) + &cmd(&["HGETALL", "h"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let mut reqs = Reqs(Vec::new(), false, false);
+        redis
+            .parse_request(&mut *s, &Hasher::from("raw"), &mut reqs)
+            .expect("parse");
+        let (mut out, tx) = stream(b"");
+        let mut reqs = reqs.0.into_iter().map(|(r, _)| r);
+        let mut write = |rsp: Option<&str>| {
+            let req = reqs.next().expect("req");
+            let mut rsp = rsp.map(|r| Command::from_ok(MemGuard::from_vec(r.as_bytes().to_vec())));
+            redis
+                .write_response(&mut Ctx::from(req), rsp.as_mut(), &mut *out)
+                .expect("write");
+            let written = flushed(&mut out, &tx);
+            tx.lock().unwrap().clear();
+            written
+        };
+        assert!(write(None).starts_with("%7\r\n"));
+        assert_eq!(
+            write(Some("*4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n")),
+            "%2\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n"
+        );
+        assert_eq!(write(Some("*1\r\n$1\r\na\r\n")), "~1\r\n$1\r\na\r\n");
+        assert_eq!(
+            write(Some("*3\r\n$-1\r\n:1\r\n$-1\r\n")),
+            "*3\r\n_\r\n:1\r\n_\r\n"
+        );
+        assert_eq!(
+            write(Some("*2\r\n*2\r\n$1\r\nx\r\n*-1\r\n*1\r\n$-1\r\n")),
+            "*2\r\n*2\r\n$1\r\nx\r\n_\r\n*1\r\n_\r\n"
+        );
+        assert_eq!(write(Some("*0\r\n")), "%0\r\n");
+    }
+
+    // cluster的重定向响应标记为!ok，由topo根据重定向重试
+    #[test]
+    fn redis_cluster_redirect() {
+        use protocol::redis::cluster::Redirect;
+        let redis = Parser::try_from("redis").expect("redis");
+        let data = "-MOVED 3999 127.0.0.1:6381\r\n-ASK 12182 10.0.0.2:7000\r\n-ERR wrong\r\n";
+        let (mut s, _) = stream(data.as_bytes());
+        let mut rsps = Vec::new();
+        while let Some(rsp) = redis.parse_response(&mut *s).expect("rsp") {
+            rsps.push((rsp.ok(), Redirect::parse(&rsp)));
+        }
+        assert_eq!(s.len(), 0);
+        assert_eq!(
+            rsps,
+            [
+                (
+                    false,
+                    Some(Redirect::Moved(3999, "127.0.0.1:6381".to_string()))
+                ),
+                (
+                    false,
+                    Some(Redirect::Ask(12182, "10.0.0.2:7000".to_string()))
+                ),
+                (true, None),
+            ]
+        );
+        assert_eq!(rsps[1].1.as_ref().expect("ask").addr(), "10.0.0.2:7000");
+    }
+
+    // ASK重定向后的请求先发送ASKING，解析响应时跳过ASKING的响应
+    #[test]
+    fn redis_cluster_asking() {
+        let redis = Parser::try_from("redis").expect("redis");
+        let (mut s, _) = stream(cmd(&["GET", "foo"]).as_bytes());
+        let (mut req, _) = parse(&redis, &mut s, false).pop().expect("get");
+        protocol::redis::cluster::asking(&mut req);
+        assert!(req.asking());
+        assert_eq!(
+            string(&req),
+            "*1\r\n$6\r\nASKING\r\n".to_string() + &cmd(&["GET", "foo"])
+        );
+
+        let data = "+OK\r\n$3\r\nbar\r\n:1\r\n";
+        let (mut s, rx, _) = duplex(&data.as_bytes()[..8]);
+        assert!(redis
+            .parse_response_of(&req, &mut *s)
+            .expect("rsp")
+            .is_none());
+        recv(&mut s, &rx, &data.as_bytes()[8..]);
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        let rsp = rsp.expect("get");
+        assert!(rsp.ok());
+        assert_eq!(string(&rsp), "$3\r\nbar\r\n");
+        let rsp = redis.parse_response(&mut *s).expect("rsp");
+        assert_eq!(string(&rsp.expect("next")), ":1\r\n");
+
+        // ASKING失败时返回ASKING的响应，请求的响应被丢弃
+        let data = "-ERR unknown\r\n-MOVED 1 a:1\r\n";
+        let (mut s, _) = stream(data.as_bytes());
+        let rsp = redis.parse_response_of(&req, &mut *s).expect("rsp");
+        let rsp = rsp.expect("asking");
+        assert!(!rsp.ok());
+        assert_eq!(string(&rsp), "-ERR unknown\r\n");
+        assert_eq!(s.len(), 0);
+    }
+
+    #[test]
+    fn redis_cluster_slots() {
+        use protocol::redis::cluster::{parse_slots, slots_request, SlotRange};
+        // 鉴权由后端连接的握手完成
+        assert_eq!(slots_request(), cmd(&["CLUSTER", "SLOTS"]).as_bytes());
+
+        let slots = "*2\r\n\
+            *4\r\n:0\r\n:5460\r\n*3\r\n$8\r\n10.0.0.1\r\n:7000\r\n$2\r\nid\r\n*4\r\n$8\r\n10.0.0.4\r\n:7000\r\n$2\r\nid\r\n*0\r\n\
+            *3\r\n:5461\r\n:16383\r\n*2\r\n$0\r\n\r\n:7001\r\n";
+        let data = slots;
+        for i in 0..data.len() {
+            let r = parse_slots(&data.as_bytes()[..i], "seed");
+            assert!(
+                matches!(r, Err(protocol::Error::ProtocolIncomplete)),
+                "{}",
+                i
+            );
+        }
+        let ranges = parse_slots(data.as_bytes(), "seed").expect("slots");
+        assert_eq!(
+            ranges,
+            [
+                SlotRange {
+                    start: 0,
+                    end: 5460,
+                    nodes: vec!["10.0.0.1:7000".to_string(), "10.0.0.4:7000".to_string()],
+                },
+                SlotRange {
+                    start: 5461,
+                    end: 16383,
+                    nodes: vec!["seed:7001".to_string()],
+                },
+            ]
+        );
+
+        let r = parse_slots(b"-ERR cluster support disabled\r\n", "seed");
+        assert!(matches!(r, Err(protocol::Error::ResponseProtocolInvalid)));
+    }
+
+    // 配置了client密码的鉴权状态
+    struct AuthReqs {
+        reqs: Reqs,
+        password: &'static str,
+        authed: bool,
+        failed: usize,
+    }
+    impl protocol::RequestProcessor for AuthReqs {
+        fn process(&mut self, req: HashedCommand, last: bool) {
+            self.reqs.process(req, last);
+        }
+        fn shard_idx(&self, hash: i64) -> usize {
+            self.reqs.shard_idx(hash)
+        }
+        fn resp3(&self) -> bool {
+            self.reqs.resp3()
+        }
+        fn set_resp3(&mut self, resp3: bool) {
+            self.reqs.set_resp3(resp3);
+        }
+        fn authed(&self) -> bool {
+            self.authed
+        }
+        fn auth(&mut self, password: &ds::RingSlice) -> bool {
+            let ok = password.as_string_lossy() == self.password;
+            self.authed |= ok;
+            self.failed += !ok as usize;
+            ok
+        }
+    }
+
+    // 鉴权前只允许auth、hello、ping、quit
+    #[test]
+    fn redis_client_auth() {
+        use protocol::RequestProcessor;
+        let redis = Parser::try_from("redis").expect("redis");
+        let mut data = cmd(&["GET", "1"]) + &cmd(&["PING"]) + &cmd(&["AUTH", "bad"]);
+        data += &(cmd(&["MGET", "1", "2"]) + &cmd(&["HELLO", "3", "AUTH", "default", "bad"]));
+        data += &(cmd(&["AUTH", "default", "pwd"]) + &cmd(&["GET", "2"]));
+        let (mut s, _) = stream(data.as_bytes());
+        let mut process = AuthReqs {
+            reqs: Reqs(Vec::new(), false, false),
+            password: "pwd",
+            authed: false,
+            failed: 0,
+        };
+        redis
+            .parse_request(&mut *s, &Hasher::from("raw"), &mut process)
+            .expect("parse");
+        assert_eq!(s.len(), 0);
+        assert!(process.authed);
+        assert_eq!(process.failed, 2);
+        // 鉴权失败时不切换协议
+        assert!(!process.resp3());
+
+        let (mut out, tx) = stream(b"");
+        let rsps: Vec<String> = process
+            .reqs
+            .0
+            .into_iter()
+            .map(|(req, _)| {
+                redis
+                    .write_response(&mut Ctx::from(req), None, &mut *out)
+                    .expect("write");
+                let written = flushed(&mut out, &tx);
+                tx.lock().unwrap().clear();
+                written
+            })
+            .collect();
+        let noauth = "-NOAUTH Authentication required.\r\n";
+        let wrongpass = "-WRONGPASS invalid username-password pair or user is disabled.\r\n";
+        assert_eq!(
+            rsps,
+            [
+                noauth,
+                "+PONG\r\n",
+                wrongpass,
+                noauth,
+                wrongpass,
+                "+OK\r\n",
+                "-ERR redis no available\r\n"
+            ]
+        );
+    }
 }
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod retry_test {
+    use std::sync::Arc;
+
+    use crate::proto_mock::*;
+    use discovery::TopologyWrite;
+    use ds::AtomicWaker;
+    use endpoint::{msgque::topo::MsgQue, Endpoint, RetryConfig};
+    use protocol::callback::{Callback, CallbackContext, CallbackPtr};
+    use protocol::{Error, Parser, Request, Retry, RetryBudget, RETRY_RSP_NOTOK};
+
+    /// 测试场景：namespace的重试策略配置
+    /// 测试步骤：
+    ///     1.未配置时使用资源的默认次数，所有错误均可重试，不限制预算；
+    ///     2.配置retry_on后，只有对应的错误可以重试，未发送成功的错误始终可以重试。
+    #[test]
+    fn retry_policy() {
+        let cfg = RetryConfig::default();
+        let retry = cfg.build("retry_test", 2);
+        assert_eq!(retry.tries(), 1);
+        assert!(retry.on(RETRY_RSP_NOTOK));
+        assert!(retry.on_err(&Error::Waiting));
+        assert!(retry.budget().is_none());
+
+        let cfg = RetryConfig {
+            max_attempts: 3,
+            retry_on: vec!["chan_full".to_string()],
+            budget_percent: 20,
+        };
+        let retry = cfg.build("retry_test", 2);
+        assert_eq!(retry.tries(), 2);
+        assert!(!retry.on(RETRY_RSP_NOTOK));
+        assert!(!retry.on_err(&Error::Waiting));
+        assert!(retry.on_err(&Error::ChanFull));
+        assert!(retry.on_err(&Error::Pending));
+        assert_eq!(retry.budget().map(|b| b.percent()), Some(20));
+
+        // max_attempts为1时不重试
+        let cfg = RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        };
+        assert_eq!(cfg.build("retry_test", 3).tries(), 0);
+    }
+
+    /// 测试场景：重试预算不超过请求数的percent%，低qps时至少允许10次重试
+    #[test]
+    fn retry_budget() {
+        let budget = RetryBudget::new(10);
+        let allowed = (0..20).filter(|_| budget.acquire()).count();
+        assert_eq!(allowed, 10);
+
+        let budget = RetryBudget::new(10);
+        (0..1000).for_each(|_| budget.on_request());
+        let allowed = (0..200).filter(|_| budget.acquire()).count();
+        assert_eq!(allowed, 100);
+    }
+
+    // 请求一直失败且topo允许继续访问时，返回实际的请求次数
+    fn attempts(retry: Option<Retry>) -> usize {
+        let mock = MockCallback::default();
+        let mut ctx = mock.ctx(mc_cmd(b"get k\r\n"));
+        ctx.send();
+        let mut attempts = 0;
+        while let Some(mut req) = mock.pop() {
+            attempts += 1;
+            if let Some(retry) = retry.as_ref() {
+                req.retry(retry);
+            }
+            req.try_next(true);
+            req.on_err(Error::Waiting);
+        }
+        assert!(ctx.complete());
+        attempts
+    }
+
+    /// 测试场景：未配置重试策略时，与原有行为一致，最多重试一次
+    /// 测试步骤：
+    ///     1.topo未设置重试策略，及使用未配置的namespace策略（各资源默认请求2次）时，失败后只重试一次；
+    ///     2.配置max_attempts后，按配置的次数重试。
+    #[test]
+    fn retry_default() {
+        assert_eq!(Retry::default().tries(), 1);
+        assert_eq!(attempts(None), 2);
+        assert_eq!(
+            attempts(Some(RetryConfig::default().build("retry_test", 2))),
+            2
+        );
+
+        let cfg = RetryConfig {
+            max_attempts: 3,
+            ..Default::default()
+        };
+        assert_eq!(attempts(Some(cfg.build("retry_test", 2))), 3);
+        let cfg = RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        };
+        assert_eq!(attempts(Some(cfg.build("retry_test", 2))), 1);
+    }
+
+    // msgque读写请求一直失败时，依次访问的队列
+    fn msgque_sent(cfg: &str, data: &[u8]) -> Vec<String> {
+        let mut mq = MsgQue::<MockBackend, Parser>::from(Parser::try_from("msgque").expect("mq"));
+        mq.update("retry_test", cfg);
+        let mq = Arc::new(mq);
+        let cb: CallbackPtr = Callback::new(Box::new(move |req| mq.send(req))).into();
+        let waker = Arc::new(AtomicWaker::default());
+        let mut ctx = CallbackContext::new(mc_cmd(data), &waker, cb, true, true, true);
+        ctx.send();
+        assert!(ctx.complete() && ctx.failed());
+        backend_sent()
+    }
+
+    /// 测试场景：msgque的读写按namespace的重试策略重试
+    /// 测试步骤：
+    ///     1.未配置时读写失败后只重试一次；
+    ///     2.配置max_attempts后，读写按配置的次数请求，每次访问不同的队列。
+    #[test]
+    fn retry_msgque() {
+        let que = "que_512: [a:1, b:1, c:1, d:1, e:1, f:1]\n";
+        for data in [&b"get q\r\n"[..], b"set q 0 0 1\r\nv\r\n"] {
+            let sent = msgque_sent(que, data);
+            assert_eq!(sent.len(), 2, "{:?}", sent);
+            assert_ne!(sent[0], sent[1]);
+
+            let cfg = format!("{}retry:\n  max_attempts: 5\n", que);
+            let sent = msgque_sent(&cfg, data);
+            assert_eq!(sent.len(), 5, "{:?}", sent);
+            let mut qs = sent.clone();
+            qs.sort();
+            qs.dedup();
+            assert_eq!(qs.len(), 5, "{:?}", sent);
+        }
+    }
+}
@@ -344,3 +344,18 @@ fn fold() {
     });
     assert_eq!(num, 912345678);
 }
+
+// 密码等的比较，长度不同、内容不同都不相等
+#[test]
+fn ct_eq() {
+    let data = b"abcdefgh".to_vec();
+    let ptr = data.as_ptr() as *mut u8;
+    // 跨越末尾的slice：ghab
+    let slice = RingSlice::from(ptr, data.len(), 6, 10);
+    assert!(slice.ct_eq(b"ghab"));
+    assert!(!slice.ct_eq(b"ghac"));
+    assert!(!slice.ct_eq(b"gha"));
+    assert!(!slice.ct_eq(b"ghab\0"));
+    assert!(!slice.ct_eq(b""));
+    assert!(RingSlice::from(ptr, data.len(), 0, 0).ct_eq(b""));
+}
@@ -1,8 +1,12 @@
 use discovery::distance::Addr;
-use endpoint::{select::Distance, Endpoint};
+use endpoint::{
+    select::{Distance, Selector},
+    Endpoint,
+};
 struct TBackend {
     addr: String,
     available: bool,
+    load: u64,
 }
 
 impl Addr for TBackend {
@@ -17,6 +21,9 @@ impl Endpoint for TBackend {
     fn available(&self) -> bool {
         self.available
     }
+    fn load(&self) -> u64 {
+        self.load
+    }
     fn send(&self, _req: Self::Item) {
         todo!()
     }
@@ -24,7 +31,15 @@ impl Endpoint for TBackend {
 
 impl TBackend {
     fn new(addr: String, available: bool) -> Self {
-        Self { addr, available }
+        Self {
+            addr,
+            available,
+            load: 0,
+        }
+    }
+    fn with_load(mut self, load: u64) -> Self {
+        self.load = load;
+        self
     }
 }
 
@@ -145,3 +160,136 @@ fn select_all_local_all_noava() {
     assert_eq!(shards.select_next_idx(0, 2), 1);
     assert_eq!(shards.select_next_idx(1, 3), 2);
 }
+
+//首次选择时跳过不可用（被熔断摘除）的local实例，全部不可用时仍返回一个local实例
+#[test]
+fn select_skip_noava() {
+    let mut shards = Distance::new();
+    shards.update(
+        vec![
+            TBackend::new("127.0.0.1".to_string(), false),
+            TBackend::new("127.0.0.2".to_string(), true),
+            TBackend::new("127.0.0.3".to_string(), false),
+            TBackend::new("127.0.0.4".to_string(), true),
+        ],
+        3,
+        false,
+    );
+    for _ in 0..4096 {
+        let (idx, b) = shards.unsafe_select();
+        assert_eq!(idx, 1);
+        assert!(b.available);
+    }
+
+    shards.update(
+        vec![
+            TBackend::new("127.0.0.1".to_string(), false),
+            TBackend::new("127.0.0.2".to_string(), false),
+            TBackend::new("127.0.0.3".to_string(), true),
+        ],
+        2,
+        false,
+    );
+    for _ in 0..4096 {
+        assert!(shards.select_idx() < 2);
+    }
+}
+
+//ewma选择local中负载最小的可用实例，不选择非local实例
+#[test]
+fn select_ewma() {
+    let backends = |loads: [(u64, bool); 4]| {
+        loads
+            .iter()
+            .enumerate()
+            .map(|(i, &(load, ava))| {
+                TBackend::new(format!("127.0.0.{}", i + 1), ava).with_load(load)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut shards = Distance::new().with_selector(Selector::Ewma);
+    shards.update(
+        backends([(300, true), (100, true), (200, true), (1, true)]),
+        3,
+        false,
+    );
+    for _ in 0..1024 {
+        assert_eq!(shards.select_idx(), 1);
+    }
+    // 负载最小的实例不可用时，选择次小的
+    shards.update(
+        backends([(300, true), (100, false), (200, true), (1, true)]),
+        3,
+        false,
+    );
+    for _ in 0..1024 {
+        assert_eq!(shards.select_idx(), 2);
+    }
+    // 负载相同时轮询
+    shards.update(
+        backends([(100, true), (100, true), (100, true), (1, true)]),
+        3,
+        false,
+    );
+    let mut hits = [0; 3];
+    (0..3000).for_each(|_| hits[shards.select_idx()] += 1);
+    assert_eq!(hits, [1000; 3]);
+}
+
+//p2c在两个随机的local实例中选择负载较小的可用实例，负载最大的实例不会被选中
+#[test]
+fn select_p2c() {
+    let mut shards = Distance::new().with_selector(Selector::P2c);
+    shards.update(
+        vec![
+            TBackend::new("127.0.0.1".to_string(), true).with_load(300),
+            TBackend::new("127.0.0.2".to_string(), true).with_load(100),
+            TBackend::new("127.0.0.3".to_string(), true).with_load(200),
+            TBackend::new("127.0.0.4".to_string(), true).with_load(1),
+        ],
+        3,
+        false,
+    );
+    let mut hits = [0; 3];
+    (0..3000).for_each(|_| hits[shards.select_idx()] += 1);
+    assert_eq!(hits[0], 0);
+    assert!(hits[1] > hits[2] && hits[2] > 0, "{:?}", hits);
+
+    // 不可用的实例不被选中
+    shards.update(
+        vec![
+            TBackend::new("127.0.0.1".to_string(), true).with_load(300),
+            TBackend::new("127.0.0.2".to_string(), false).with_load(100),
+            TBackend::new("127.0.0.3".to_string(), true).with_load(200),
+        ],
+        3,
+        false,
+    );
+    for _ in 0..1024 {
+        assert_ne!(shards.select_idx(), 1);
+    }
+    assert_eq!(Selector::from("p2c"), Selector::P2c);
+    assert_eq!(Selector::from("ewma"), Selector::Ewma);
+}
+
+//按负载选择时，更新实例后也不使用时间配额
+#[test]
+fn select_quota() {
+    let backends = || {
+        (1..=3)
+            .map(|i| TBackend::new(format!("127.0.0.{}", i), true))
+            .collect::<Vec<_>>()
+    };
+    let mut shards = Distance::new().with_selector(Selector::Ewma);
+    shards.update(backends(), 3, true);
+    assert!(shards.quota().is_none());
+    let mut shards = Distance::new().with_selector(Selector::P2c);
+    shards.update(backends(), 3, true);
+    assert!(shards.quota().is_none());
+
+    let mut shards = Distance::new().with_selector(Selector::ByDistance);
+    shards.update(backends(), 3, true);
+    assert!(shards.quota().is_some());
+    shards.update(backends(), 3, false);
+    assert!(shards.quota().is_none());
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tls_test {
+    use net::{Listener, Stream};
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::{X509NameBuilder, X509};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // 生成127.0.0.1的自签名证书，同时作为CA校验后端证书
+    fn self_signed(dir: &std::path::Path) -> (String, String) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "breeze").unwrap();
+        let name = name.build();
+        let mut x509 = X509::builder().unwrap();
+        x509.set_version(2).unwrap();
+        x509.set_subject_name(&name).unwrap();
+        x509.set_issuer_name(&name).unwrap();
+        x509.set_pubkey(&key).unwrap();
+        x509.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        x509.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        let san = SubjectAlternativeName::new()
+            .ip("127.0.0.1")
+            .build(&x509.x509v3_context(None, None))
+            .unwrap();
+        x509.append_extension(san).unwrap();
+        x509.sign(&key, MessageDigest::sha256()).unwrap();
+        let cert = dir.join("cert.pem");
+        let pkey = dir.join("key.pem");
+        std::fs::write(&cert, x509.build().to_pem().unwrap()).unwrap();
+        std::fs::write(&pkey, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        let path = |p: std::path::PathBuf| p.to_str().unwrap().to_string();
+        (path(cert), path(pkey))
+    }
+
+    /// 测试场景：tls监听及连接后端
+    /// 测试步骤：
+    ///     1.证书与私钥未同时配置、证书不存在时，初始化失败；
+    ///     2.使用自签名证书初始化，监听tls端口；
+    ///     3.以tls连接该端口，收发数据；
+    ///     4.非tls的连接握手失败。
+    #[test]
+    fn tls_listen_and_connect() {
+        assert!(net::tls::init("cert.pem", "", "").is_err());
+        assert!(net::tls::init("/not/exists/cert.pem", "/not/exists/key.pem", "").is_err());
+
+        // 每次运行使用独立的目录，避免并发运行时互相覆盖证书
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let name = format!("breeze_tls_{}_{}", std::process::id(), nanos);
+        let dir = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert, key) = self_signed(&dir);
+        net::tls::init(&cert, &key, &cert).expect("tls init");
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            // 绑定0端口，由系统分配，避免端口冲突
+            let l = Listener::bind("tls", "127.0.0.1:0")
+                .await
+                .expect("bind tls");
+            let Listener::Tls(tls) = &l else {
+                panic!("not tls listener");
+            };
+            let addr = tls.local_addr().expect("local addr").to_string();
+            let target = addr.clone();
+            let client = tokio::spawn(async move {
+                let mut s = net::connect(&target, true).await.expect("connect tls");
+                s.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+                s.flush().await.unwrap();
+                let mut rsp = [0u8; 7];
+                s.read_exact(&mut rsp).await.unwrap();
+                assert_eq!(&rsp, b"+PONG\r\n");
+            });
+
+            let (mut s, _addr) = l.accept().await.expect("accept");
+            assert!(matches!(s, Stream::Tls(_)));
+            let mut buf = [0u8; 14];
+            s.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"*1\r\n$4\r\nPING\r\n");
+            s.write_all(b"+PONG\r\n").await.unwrap();
+            s.flush().await.unwrap();
+            client.await.unwrap();
+
+            // 未使用tls的连接，握手失败后被关闭
+            let mut plain = net::connect(&addr, false).await.expect("connect tcp");
+            let _ = plain.write_all(b"*1\r\n$4\r\nPING\r\n").await;
+            let mut rsp = Vec::new();
+            let _ = plain.read_to_end(&mut rsp).await;
+            assert!(!rsp.starts_with(b"+PONG"));
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}